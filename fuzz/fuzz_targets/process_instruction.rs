@@ -0,0 +1,446 @@
+//! Fuzzes instruction parsing and account validation.
+//!
+//! Each input starts from a valid scenario for one of the program's
+//! instructions, optionally replaces the instruction data, and then applies a
+//! list of account mutations: new owners, resized data, cleared signer or
+//! writable flags, swapped, aliased or dropped accounts. FillSignedOrder's
+//! Ed25519 instruction gets byte patches too, and Resolve an arbitrary ruling
+//! and arbiter fee. The program must never crash the runtime, must not
+//! succeed without the real authorities' signatures, and any success must
+//! conserve tokens and lamports and only pay the parties of the trade. A
+//! filled order must carry the maker's signature whenever the Ed25519 program
+//! would accept the transaction, and the arbiter never earns more than its
+//! fee.
+
+#![no_main]
+
+#[allow(dead_code)]
+#[path = "../../tests/helpers.rs"]
+mod helpers;
+#[allow(dead_code)]
+#[path = "../../tests/scenario.rs"]
+mod scenario;
+
+use {
+    agave_feature_set::FeatureSet,
+    arbitrary::Arbitrary,
+    blueshift_escrow::{fill_signed_order::FillSignedOrderInstructionData, helpers::sha256, Config},
+    helpers::*,
+    libfuzzer_sys::fuzz_target,
+    mollusk_svm::{result::ProgramResult, Mollusk},
+    scenario::{Ata, EscrowScenario, EscrowScenarioBuilder},
+    solana_account::{Account, WritableAccount},
+    solana_instruction::Instruction,
+    solana_keypair::Keypair,
+    solana_pubkey::Pubkey,
+    solana_signer::Signer,
+    spl_token::solana_program::{program_option::COption, program_pack::Pack},
+    std::collections::HashMap,
+};
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Kind {
+    Make,
+    Take,
+    Refund,
+    InitializeConfig,
+    SetPause,
+    SetEnforcement,
+    SetMintPolicy,
+    AddDenylistEntry,
+    RemoveDenylistEntry,
+    CloseReceipt,
+    Claim,
+    FillSignedOrder,
+    CancelAll,
+    Release,
+    Resolve,
+    CancelOrder,
+    CloseOrderNonce,
+}
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Owner {
+    System,
+    Token,
+    Program,
+    Random,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Mutation {
+    ClearSigner(u8),
+    ClearWritable(u8),
+    SetOwner(u8, Owner),
+    ResizeData(u8, u8),
+    SetLamports(u8, u64),
+    Swap(u8, u8),
+    Alias { source: u8, target: u8 },
+    Drop(u8),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    kind: Kind,
+    data: Option<Vec<u8>>,
+    // Resolve's ruling, and the fee of the arbiter making it
+    settle_bps: u16,
+    arbiter_fee_bps: u16,
+    // (index, byte) patches to the Ed25519 instruction before FillSignedOrder
+    signature_patches: Vec<(u16, u8)>,
+    mutations: Vec<Mutation>,
+}
+
+struct Scenario {
+    instruction: Instruction,
+    accounts: HashMap<Pubkey, Account>,
+    // The accounts whose signatures authorize the instruction
+    authorities: Vec<Pubkey>,
+    // Wallets allowed to end up with more tokens than before
+    parties: Vec<Pubkey>,
+    // A wallet's most tokens of a mint it may gain: (owner, mint, amount)
+    gain_cap: Option<(Pubkey, Pubkey, u128)>,
+    // The Ed25519 instruction before FillSignedOrder, and the maker and
+    // order message it must carry a signature of
+    signed_order: Option<(Instruction, Pubkey, Vec<u8>)>,
+}
+
+const NOW: i64 = 1_000;
+const LATER: i64 = 2_000;
+const DEPOSIT: u64 = 1_500;
+const RECEIVE: u64 = 2_000;
+const PREIMAGE: [u8; 32] = [7; 32];
+
+thread_local! {
+    static MOLLUSK: Mollusk = {
+        let elf = |path: &str| std::fs::read(format!("{}/../{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap();
+        let loader = mollusk_svm::program::loader_keys::LOADER_V3;
+        let mut mollusk = Mollusk::default();
+        mollusk.add_program_with_elf_and_loader(&PROGRAM_ID, &elf("target/deploy/blueshift_escrow.so"), &loader);
+        mollusk.add_program_with_elf_and_loader(&spl_token::ID, &elf("tests/elf/token.so"), &loader);
+        mollusk.add_program_with_elf_and_loader(&ATOKEN_PROGRAM_ID, &elf("tests/elf/associated_token.so"), &loader);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+        mollusk
+    };
+}
+
+fn wallet() -> Account {
+    Account::new(10_000_000, 0, &solana_system_program::id())
+}
+
+/// An open escrow of `RECEIVE` for `DEPOSIT`, and the token accounts both
+/// parties trade through.
+fn open_scenario() -> EscrowScenarioBuilder {
+    EscrowScenario::builder()
+        .seed(7)
+        .open(RECEIVE, DEPOSIT)
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::MakerB, 0)
+        .balance(Ata::TakerA, 0)
+        .balance(Ata::TakerB, 3_000)
+}
+
+/// An arbitrated escrow the taker already paid into, with the arbiter's
+/// wallet and token account for mint B.
+fn taken_scenario(arbiter: &Pubkey, arbiter_fee_bps: u16) -> EscrowScenario {
+    let mut scenario = open_scenario().arbiter(*arbiter, arbiter_fee_bps).build();
+    scenario.set_account(*arbiter, wallet());
+    scenario.set_account(derive_associated_token_account(arbiter, &scenario.mint_b), create_token_account(arbiter, &scenario.mint_b, 0));
+    let instruction = with_associated_token_program(scenario.take_into_escrow_instruction());
+    MOLLUSK.with(|mollusk| run(mollusk, &mut scenario, instruction, ProgramResult::Success));
+    scenario
+}
+
+/// The maker's order, signed with `patches` applied to the Ed25519
+/// instruction, with 800 token A approved to the delegate.
+fn order_scenario(patches: &[(u16, u8)]) -> (EscrowScenario, Instruction, Instruction, Vec<u8>) {
+    let maker = Keypair::new_from_array([9; 32]);
+    let mut scenario = EscrowScenario::builder()
+        .maker(maker.pubkey())
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    let maker_ata_a = scenario.ata(Ata::MakerA);
+    let mut account = scenario.accounts[&maker_ata_a].clone();
+    let mut token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+    token_account.delegate = COption::Some(DELEGATE_ADDRESS);
+    token_account.delegated_amount = 800;
+    spl_token::state::Account::pack(token_account, &mut account.data).unwrap();
+    scenario.set_account(maker_ata_a, account);
+
+    let order = FillSignedOrderInstructionData { amount_a: 400, amount_b: 750, expiry: LATER, nonce: 0, maker_nonce: 0 };
+    let message = order.message(&scenario.maker.to_bytes(), &scenario.mint_a.to_bytes(), &scenario.mint_b.to_bytes()).to_vec();
+    let mut signature = create_ed25519_instruction(&maker, &message);
+    for &(index, byte) in patches.iter().take(8) {
+        let len = signature.data.len();
+        signature.data[index as usize % len] = byte;
+    }
+    let instruction = scenario.fill_signed_order_instruction(order.amount_a, order.amount_b, order.expiry, order.nonce, order.maker_nonce);
+    let instruction = with_associated_token_program(instruction);
+    scenario.set_account(solana_instructions_sysvar::ID, create_instructions_sysvar_account(&[&signature, &instruction], 1));
+    (scenario, instruction, signature, message)
+}
+
+/// `maker`'s order nonce 0, spent at maker nonce 0 and paid for by `payer`.
+fn spent_order_nonce_account(maker: &Pubkey, payer: &Pubkey) -> Account {
+    let (_, bump) = derive_order_nonce_pda(maker, 0);
+    let mut data = maker.to_bytes().to_vec();
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(payer.as_ref());
+    data.push(bump);
+    Account::create(1_000_000, data, PROGRAM_ID, false, 0)
+}
+
+fn scenario(input: &Input) -> Scenario {
+    let admin = Pubkey::new_unique();
+    let arbiter = Pubkey::new_unique();
+    let mut gain_cap = None;
+    let mut signed_order = None;
+
+    let (scenario, instruction, authorities, parties) = match input.kind {
+        Kind::Make => {
+            let scenario = EscrowScenario::builder().seed(7).balance(Ata::MakerA, 1_000).balance(Ata::Vault, 0).build();
+            let instruction = with_associated_token_program(scenario.make_instruction(2_000, 500));
+            let (maker, escrow) = (scenario.maker, scenario.escrow);
+            (scenario, instruction, vec![maker], vec![maker, escrow])
+        }
+        Kind::Take => {
+            let scenario = open_scenario().build();
+            let instruction = with_associated_token_program(scenario.take_instruction());
+            let (maker, taker) = (scenario.maker, scenario.taker);
+            (scenario, instruction, vec![taker], vec![maker, taker])
+        }
+        Kind::Refund => {
+            let scenario = open_scenario().build();
+            let instruction = with_associated_token_program(scenario.refund_instruction());
+            let maker = scenario.maker;
+            (scenario, instruction, vec![maker], vec![maker])
+        }
+        Kind::InitializeConfig => {
+            let mut scenario = EscrowScenario::builder().build();
+            let authority = Pubkey::new_unique();
+            scenario.set_account(authority, wallet());
+            let (program_data, account) = create_program_data_account(Some(&authority));
+            scenario.set_account(program_data, account);
+            let instruction = create_initialize_config_instruction(&authority, &admin);
+            (scenario, instruction, vec![authority], vec![])
+        }
+        Kind::SetPause | Kind::SetEnforcement | Kind::SetMintPolicy | Kind::AddDenylistEntry | Kind::RemoveDenylistEntry => {
+            let mut scenario = EscrowScenario::builder().build();
+            scenario.set_account(admin, wallet());
+            scenario.set_account(CONFIG_ADDRESS, create_config_account(&admin, 0, 0));
+            let wallet = scenario.taker;
+            let instruction = match input.kind {
+                Kind::SetPause => create_set_pause_instruction(&admin, Config::PAUSE_TAKE),
+                Kind::SetEnforcement => create_set_enforcement_instruction(&admin, Config::ENFORCE_MINT_POLICY),
+                Kind::SetMintPolicy => create_set_mint_policy_instruction(&admin, &scenario.mint_a, true, 1, 1_000),
+                Kind::AddDenylistEntry => create_add_denylist_entry_instruction(&admin, &wallet),
+                _ => {
+                    scenario.set_account(derive_denylist_pda(&wallet).0, create_denylist_entry_account(&wallet));
+                    create_remove_denylist_entry_instruction(&admin, &wallet)
+                }
+            };
+            (scenario, instruction, vec![admin], vec![])
+        }
+        Kind::CloseReceipt => {
+            let mut scenario = open_scenario().build();
+            let instruction = with_receipt(with_associated_token_program(scenario.take_instruction()), &scenario.escrow);
+            MOLLUSK.with(|mollusk| run(mollusk, &mut scenario, instruction, ProgramResult::Success));
+            let taker = scenario.taker;
+            let instruction = create_close_receipt_instruction(&taker, &taker, &scenario.escrow);
+            (scenario, instruction, vec![taker], vec![])
+        }
+        Kind::Claim => {
+            let scenario = open_scenario().hashlock(sha256(&PREIMAGE), LATER).build();
+            let instruction = with_associated_token_program(scenario.claim_instruction(&PREIMAGE));
+            let taker = scenario.taker;
+            (scenario, instruction, vec![taker], vec![taker])
+        }
+        Kind::FillSignedOrder => {
+            let (scenario, instruction, signature, message) = order_scenario(&input.signature_patches);
+            let (maker, taker) = (scenario.maker, scenario.taker);
+            signed_order = Some((signature, maker, message));
+            (scenario, instruction, vec![taker], vec![maker, taker])
+        }
+        Kind::CancelAll | Kind::CancelOrder => {
+            let scenario = EscrowScenario::builder().build();
+            let maker = scenario.maker;
+            let instruction = match input.kind {
+                Kind::CancelAll => create_cancel_all_instruction(&maker),
+                _ => create_cancel_order_instruction(&maker, 0),
+            };
+            (scenario, instruction, vec![maker], vec![])
+        }
+        Kind::Release => {
+            let scenario = taken_scenario(&arbiter, input.arbiter_fee_bps % 10_001);
+            let instruction = with_associated_token_program(scenario.release_instruction());
+            let (maker, taker) = (scenario.maker, scenario.taker);
+            (scenario, instruction, vec![maker, taker], vec![maker, taker])
+        }
+        Kind::Resolve => {
+            let arbiter_fee_bps = input.arbiter_fee_bps % 10_001;
+            let scenario = taken_scenario(&arbiter, arbiter_fee_bps);
+            let instruction = with_associated_token_program(scenario.resolve_instruction(&arbiter, input.settle_bps));
+            let instruction = with_arbiter_account(instruction, &derive_associated_token_account(&arbiter, &scenario.mint_b));
+            // The fee is a share of the payment the maker gets, at most all of it
+            gain_cap = Some((arbiter, scenario.mint_b, RECEIVE as u128 * arbiter_fee_bps as u128 / 10_000));
+            let (maker, taker) = (scenario.maker, scenario.taker);
+            (scenario, instruction, vec![arbiter], vec![maker, taker, arbiter])
+        }
+        Kind::CloseOrderNonce => {
+            let mut scenario = EscrowScenario::builder().build();
+            let (maker, taker) = (scenario.maker, scenario.taker);
+            scenario.set_account(derive_order_nonce_pda(&maker, 0).0, spent_order_nonce_account(&maker, &taker));
+            scenario.set_account(derive_maker_state_pda(&maker).0, create_maker_state_account(&maker, 1));
+            let instruction = create_close_order_nonce_instruction(&maker, &maker, &taker, 0);
+            (scenario, instruction, vec![maker], vec![])
+        }
+    };
+
+    Scenario {
+        accounts: scenario.accounts_for(&instruction).into_iter().collect(),
+        instruction,
+        authorities,
+        parties,
+        gain_cap,
+        signed_order,
+    }
+}
+
+fn mutate(scenario: &mut Scenario, mutation: &Mutation) {
+    let metas = &mut scenario.instruction.accounts;
+    if metas.is_empty() {
+        return;
+    }
+    let len = metas.len();
+    let index = |i: u8| i as usize % len;
+
+    match *mutation {
+        Mutation::ClearSigner(i) => metas[index(i)].is_signer = false,
+        Mutation::ClearWritable(i) => metas[index(i)].is_writable = false,
+        Mutation::SetOwner(i, owner) => {
+            let key = metas[index(i)].pubkey;
+            let owner = match owner {
+                Owner::System => solana_system_program::id(),
+                Owner::Token => spl_token::ID,
+                Owner::Program => PROGRAM_ID,
+                Owner::Random => Pubkey::new_unique(),
+            };
+            scenario.accounts.entry(key).or_default().owner = owner;
+        }
+        Mutation::ResizeData(i, len) => {
+            let key = metas[index(i)].pubkey;
+            scenario.accounts.entry(key).or_default().data.resize(len as usize, 0);
+        }
+        Mutation::SetLamports(i, lamports) => {
+            let key = metas[index(i)].pubkey;
+            scenario.accounts.entry(key).or_default().lamports = lamports;
+        }
+        Mutation::Swap(a, b) => {
+            let (a, b) = (index(a), index(b));
+            let key = metas[a].pubkey;
+            metas[a].pubkey = metas[b].pubkey;
+            metas[b].pubkey = key;
+        }
+        Mutation::Alias { source, target } => {
+            let source = index(source);
+            metas[index(target)].pubkey = metas[source].pubkey;
+        }
+        Mutation::Drop(i) => {
+            metas.remove(index(i));
+        }
+    }
+}
+
+// Token balances per (mint, wallet) over every token account in the list
+fn token_balances(accounts: &[(Pubkey, Account)]) -> HashMap<(Pubkey, Pubkey), u128> {
+    let mut balances = HashMap::new();
+    for (_, account) in accounts {
+        if account.owner != spl_token::ID || account.lamports == 0 {
+            continue;
+        }
+        if let Ok(token_account) = spl_token::state::Account::unpack(&account.data) {
+            *balances.entry((token_account.mint, token_account.owner)).or_default() += token_account.amount as u128;
+        }
+    }
+    balances
+}
+
+fn total_per_mint(balances: &HashMap<(Pubkey, Pubkey), u128>) -> HashMap<Pubkey, u128> {
+    let mut totals = HashMap::new();
+    for ((mint, _), amount) in balances {
+        *totals.entry(*mint).or_default() += amount;
+    }
+    totals
+}
+
+// The (signer, message) pairs of the Ed25519 instruction's signature entries
+fn ed25519_entries(data: &[u8]) -> Vec<(Pubkey, Vec<u8>)> {
+    let count = data.first().copied().unwrap_or_default() as usize;
+    (0..count)
+        .filter_map(|i| {
+            let offsets = data.get(2 + i * 14..16 + i * 14)?;
+            let field = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]) as usize;
+            let signer = data.get(field(4)..field(4) + 32)?;
+            let message = data.get(field(8)..field(8) + field(10))?;
+            Some((Pubkey::try_from(signer).ok()?, message.to_vec()))
+        })
+        .collect()
+}
+
+fuzz_target!(|input: Input| {
+    let mut scenario = scenario(&input);
+    if let Some(data) = input.data {
+        scenario.instruction.data = data;
+    }
+    for mutation in input.mutations.iter().take(8) {
+        mutate(&mut scenario, mutation);
+    }
+
+    let accounts: Vec<(Pubkey, Account)> = scenario.accounts.iter().map(|(k, a)| (*k, a.clone())).collect();
+    let result = MOLLUSK.with(|mollusk| mollusk.process_instruction(&scenario.instruction, &accounts));
+    if result.program_result.is_err() {
+        return;
+    }
+
+    // Nothing succeeds without every authority's signature
+    for authority in &scenario.authorities {
+        let signed = scenario.instruction.accounts.iter().any(|meta| meta.pubkey == *authority && meta.is_signer);
+        assert!(signed, "succeeded without {authority}'s signature: {:?}", input.mutations);
+    }
+
+    // A filled order carries the maker's signature of it whenever the Ed25519
+    // program would have let the transaction through
+    if let Some((signature, maker, message)) = &scenario.signed_order {
+        if agave_precompiles::ed25519::verify(&signature.data, &[&signature.data], &FeatureSet::all_enabled()).is_ok() {
+            let entries = ed25519_entries(&signature.data);
+            assert!(entries.contains(&(*maker, message.clone())), "filled an order the maker never signed: {:?}", input.signature_patches);
+        }
+    }
+
+    // Lamports are only moved, never created or destroyed
+    let lamports = |accounts: &[(Pubkey, Account)]| accounts.iter().map(|(_, a)| a.lamports as u128).sum::<u128>();
+    let touched: Vec<Pubkey> = result.resulting_accounts.iter().map(|(key, _)| *key).collect();
+    let before: Vec<(Pubkey, Account)> = accounts.iter().filter(|(key, _)| touched.contains(key)).cloned().collect();
+    assert_eq!(lamports(&before), lamports(&result.resulting_accounts), "lamports not conserved");
+
+    // Tokens are conserved per mint and only flow to the expected parties
+    let balances_before = token_balances(&before);
+    let balances_after = token_balances(&result.resulting_accounts);
+    assert_eq!(total_per_mint(&balances_before), total_per_mint(&balances_after), "tokens not conserved");
+    for ((mint, owner), amount) in &balances_after {
+        let previous = balances_before.get(&(*mint, *owner)).copied().unwrap_or_default();
+        assert!(
+            *amount <= previous || scenario.parties.contains(owner),
+            "{owner} gained {} of {mint}",
+            amount - previous
+        );
+        // The arbiter's fee is its whole share, whatever the ruling
+        if let Some((capped, capped_mint, cap)) = scenario.gain_cap {
+            if (capped, capped_mint) == (*owner, *mint) {
+                assert!(amount.saturating_sub(previous) <= cap, "arbiter took {} of a {cap} fee", amount - previous);
+            }
+        }
+    }
+});
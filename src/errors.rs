@@ -55,4 +55,287 @@ impl ToStr for PinocchioError {
             PinocchioError::InvalidAddress => "Error: Account Address is invalid",
         }
     }
-}
\ No newline at end of file
+}
+
+/// Errors specific to the escrow. Their codes are reserved in
+/// `EscrowError::FIRST_CODE..=EscrowError::LAST_CODE`, clear of
+/// `PinocchioError` and the token programs' error codes, so a client can tell
+/// from the code alone which failure it got.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+#[repr(u32)]
+pub enum EscrowError {
+    // 6000
+    /// Make was asked to deposit zero tokens.
+    #[error("Deposit amount must be greater than zero")]
+    ZeroDeposit = 6000,
+    /// An escrow with this maker and seed already exists.
+    #[error("An escrow with this seed already exists")]
+    SeedCollision,
+    /// The escrow account isn't the PDA of its maker, seed and bump.
+    #[error("Escrow account does not match its PDA")]
+    EscrowPdaMismatch,
+    /// The mints passed don't match the ones stored on the escrow.
+    #[error("Mint does not match the escrow")]
+    MintMismatch,
+    /// The maker passed doesn't match the one stored on the escrow.
+    #[error("Maker does not match the escrow")]
+    MakerMismatch,
+    /// The vault doesn't hold the escrowed deposit.
+    #[error("Vault does not hold the escrowed deposit")]
+    VaultBalanceMismatch,
+    /// The token program is neither Token nor Token-2022.
+    #[error("Token program is not supported")]
+    UnsupportedTokenProgram,
+    /// A Token-2022 mint extension puts the escrowed tokens at risk and the
+    /// maker didn't accept it.
+    #[error("Mint has a Token-2022 extension that puts escrowed tokens at risk")]
+    UnsafeMintExtension,
+    /// A destination requires memos, but the Memo program wasn't passed.
+    #[error("Destination requires a memo but no Memo program was passed")]
+    MemoProgramMissing,
+    /// CPI Guard stops the escrow from moving tokens out of the account.
+    #[error("CPI Guard blocks the escrow from transferring out of this account")]
+    CpiGuardEnabled,
+    /// The account passed as the config isn't the config PDA.
+    #[error("Config account does not match its PDA")]
+    ConfigMismatch,
+    /// The admin has paused this instruction.
+    #[error("Instruction is paused")]
+    InstructionPaused,
+    /// The signer isn't the admin, the upgrade authority when initializing
+    /// the config, or the escrow's arbiter on Resolve.
+    #[error("Signer is not allowed to administer the escrow")]
+    Unauthorized,
+    /// Mint policies are enforced, but a mint's policy account wasn't passed.
+    #[error("Mint policy account is missing")]
+    MintPolicyMissing,
+    /// The account passed as a mint's policy isn't its policy PDA.
+    #[error("Mint policy account does not match its PDA")]
+    MintPolicyMismatch,
+    /// The mint is blocked, or has no policy while one is required.
+    #[error("Mint is not allowed")]
+    MintNotAllowed,
+    /// The amount of a mint is outside its policy's limits.
+    #[error("Amount is outside the mint policy's limits")]
+    AmountOutOfRange,
+    /// The account passed as a wallet's denylist entry isn't its PDA.
+    #[error("Denylist entry does not match its PDA")]
+    DenylistEntryMismatch,
+    /// The admin denied the maker or taker.
+    #[error("Wallet is denied")]
+    WalletDenied,
+    /// Take's referral fee is above the maximum the maker allowed.
+    #[error("Referral fee exceeds the maker's maximum")]
+    ReferralFeeTooHigh,
+    /// Take sets a referral fee without passing the referrer's token account.
+    #[error("Referrer token account is missing")]
+    ReferrerMissing,
+    /// The account passed as an escrow's receipt isn't its receipt PDA.
+    #[error("Receipt account does not match its PDA")]
+    ReceiptMismatch,
+    /// Take asks for a receipt without passing the receipt account.
+    #[error("Receipt account is missing")]
+    ReceiptMissing,
+    /// Take was called on a hashlocked escrow, which only Claim settles.
+    #[error("Escrow is hashlocked and settles through Claim")]
+    HashlockedEscrow,
+    /// Claim was called on an escrow without a hashlock.
+    #[error("Escrow has no hashlock")]
+    NotHashlocked,
+    /// Claim's preimage doesn't hash to the escrow's hashlock.
+    #[error("Preimage does not match the hashlock")]
+    PreimageMismatch,
+    /// The hashlock's timeout has passed, so only Refund can settle it.
+    #[error("Hashlock timeout has passed")]
+    HashlockExpired,
+    /// Refund was called on a hashlocked escrow before its timeout.
+    #[error("Hashlock timeout has not passed yet")]
+    HashlockNotExpired,
+    /// Take found no Ed25519 instruction in which the escrow's authorizer
+    /// signed the release to this taker.
+    #[error("Authorizer's signed release is missing")]
+    AuthorizationMissing,
+    /// The authorizer's signed release has expired.
+    #[error("Authorizer's signed release has expired")]
+    AuthorizationExpired,
+    /// The order nonce account isn't the PDA of the maker.
+    #[error("Order nonce account does not match its PDA")]
+    OrderNonceMismatch,
+    /// FillSignedOrder found no Ed25519 instruction in which the maker signed
+    /// the order.
+    #[error("Maker's signed order is missing")]
+    OrderSignatureMissing,
+    /// The signed order has expired.
+    #[error("Order has expired")]
+    OrderExpired,
+    /// The order's nonce is spent: an order with it was filled already, or
+    /// the maker cancelled it.
+    #[error("Order nonce was already filled or cancelled")]
+    OrderNonceInvalid,
+    /// The maker state account isn't the PDA of the maker.
+    #[error("Maker state account does not match its PDA")]
+    MakerStateMismatch,
+    /// The maker cancelled the escrow with CancelAll. Refund still works.
+    #[error("Escrow was cancelled by its maker")]
+    EscrowCancelled,
+    /// Release or Resolve was called on an escrow without an arbiter.
+    #[error("Escrow has no arbiter")]
+    NotArbitrated,
+    /// Release or Resolve was called before Take put the taker's payment in
+    /// the escrow.
+    #[error("Escrow has not been taken yet")]
+    EscrowNotTaken,
+    /// Take or Refund was called on an arbitrated escrow that holds the
+    /// taker's payment, which only Release or Resolve settles.
+    #[error("Escrow was taken and awaits release or resolution")]
+    EscrowAlreadyTaken,
+    /// Take on an arbitrated escrow without passing the payment vault.
+    #[error("Payment vault account is missing")]
+    PaymentVaultMissing,
+    /// Resolve pays an arbiter fee without the arbiter's token account.
+    #[error("Arbiter token account is missing")]
+    ArbiterAccountMissing,
+    /// The order was signed at an earlier maker nonce, which CancelAll moved
+    /// past.
+    #[error("Order was cancelled by its maker")]
+    OrderCancelled,
+    /// Take names a token account of the taker as the referrer's.
+    #[error("Taker can't be its own referrer")]
+    SelfReferral,
+    /// CloseOrderNonce was asked to close a nonce spent at the maker's
+    /// current nonce, whose orders could still fill without it.
+    #[error("Order nonce still guards the maker's current orders")]
+    OrderNonceLive,
+}
+
+impl EscrowError {
+    pub const FIRST_CODE: u32 = 6000;
+    pub const LAST_CODE: u32 = 6099;
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl TryFrom<u32> for EscrowError {
+    type Error = ProgramError;
+    fn try_from(error: u32) -> Result<Self, Self::Error> {
+        match error {
+            6000 => Ok(EscrowError::ZeroDeposit),
+            6001 => Ok(EscrowError::SeedCollision),
+            6002 => Ok(EscrowError::EscrowPdaMismatch),
+            6003 => Ok(EscrowError::MintMismatch),
+            6004 => Ok(EscrowError::MakerMismatch),
+            6005 => Ok(EscrowError::VaultBalanceMismatch),
+            6006 => Ok(EscrowError::UnsupportedTokenProgram),
+            6007 => Ok(EscrowError::UnsafeMintExtension),
+            6008 => Ok(EscrowError::MemoProgramMissing),
+            6009 => Ok(EscrowError::CpiGuardEnabled),
+            6010 => Ok(EscrowError::ConfigMismatch),
+            6011 => Ok(EscrowError::InstructionPaused),
+            6012 => Ok(EscrowError::Unauthorized),
+            6013 => Ok(EscrowError::MintPolicyMissing),
+            6014 => Ok(EscrowError::MintPolicyMismatch),
+            6015 => Ok(EscrowError::MintNotAllowed),
+            6016 => Ok(EscrowError::AmountOutOfRange),
+            6017 => Ok(EscrowError::DenylistEntryMismatch),
+            6018 => Ok(EscrowError::WalletDenied),
+            6019 => Ok(EscrowError::ReferralFeeTooHigh),
+            6020 => Ok(EscrowError::ReferrerMissing),
+            6021 => Ok(EscrowError::ReceiptMismatch),
+            6022 => Ok(EscrowError::ReceiptMissing),
+            6023 => Ok(EscrowError::HashlockedEscrow),
+            6024 => Ok(EscrowError::NotHashlocked),
+            6025 => Ok(EscrowError::PreimageMismatch),
+            6026 => Ok(EscrowError::HashlockExpired),
+            6027 => Ok(EscrowError::HashlockNotExpired),
+            6028 => Ok(EscrowError::AuthorizationMissing),
+            6029 => Ok(EscrowError::AuthorizationExpired),
+            6030 => Ok(EscrowError::OrderNonceMismatch),
+            6031 => Ok(EscrowError::OrderSignatureMissing),
+            6032 => Ok(EscrowError::OrderExpired),
+            6033 => Ok(EscrowError::OrderNonceInvalid),
+            6034 => Ok(EscrowError::MakerStateMismatch),
+            6035 => Ok(EscrowError::EscrowCancelled),
+            6036 => Ok(EscrowError::NotArbitrated),
+            6037 => Ok(EscrowError::EscrowNotTaken),
+            6038 => Ok(EscrowError::EscrowAlreadyTaken),
+            6039 => Ok(EscrowError::PaymentVaultMissing),
+            6040 => Ok(EscrowError::ArbiterAccountMissing),
+            6041 => Ok(EscrowError::OrderCancelled),
+            6042 => Ok(EscrowError::SelfReferral),
+            6043 => Ok(EscrowError::OrderNonceLive),
+            _ => Err(ProgramError::InvalidArgument),
+        }
+    }
+}
+
+impl ToStr for EscrowError {
+    fn to_str<E>(&self) -> &'static str {
+        match self {
+            EscrowError::ZeroDeposit => "Error: Deposit amount must be greater than zero",
+            EscrowError::SeedCollision => "Error: An escrow with this seed already exists",
+            EscrowError::EscrowPdaMismatch => "Error: Escrow account does not match its PDA",
+            EscrowError::MintMismatch => "Error: Mint does not match the escrow",
+            EscrowError::MakerMismatch => "Error: Maker does not match the escrow",
+            EscrowError::VaultBalanceMismatch => "Error: Vault does not hold the escrowed deposit",
+            EscrowError::UnsupportedTokenProgram => "Error: Token program is not supported",
+            EscrowError::UnsafeMintExtension => "Error: Mint has a Token-2022 extension that puts escrowed tokens at risk",
+            EscrowError::MemoProgramMissing => "Error: Destination requires a memo but no Memo program was passed",
+            EscrowError::CpiGuardEnabled => "Error: CPI Guard blocks the escrow from transferring out of this account",
+            EscrowError::ConfigMismatch => "Error: Config account does not match its PDA",
+            EscrowError::InstructionPaused => "Error: Instruction is paused",
+            EscrowError::Unauthorized => "Error: Signer is not allowed to administer the escrow",
+            EscrowError::MintPolicyMissing => "Error: Mint policy account is missing",
+            EscrowError::MintPolicyMismatch => "Error: Mint policy account does not match its PDA",
+            EscrowError::MintNotAllowed => "Error: Mint is not allowed",
+            EscrowError::AmountOutOfRange => "Error: Amount is outside the mint policy's limits",
+            EscrowError::DenylistEntryMismatch => "Error: Denylist entry does not match its PDA",
+            EscrowError::WalletDenied => "Error: Wallet is denied",
+            EscrowError::ReferralFeeTooHigh => "Error: Referral fee exceeds the maker's maximum",
+            EscrowError::ReferrerMissing => "Error: Referrer token account is missing",
+            EscrowError::ReceiptMismatch => "Error: Receipt account does not match its PDA",
+            EscrowError::ReceiptMissing => "Error: Receipt account is missing",
+            EscrowError::HashlockedEscrow => "Error: Escrow is hashlocked and settles through Claim",
+            EscrowError::NotHashlocked => "Error: Escrow has no hashlock",
+            EscrowError::PreimageMismatch => "Error: Preimage does not match the hashlock",
+            EscrowError::HashlockExpired => "Error: Hashlock timeout has passed",
+            EscrowError::HashlockNotExpired => "Error: Hashlock timeout has not passed yet",
+            EscrowError::AuthorizationMissing => "Error: Authorizer's signed release is missing",
+            EscrowError::AuthorizationExpired => "Error: Authorizer's signed release has expired",
+            EscrowError::OrderNonceMismatch => "Error: Order nonce account does not match its PDA",
+            EscrowError::OrderSignatureMissing => "Error: Maker's signed order is missing",
+            EscrowError::OrderExpired => "Error: Order has expired",
+            EscrowError::OrderNonceInvalid => "Error: Order nonce was already filled or cancelled",
+            EscrowError::MakerStateMismatch => "Error: Maker state account does not match its PDA",
+            EscrowError::EscrowCancelled => "Error: Escrow was cancelled by its maker",
+            EscrowError::NotArbitrated => "Error: Escrow has no arbiter",
+            EscrowError::EscrowNotTaken => "Error: Escrow has not been taken yet",
+            EscrowError::EscrowAlreadyTaken => "Error: Escrow was taken and awaits release or resolution",
+            EscrowError::PaymentVaultMissing => "Error: Payment vault account is missing",
+            EscrowError::ArbiterAccountMissing => "Error: Arbiter token account is missing",
+            EscrowError::OrderCancelled => "Error: Order was cancelled by its maker",
+            EscrowError::SelfReferral => "Error: Taker can't be its own referrer",
+            EscrowError::OrderNonceLive => "Error: Order nonce still guards the maker's current orders",
+        }
+    }
+}
+
+/// Describes a custom error code returned by the program, for clients that
+/// only see the `Custom(code)` of a failed transaction. Returns `None` for
+/// codes the program doesn't define.
+///
+/// Codes 0 to 5 are ambiguous: they are `PinocchioError`s here, but the
+/// token programs, the associated token program and the system program
+/// return the same codes for their own errors, which a failed CPI passes
+/// through unchanged. Those are described as the `PinocchioError`, so check
+/// the transaction logs for which program failed before relying on them.
+#[cfg(not(target_os = "solana"))]
+pub fn decode_error(code: u32) -> Option<&'static str> {
+    if let Ok(error) = EscrowError::try_from(code) {
+        return Some(error.to_str::<EscrowError>());
+    }
+    PinocchioError::try_from(code).ok().map(|error| error.to_str::<PinocchioError>())
+}
@@ -0,0 +1,169 @@
+//! Events logged with `sol_log_data`, so indexers can follow escrows without
+//! reading accounts that Take and Refund close. Each event is one
+//! `Program data:` line: the event name, then every field as its own
+//! little-endian slice, in declaration order.
+
+use pinocchio::{log::sol_log_data, pubkey::Pubkey};
+
+/// Make opened an escrow.
+pub struct MakeEvent<'a> {
+  pub escrow: &'a Pubkey,
+  pub maker: &'a Pubkey,
+  pub seed: u64,
+  pub amount: u64,
+  pub receive: u64,
+  pub terms_hash: &'a [u8; 32],
+  pub external_ref: &'a [u8; 16],
+}
+
+impl MakeEvent<'_> {
+  pub const NAME: &'static [u8] = b"make";
+
+  pub fn emit(&self) {
+    sol_log_data(&[
+      Self::NAME,
+      self.escrow,
+      self.maker,
+      &self.seed.to_le_bytes(),
+      &self.amount.to_le_bytes(),
+      &self.receive.to_le_bytes(),
+      self.terms_hash,
+      self.external_ref,
+    ]);
+  }
+}
+
+/// Take settled an escrow, splitting `amount_b` between the maker and the
+/// referrer. On an arbitrated escrow the maker's part waits in the payment
+/// vault instead.
+pub struct TakeEvent<'a> {
+  pub escrow: &'a Pubkey,
+  pub maker: &'a Pubkey,
+  pub taker: &'a Pubkey,
+  pub amount_a: u64,
+  pub amount_b: u64,
+  pub referral_fee: u64,
+  pub terms_hash: &'a [u8; 32],
+  pub external_ref: &'a [u8; 16],
+}
+
+impl TakeEvent<'_> {
+  pub const NAME: &'static [u8] = b"take";
+
+  pub fn emit(&self) {
+    sol_log_data(&[
+      Self::NAME,
+      self.escrow,
+      self.maker,
+      self.taker,
+      &self.amount_a.to_le_bytes(),
+      &self.amount_b.to_le_bytes(),
+      &self.referral_fee.to_le_bytes(),
+      self.terms_hash,
+      self.external_ref,
+    ]);
+  }
+}
+
+/// Claim paid out a hashlocked escrow, revealing the preimage.
+pub struct ClaimEvent<'a> {
+  pub escrow: &'a Pubkey,
+  pub recipient: &'a Pubkey,
+  pub amount: u64,
+  pub preimage: &'a [u8; 32],
+}
+
+impl ClaimEvent<'_> {
+  pub const NAME: &'static [u8] = b"claim";
+
+  pub fn emit(&self) {
+    sol_log_data(&[
+      Self::NAME,
+      self.escrow,
+      self.recipient,
+      &self.amount.to_le_bytes(),
+      self.preimage,
+    ]);
+  }
+}
+
+/// FillSignedOrder settled a maker's signed order.
+pub struct FillEvent<'a> {
+  pub maker: &'a Pubkey,
+  pub taker: &'a Pubkey,
+  pub mint_a: &'a Pubkey,
+  pub mint_b: &'a Pubkey,
+  pub amount_a: u64,
+  pub amount_b: u64,
+  pub nonce: u64,
+}
+
+impl FillEvent<'_> {
+  pub const NAME: &'static [u8] = b"fill";
+
+  pub fn emit(&self) {
+    sol_log_data(&[
+      Self::NAME,
+      self.maker,
+      self.taker,
+      self.mint_a,
+      self.mint_b,
+      &self.amount_a.to_le_bytes(),
+      &self.amount_b.to_le_bytes(),
+      &self.nonce.to_le_bytes(),
+    ]);
+  }
+}
+
+/// The maker and the taker released an arbitrated escrow, completing the
+/// trade.
+pub struct ReleaseEvent<'a> {
+  pub escrow: &'a Pubkey,
+  pub maker: &'a Pubkey,
+  pub taker: &'a Pubkey,
+  pub amount_a: u64,
+  pub amount_b: u64,
+}
+
+impl ReleaseEvent<'_> {
+  pub const NAME: &'static [u8] = b"release";
+
+  pub fn emit(&self) {
+    sol_log_data(&[
+      Self::NAME,
+      self.escrow,
+      self.maker,
+      self.taker,
+      &self.amount_a.to_le_bytes(),
+      &self.amount_b.to_le_bytes(),
+    ]);
+  }
+}
+
+/// The arbiter resolved an arbitrated escrow, completing `settle_bps` of the
+/// trade: `amount_a` of the deposit went to the taker and `amount_b` of the
+/// payment to the maker, the rest back to each after the arbiter's fee.
+pub struct ResolveEvent<'a> {
+  pub escrow: &'a Pubkey,
+  pub arbiter: &'a Pubkey,
+  pub settle_bps: u16,
+  pub amount_a: u64,
+  pub amount_b: u64,
+  pub arbiter_fee: u64,
+}
+
+impl ResolveEvent<'_> {
+  pub const NAME: &'static [u8] = b"resolve";
+
+  pub fn emit(&self) {
+    sol_log_data(&[
+      Self::NAME,
+      self.escrow,
+      self.arbiter,
+      &self.settle_bps.to_le_bytes(),
+      &self.amount_a.to_le_bytes(),
+      &self.amount_b.to_le_bytes(),
+      &self.arbiter_fee.to_le_bytes(),
+    ]);
+  }
+}
@@ -0,0 +1,94 @@
+use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{helpers::{AccountCheck, ConfigAccount, DenylistEntryAccount, ProgramAccount, ProgramAccountInit, SignerAccount}, DenylistEntry};
+
+pub struct AddDenylistEntryAccounts<'a> {
+  pub admin: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub denylist_entry: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for AddDenylistEntryAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [admin, config, denylist_entry, system_program] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(admin)?;
+    ConfigAccount::check_admin(config, admin)?;
+
+    Ok(Self {
+      admin,
+      config,
+      denylist_entry,
+      system_program,
+    })
+  }
+}
+
+pub struct DenylistEntryInstructionData {
+  pub wallet: Pubkey,
+}
+
+impl<'a> TryFrom<&'a [u8]> for DenylistEntryInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let wallet: Pubkey = data.try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    Ok(Self { wallet })
+  }
+}
+
+pub struct AddDenylistEntry<'a> {
+  pub accounts: AddDenylistEntryAccounts<'a>,
+  pub instruction_data: DenylistEntryInstructionData,
+  pub bump: u8,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for AddDenylistEntry<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = AddDenylistEntryAccounts::try_from(accounts)?;
+    let instruction_data = DenylistEntryInstructionData::try_from(data)?;
+    let bump = DenylistEntryAccount::check(accounts.denylist_entry, &instruction_data.wallet)?;
+
+    let bump_binding = [bump];
+    let entry_seeds = [
+      Seed::from(DenylistEntry::SEED),
+      Seed::from(instruction_data.wallet.as_ref()),
+      Seed::from(&bump_binding),
+    ];
+
+    // Fails if the wallet is already denied
+    ProgramAccount::init::<DenylistEntry>(
+      accounts.admin,
+      accounts.denylist_entry,
+      &entry_seeds,
+      DenylistEntry::LEN
+    )?;
+    log!("Denylist entry initialized");
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+      bump,
+    })
+  }
+}
+
+impl<'a> AddDenylistEntry<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &7;
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.denylist_entry.try_borrow_mut_data()?;
+    DenylistEntry::load_mut(data.as_mut())?.set_inner(self.instruction_data.wallet, [self.bump]);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,78 @@
+use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{helpers::{AccountCheck, MakerStateAccount, ProgramAccount, ProgramAccountInit, SignerAccount}, MakerState};
+
+pub struct CancelAllAccounts<'a> {
+  pub maker: &'a AccountInfo,
+  pub maker_state: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CancelAllAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [maker, maker_state, system_program] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(maker)?;
+
+    Ok(Self {
+      maker,
+      maker_state,
+      system_program,
+    })
+  }
+}
+
+pub struct CancelAll<'a> {
+  pub accounts: CancelAllAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CancelAll<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let accounts = CancelAllAccounts::try_from(accounts)?;
+
+    // The maker's first CancelAll creates the state, at the nonce zero every
+    // escrow so far was made at
+    let bump = MakerStateAccount::check(accounts.maker_state, accounts.maker)?;
+    if accounts.maker_state.data_is_empty() {
+      let bump_binding = [bump];
+      let maker_state_seeds = [
+        Seed::from(MakerState::SEED),
+        Seed::from(accounts.maker.key().as_ref()),
+        Seed::from(&bump_binding),
+      ];
+      ProgramAccount::init::<MakerState>(
+        accounts.maker,
+        accounts.maker_state,
+        &maker_state_seeds,
+        MakerState::LEN
+      )?;
+      let mut data = accounts.maker_state.try_borrow_mut_data()?;
+      MakerState::load_mut(data.as_mut())?.set_inner(*accounts.maker.key(), 0, bump_binding);
+    } else {
+      ProgramAccount::check(accounts.maker_state)?;
+    }
+
+    Ok(Self { accounts })
+  }
+}
+
+impl<'a> CancelAll<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &12;
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.maker_state.try_borrow_mut_data()?;
+    let maker_state = MakerState::load_mut(data.as_mut())?;
+    maker_state.advance();
+    let nonce = maker_state.nonce;
+    log!("CancelAll: maker nonce is now {}", nonce);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,81 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::helpers::{AccountCheck, MakerStateAccount, OrderNonceAccount, SignerAccount};
+
+pub struct CancelOrderAccounts<'a> {
+  pub maker: &'a AccountInfo,
+  pub order_nonce: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+  pub maker_state: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CancelOrderAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [maker, order_nonce, system_program, maker_state] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(maker)?;
+
+    Ok(Self {
+      maker,
+      order_nonce,
+      system_program,
+      maker_state,
+    })
+  }
+}
+
+pub struct CancelOrderInstructionData {
+  /// Nonce of the signed orders to cancel
+  pub nonce: u64,
+}
+
+impl<'a> TryFrom<&'a [u8]> for CancelOrderInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let nonce = u64::from_le_bytes(data.try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+
+    Ok(Self { nonce })
+  }
+}
+
+pub struct CancelOrder<'a> {
+  pub accounts: CancelOrderAccounts<'a>,
+  pub instruction_data: CancelOrderInstructionData,
+  pub order_nonce_bump: u8,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for CancelOrder<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = CancelOrderAccounts::try_from(accounts)?;
+    let instruction_data = CancelOrderInstructionData::try_from(data)?;
+    let order_nonce_bump = OrderNonceAccount::check_unspent(accounts.order_nonce, accounts.maker, instruction_data.nonce)?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+      order_nonce_bump,
+    })
+  }
+}
+
+impl<'a> CancelOrder<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &15;
+
+  pub fn process(&mut self) -> ProgramResult {
+    // Spending the nonce voids every order signed with it, as a fill would
+    let nonce = self.instruction_data.nonce;
+    let maker_nonce = MakerStateAccount::nonce(self.accounts.maker_state, self.accounts.maker)?;
+    OrderNonceAccount::spend(self.accounts.maker, self.accounts.order_nonce, self.accounts.maker, nonce, maker_nonce, self.order_nonce_bump)?;
+    log!("CancelOrder: orders with nonce {} cancelled", nonce);
+
+    Ok(())
+  }
+}
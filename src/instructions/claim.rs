@@ -0,0 +1,188 @@
+use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::create_program_address, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+use pinocchio_log::log;
+use pinocchio_token_2022::instructions::{CloseAccount, TransferChecked};
+
+use crate::{errors::{EscrowError, PinocchioError}, events::ClaimEvent, helpers::{sha256, AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, ConfigAccount, DenylistEntryAccount, MintInterface, ProgramAccount, SignerAccount, TokenAccountInterface, TokenProgram}, Config, Escrow};
+
+pub struct ClaimAccounts<'a> {
+  pub payer: &'a AccountInfo,
+  pub recipient: &'a AccountInfo,
+  pub maker: &'a AccountInfo,
+  pub escrow: &'a AccountInfo,
+  pub mint_a: &'a AccountInfo,
+  pub vault: &'a AccountInfo,
+  pub recipient_ata_a: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+  pub token_program: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub recipient_denylist_entry: &'a AccountInfo,
+  /// Only needed when `recipient_ata_a` requires memos
+  pub memo_program: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ClaimAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [payer, recipient, maker, escrow, mint_a, vault, recipient_ata_a, system_program, token_program, _, config, recipient_denylist_entry, remaining @ ..] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Basic Accounts Checks. Anyone holding the preimage may submit the
+    // claim, the deposit still only goes to the recipient. Claim settles a
+    // trade as Take does, so it follows Take's pause flag.
+    SignerAccount::check(payer)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_TAKE)?;
+    DenylistEntryAccount::check_not_denied(recipient_denylist_entry, recipient)?;
+    ProgramAccount::check(escrow)?;
+    MintInterface::check(mint_a)?;
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    // recipient_ata_a may not exist yet, so only its address is checked here
+    AssociatedTokenAccount::check_address(recipient_ata_a, recipient, mint_a, token_program)?;
+    AssociatedTokenAccount::check(vault, escrow, mint_a, token_program)?;
+
+    Ok(Self {
+      payer,
+      recipient,
+      maker,
+      escrow,
+      mint_a,
+      vault,
+      recipient_ata_a,
+      system_program,
+      token_program,
+      config,
+      recipient_denylist_entry,
+      memo_program: remaining.first(),
+    })
+  }
+}
+
+pub struct ClaimInstructionData {
+  pub preimage: [u8; 32],
+}
+
+impl<'a> TryFrom<&'a [u8]> for ClaimInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let preimage = data.try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    Ok(Self { preimage })
+  }
+}
+
+pub struct Claim<'a> {
+  pub accounts: ClaimAccounts<'a>,
+  pub instruction_data: ClaimInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Claim<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = ClaimAccounts::try_from(accounts)?;
+    let instruction_data = ClaimInstructionData::try_from(data)?;
+
+    AssociatedTokenAccount::init_if_needed(
+      accounts.recipient_ata_a,
+      accounts.mint_a,
+      accounts.payer,
+      accounts.recipient,
+      accounts.system_program,
+      accounts.token_program,
+    )?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+    })
+  }
+}
+
+impl<'a> Claim<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &10;
+  pub const MEMO: &'a [u8] = b"Escrow claim";
+
+  pub fn process(&mut self) -> ProgramResult {
+    log!("Claim: process started");
+    let data = self.accounts.escrow.try_borrow_data()?;
+    let escrow = Escrow::read(&data)?;
+
+    // Check the escrow belongs to this maker, holds this mint and pays this recipient
+    if escrow.maker.ne(self.accounts.maker.key()) {
+      return Err(EscrowError::MakerMismatch.into());
+    }
+    if escrow.mint_a.ne(self.accounts.mint_a.key()) {
+      return Err(EscrowError::MintMismatch.into());
+    }
+    if !escrow.is_hashlocked() {
+      return Err(EscrowError::NotHashlocked.into());
+    }
+    if escrow.recipient.ne(self.accounts.recipient.key()) {
+      return Err(PinocchioError::InvalidAddress.into());
+    }
+
+    // Check if the escrow is valid
+    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)
+      .map_err(|_| EscrowError::EscrowPdaMismatch)?;
+    if &escrow_key != self.accounts.escrow.key() {
+      return Err(EscrowError::EscrowPdaMismatch.into());
+    }
+
+    // From the timeout on the maker's refund wins, so the two never race
+    if Clock::get()?.unix_timestamp >= escrow.timeout {
+      return Err(EscrowError::HashlockExpired.into());
+    }
+    if sha256(&self.instruction_data.preimage) != escrow.hashlock {
+      return Err(EscrowError::PreimageMismatch.into());
+    }
+
+    let seed_binding = escrow.seed.to_le_bytes();
+    let bump_binding = escrow.bump;
+    let escrow_seeds = [
+      Seed::from(b"escrow"),
+      Seed::from(self.accounts.maker.key().as_ref()),
+      Seed::from(&seed_binding),
+      Seed::from(&bump_binding),
+    ];
+    let signer = Signer::from(&escrow_seeds);
+
+    // Transfer from the Vault to the Recipient
+    let amount = TokenAccountInterface::amount(self.accounts.vault)?;
+    TokenAccountInterface::memo_if_required(self.accounts.recipient_ata_a, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
+      from: self.accounts.vault,
+      mint: self.accounts.mint_a,
+      to: self.accounts.recipient_ata_a,
+      authority: self.accounts.escrow,
+      amount,
+      decimals: MintInterface::decimals(self.accounts.mint_a)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    // Close the Vault, once any transfer fees withheld on it are harvested
+    TokenAccountInterface::harvest_withheld_fees(self.accounts.vault, self.accounts.mint_a, self.accounts.token_program)?;
+    CloseAccount {
+      account: self.accounts.vault,
+      destination: self.accounts.maker,
+      authority: self.accounts.escrow,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    // Publish the preimage, which the counterparty needs on the other chain
+    ClaimEvent {
+      escrow: self.accounts.escrow.key(),
+      recipient: self.accounts.recipient.key(),
+      amount,
+      preimage: &self.instruction_data.preimage,
+    }.emit();
+
+    // Close the Escrow, whose rent the maker paid
+    drop(data);
+    ProgramAccount::close(self.accounts.escrow, self.accounts.maker)?;
+
+    Ok(())
+  }
+}
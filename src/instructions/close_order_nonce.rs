@@ -0,0 +1,79 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::create_program_address, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{errors::{EscrowError, PinocchioError}, helpers::{AccountCheck, AccountClose, MakerStateAccount, ProgramAccount, SignerAccount}, OrderNonce};
+
+pub struct CloseOrderNonceAccounts<'a> {
+  pub authority: &'a AccountInfo,
+  pub maker: &'a AccountInfo,
+  pub payer: &'a AccountInfo,
+  pub order_nonce: &'a AccountInfo,
+  pub maker_state: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CloseOrderNonceAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [authority, maker, payer, order_nonce, maker_state] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(authority)?;
+    ProgramAccount::check(order_nonce)?;
+
+    Ok(Self {
+      authority,
+      maker,
+      payer,
+      order_nonce,
+      maker_state,
+    })
+  }
+}
+
+pub struct CloseOrderNonce<'a> {
+  pub accounts: CloseOrderNonceAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CloseOrderNonce<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let accounts = CloseOrderNonceAccounts::try_from(accounts)?;
+
+    {
+      let data = accounts.order_nonce.try_borrow_data()?;
+      let order_nonce = OrderNonce::load(&data)?;
+      let order_nonce_key = create_program_address(&[OrderNonce::SEED, accounts.maker.key(), &order_nonce.nonce.to_le_bytes(), &order_nonce.bump], &crate::ID)
+        .map_err(|_| EscrowError::OrderNonceMismatch)?;
+      if order_nonce_key.ne(accounts.order_nonce.key()) {
+        return Err(EscrowError::OrderNonceMismatch.into());
+      }
+      // Either party may clean up, but the rent goes back to whoever paid it
+      if order_nonce.maker.ne(accounts.authority.key()) && order_nonce.payer.ne(accounts.authority.key()) {
+        return Err(EscrowError::Unauthorized.into());
+      }
+      if order_nonce.payer.ne(accounts.payer.key()) {
+        return Err(PinocchioError::InvalidAddress.into());
+      }
+      // Orders signed at the maker's current nonce could fill again
+      if !order_nonce.is_stale(MakerStateAccount::nonce(accounts.maker_state, accounts.maker)?) {
+        return Err(EscrowError::OrderNonceLive.into());
+      }
+    }
+
+    Ok(Self { accounts })
+  }
+}
+
+impl<'a> CloseOrderNonce<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &16;
+
+  pub fn process(&mut self) -> ProgramResult {
+    ProgramAccount::close(self.accounts.order_nonce, self.accounts.payer)?;
+    log!("Order nonce closed");
+
+    Ok(())
+  }
+}
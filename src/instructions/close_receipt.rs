@@ -0,0 +1,66 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{errors::{EscrowError, PinocchioError}, helpers::{AccountCheck, AccountClose, ProgramAccount, SignerAccount}, Receipt};
+
+pub struct CloseReceiptAccounts<'a> {
+  pub authority: &'a AccountInfo,
+  pub taker: &'a AccountInfo,
+  pub receipt: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CloseReceiptAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [authority, taker, receipt] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(authority)?;
+    ProgramAccount::check(receipt)?;
+
+    Ok(Self {
+      authority,
+      taker,
+      receipt,
+    })
+  }
+}
+
+pub struct CloseReceipt<'a> {
+  pub accounts: CloseReceiptAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CloseReceipt<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let accounts = CloseReceiptAccounts::try_from(accounts)?;
+
+    {
+      let data = accounts.receipt.try_borrow_data()?;
+      let receipt = Receipt::load(&data)?;
+      // Either party may clean up, but the rent goes back to the taker who paid it
+      if receipt.maker.ne(accounts.authority.key()) && receipt.taker.ne(accounts.authority.key()) {
+        return Err(EscrowError::Unauthorized.into());
+      }
+      if receipt.taker.ne(accounts.taker.key()) {
+        return Err(PinocchioError::InvalidAddress.into());
+      }
+    }
+
+    Ok(Self { accounts })
+  }
+}
+
+impl<'a> CloseReceipt<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &9;
+
+  pub fn process(&mut self) -> ProgramResult {
+    ProgramAccount::close(self.accounts.receipt, self.accounts.taker)?;
+    log!("Receipt closed");
+
+    Ok(())
+  }
+}
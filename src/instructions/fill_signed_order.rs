@@ -0,0 +1,277 @@
+use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::Pubkey, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+use pinocchio_log::log;
+use pinocchio_token_2022::instructions::TransferChecked;
+
+use crate::{errors::{EscrowError, PinocchioError}, events::FillEvent, helpers::{AccountCheck, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, ConfigAccount, DenylistEntryAccount, Ed25519Instructions, MintInterface, MakerStateAccount, MintPolicyAccount, OrderNonceAccount, SignerAccount, TokenAccountInterface, TokenProgram}, Config};
+
+pub struct FillSignedOrderAccounts<'a> {
+  pub taker: &'a AccountInfo,
+  pub maker: &'a AccountInfo,
+  pub mint_a: &'a AccountInfo,
+  pub mint_b: &'a AccountInfo,
+  pub maker_ata_a: &'a AccountInfo,
+  pub maker_ata_b: &'a AccountInfo,
+  pub taker_ata_a: &'a AccountInfo,
+  pub taker_ata_b: &'a AccountInfo,
+  pub order_nonce: &'a AccountInfo,
+  pub delegate: &'a AccountInfo,
+  pub instructions_sysvar: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+  pub token_program: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub maker_denylist_entry: &'a AccountInfo,
+  pub taker_denylist_entry: &'a AccountInfo,
+  pub maker_state: &'a AccountInfo,
+  /// Only needed when `taker_ata_a` or `maker_ata_b` requires memos
+  pub memo_program: Option<&'a AccountInfo>,
+  /// Only needed while the admin enforces mint policies
+  pub mint_a_policy: Option<&'a AccountInfo>,
+  pub mint_b_policy: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for FillSignedOrderAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [taker, maker, mint_a, mint_b, maker_ata_a, maker_ata_b, taker_ata_a, taker_ata_b, order_nonce, delegate, instructions_sysvar, system_program, token_program, _, config, maker_denylist_entry, taker_denylist_entry, maker_state, remaining @ ..] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Basic Accounts Checks. The maker doesn't sign, the order's signature
+    // stands in for it.
+    SignerAccount::check(taker)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_TAKE)?;
+    DenylistEntryAccount::check_not_denied(maker_denylist_entry, maker)?;
+    DenylistEntryAccount::check_not_denied(taker_denylist_entry, taker)?;
+    MintInterface::check(mint_a)?;
+    MintInterface::check(mint_b)?;
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    MintInterface::check_program(mint_b, token_program)?;
+    AssociatedTokenAccount::check(maker_ata_a, maker, mint_a, token_program)?;
+    AssociatedTokenAccount::check(taker_ata_b, taker, mint_b, token_program)?;
+    if delegate.key().ne(&FillSignedOrder::DELEGATE) {
+      return Err(PinocchioError::InvalidAddress.into());
+    }
+    // The taker's payment is a CPI transfer the taker signs for. The maker's
+    // leg goes through the delegate, which CPI Guard allows.
+    if TokenAccountInterface::cpi_guard_enabled(taker_ata_b)? {
+      return Err(EscrowError::CpiGuardEnabled.into());
+    }
+
+    Ok(Self {
+      taker,
+      maker,
+      mint_a,
+      mint_b,
+      maker_ata_a,
+      maker_ata_b,
+      taker_ata_a,
+      taker_ata_b,
+      order_nonce,
+      delegate,
+      instructions_sysvar,
+      system_program,
+      token_program,
+      config,
+      maker_denylist_entry,
+      taker_denylist_entry,
+      maker_state,
+      memo_program: remaining.first(),
+      mint_a_policy: remaining.get(1),
+      mint_b_policy: remaining.get(2),
+    })
+  }
+}
+
+/// The order the maker signed, without the maker and mints, which the
+/// accounts carry.
+pub struct FillSignedOrderInstructionData {
+  pub amount_a: u64,
+  pub amount_b: u64,
+  pub expiry: i64,
+  /// Any nonce the maker hasn't spent yet. Orders sharing one are
+  /// alternatives, the first filled voids the rest.
+  pub nonce: u64,
+  /// The maker's `MakerState` nonce when signing, so CancelAll voids the order
+  pub maker_nonce: u64,
+}
+
+impl<'a> TryFrom<&'a [u8]> for FillSignedOrderInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    if data.len() != Self::LEN {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let amount_a = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let amount_b = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let expiry = i64::from_le_bytes(data[16..24].try_into().unwrap());
+    let nonce = u64::from_le_bytes(data[24..32].try_into().unwrap());
+    let maker_nonce = u64::from_le_bytes(data[32..40].try_into().unwrap());
+
+    // Instruction Checks
+    if amount_a == 0 {
+      return Err(EscrowError::ZeroDeposit.into());
+    }
+
+    Ok(Self {
+      amount_a,
+      amount_b,
+      expiry,
+      nonce,
+      maker_nonce,
+    })
+  }
+}
+
+impl FillSignedOrderInstructionData {
+  pub const LEN: usize = size_of::<u64>() * 2 + size_of::<i64>() + size_of::<u64>() * 2;
+  /// The program id, maker, mint A and mint B, then the instruction data
+  pub const MESSAGE_LEN: usize = size_of::<Pubkey>() * 4 + Self::LEN;
+
+  /// The message the maker signs for this order. Starts with the program id,
+  /// so the signature can't be replayed against another program.
+  pub fn message(&self, maker: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey) -> [u8; Self::MESSAGE_LEN] {
+    let mut message = [0; Self::MESSAGE_LEN];
+    message[..32].copy_from_slice(&crate::ID);
+    message[32..64].copy_from_slice(maker);
+    message[64..96].copy_from_slice(mint_a);
+    message[96..128].copy_from_slice(mint_b);
+    message[128..136].copy_from_slice(&self.amount_a.to_le_bytes());
+    message[136..144].copy_from_slice(&self.amount_b.to_le_bytes());
+    message[144..152].copy_from_slice(&self.expiry.to_le_bytes());
+    message[152..160].copy_from_slice(&self.nonce.to_le_bytes());
+    message[160..].copy_from_slice(&self.maker_nonce.to_le_bytes());
+    message
+  }
+}
+
+pub struct FillSignedOrder<'a> {
+  pub accounts: FillSignedOrderAccounts<'a>,
+  pub instruction_data: FillSignedOrderInstructionData,
+  pub order_nonce_bump: u8,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for FillSignedOrder<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = FillSignedOrderAccounts::try_from(accounts)?;
+    let instruction_data = FillSignedOrderInstructionData::try_from(data)?;
+
+    // The same policies Make applies to a deposit
+    let enforcement = ConfigAccount::enforcement(accounts.config)?;
+    MintPolicyAccount::enforce(accounts.mint_a_policy, accounts.mint_a, instruction_data.amount_a, enforcement)?;
+    MintPolicyAccount::enforce(accounts.mint_b_policy, accounts.mint_b, instruction_data.amount_b, enforcement)?;
+    MintInterface::check_safe_extensions(accounts.mint_a)?;
+    MintInterface::check_safe_extensions(accounts.mint_b)?;
+
+    let order_nonce_bump = OrderNonceAccount::check_unspent(accounts.order_nonce, accounts.maker, instruction_data.nonce)?;
+
+    // Initialize necessary accounts
+    AssociatedTokenAccount::init_if_needed(
+      accounts.taker_ata_a,
+      accounts.mint_a,
+      accounts.taker,
+      accounts.taker,
+      accounts.system_program,
+      accounts.token_program,
+    )?;
+
+    AssociatedTokenAccount::init_if_needed(
+      accounts.maker_ata_b,
+      accounts.mint_b,
+      accounts.taker,
+      accounts.maker,
+      accounts.system_program,
+      accounts.token_program,
+    )?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+      order_nonce_bump,
+    })
+  }
+}
+
+impl<'a> FillSignedOrder<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &11;
+  pub const MEMO: &'a [u8] = b"Escrow fill";
+  pub const DELEGATE_SEED: &'static [u8] = b"delegate";
+  /// The PDA makers approve as delegate of their token A account for the
+  /// orders they sign
+  // 6CajU6yNNUpRbHgTC2E9R8LUiixXg1qTsLwrGwprLtDM
+  pub const DELEGATE: Pubkey = [
+    0x4d, 0x41, 0xf6, 0xf3, 0x05, 0xbd, 0xc8, 0x4b,
+    0xee, 0x4f, 0x6a, 0xa8, 0x68, 0x3a, 0xc6, 0xc3,
+    0xb7, 0x71, 0xa4, 0xe2, 0x7f, 0x47, 0xa3, 0x13,
+    0x60, 0x06, 0x1f, 0x56, 0x44, 0xa5, 0xe2, 0x60,
+  ];
+  pub const DELEGATE_BUMP: u8 = 254;
+
+  pub fn process(&mut self) -> ProgramResult {
+    log!("FillSignedOrder: process started");
+    let order = &self.instruction_data;
+
+    // Check the maker signed this exact order, and that it is still live
+    let message = order.message(self.accounts.maker.key(), self.accounts.mint_a.key(), self.accounts.mint_b.key());
+    Ed25519Instructions::find_signed(self.accounts.instructions_sysvar, self.accounts.maker.key(), |signed| signed == &message)?
+      .ok_or(EscrowError::OrderSignatureMissing)?;
+    if Clock::get()?.unix_timestamp >= order.expiry {
+      return Err(EscrowError::OrderExpired.into());
+    }
+    let maker_nonce = MakerStateAccount::nonce(self.accounts.maker_state, self.accounts.maker)?;
+    if order.maker_nonce != maker_nonce {
+      return Err(EscrowError::OrderCancelled.into());
+    }
+
+    // Spend the nonce, so the order can't fill twice
+    OrderNonceAccount::spend(self.accounts.taker, self.accounts.order_nonce, self.accounts.maker, order.nonce, maker_nonce, self.order_nonce_bump)?;
+
+    let bump_binding = [Self::DELEGATE_BUMP];
+    let delegate_seeds = [
+      Seed::from(Self::DELEGATE_SEED),
+      Seed::from(&bump_binding),
+    ];
+    let signer = Signer::from(&delegate_seeds);
+
+    // Transfer from the Maker to the Taker, as the maker's delegate
+    TokenAccountInterface::memo_if_required(self.accounts.taker_ata_a, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
+      from: self.accounts.maker_ata_a,
+      mint: self.accounts.mint_a,
+      to: self.accounts.taker_ata_a,
+      authority: self.accounts.delegate,
+      amount: order.amount_a,
+      decimals: MintInterface::decimals(self.accounts.mint_a)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    // Transfer from the Taker to the Maker
+    TokenAccountInterface::memo_if_required(self.accounts.maker_ata_b, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
+      from: self.accounts.taker_ata_b,
+      mint: self.accounts.mint_b,
+      to: self.accounts.maker_ata_b,
+      authority: self.accounts.taker,
+      amount: order.amount_b,
+      decimals: MintInterface::decimals(self.accounts.mint_b)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke()?;
+
+    FillEvent {
+      maker: self.accounts.maker.key(),
+      taker: self.accounts.taker.key(),
+      mint_a: self.accounts.mint_a.key(),
+      mint_b: self.accounts.mint_b.key(),
+      amount_a: order.amount_a,
+      amount_b: order.amount_b,
+      nonce: order.nonce,
+    }.emit();
+
+    Ok(())
+  }
+}
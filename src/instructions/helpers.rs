@@ -1,19 +1,72 @@
-use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::find_program_address, sysvars::{rent::Rent, Sysvar}, ProgramResult};
+use pinocchio::{account_info::AccountInfo, cpi::invoke, instruction::{AccountMeta, Instruction, Seed, Signer}, program_error::ProgramError, pubkey::{find_program_address, Pubkey}, sysvars::{instructions::Instructions, rent::Rent, Sysvar}, ProgramResult};
 use pinocchio_associated_token_account::instructions::Create;
 use pinocchio_log::log;
-use pinocchio_system::instructions::CreateAccount;
+use pinocchio_system::instructions::{Allocate, Assign, CreateAccount, Transfer};
 //use pinocchio_token::state::TokenAccount;
-use crate::errors::PinocchioError;
+use core::mem::size_of;
+use crate::{errors::{EscrowError, PinocchioError}, Config, DenylistEntry, MakerState, MintPolicy, OrderNonce, Receipt};
 
 pub const TOKEN_2022_PROGRAM_ID: [u8; 32] = [
     0x06, 0xdd, 0xf6, 0xe1, 0xee, 0x75, 0x8f, 0xde, 0x18, 0x42, 0x5d, 0xbc, 0xe4, 0x6c, 0xcd, 0xda,
     0xb6, 0x1a, 0xfc, 0x4d, 0x83, 0xb9, 0x0d, 0x27, 0xfe, 0xbd, 0xf9, 0x28, 0xd8, 0xa1, 0x8b, 0xfc,
 ];
 
+// MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr
+pub const MEMO_PROGRAM_ID: [u8; 32] = [
+    0x05, 0x4a, 0x53, 0x5a, 0x99, 0x29, 0x21, 0x06, 0x4d, 0x24, 0xe8, 0x71, 0x60, 0xda, 0x38, 0x7c,
+    0x7c, 0x35, 0xb5, 0xdd, 0xbc, 0x92, 0xbb, 0x81, 0xe4, 0x1f, 0xa8, 0x40, 0x41, 0x05, 0x44, 0x8d,
+];
+
 const TOKEN_2022_ACCOUNT_DISCRIMINATOR_OFFSET: usize = 165;
 pub const TOKEN_2022_MINT_DISCRIMINATOR: u8 = 0x01;
 pub const TOKEN_2022_TOKEN_ACCOUNT_DISCRIMINATOR: u8 = 0x02;
 
+// Token-2022 extension types the program inspects
+const TOKEN_2022_EXTENSIONS_OFFSET: usize = TOKEN_2022_ACCOUNT_DISCRIMINATOR_OFFSET + 1;
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_TRANSFER_FEE_AMOUNT: u16 = 2;
+const EXTENSION_DEFAULT_ACCOUNT_STATE: u16 = 6;
+const EXTENSION_MEMO_TRANSFER: u16 = 8;
+const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+const EXTENSION_CPI_GUARD: u16 = 11;
+const EXTENSION_PERMANENT_DELEGATE: u16 = 12;
+const ACCOUNT_STATE_FROZEN: u8 = 2;
+
+/// Finds a Token-2022 extension by walking the type-length-value entries
+/// after the account type byte, and maps its value with `f`. Returns `None`
+/// for accounts of the Token program and for Token-2022 accounts without the
+/// extension.
+fn find_extension<R>(account: &AccountInfo, extension_type: u16, f: impl FnOnce(&[u8]) -> R) -> Result<Option<R>, ProgramError> {
+    if !account.is_owned_by(&TOKEN_2022_PROGRAM_ID) {
+        return Ok(None);
+    }
+
+    let data = account.try_borrow_data()?;
+    let mut offset = TOKEN_2022_EXTENSIONS_OFFSET;
+    while offset + 4 <= data.len() {
+        let found_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if found_type == EXTENSION_UNINITIALIZED {
+            break;
+        }
+        let value = data
+            .get(offset + 4..offset + 4 + length)
+            .ok_or(PinocchioError::InvalidAccountData)?;
+        if found_type == extension_type {
+            return Ok(Some(f(value)));
+        }
+        offset += 4 + length;
+    }
+
+    Ok(None)
+}
+
+// Offsets into the base layouts, which Token-2022 keeps identical to Token
+const MINT_DECIMALS_OFFSET: usize = 44;
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
 pub trait AccountCheck {
     fn check(account: &AccountInfo) -> Result<(), ProgramError>;
 }
@@ -61,6 +114,30 @@ impl AccountCheck for TokenAccount {
     }
 }
 
+pub struct TokenProgram;
+ 
+impl AccountCheck for TokenProgram {
+    fn check(account: &AccountInfo) -> Result<(), ProgramError> {
+        if account.key().ne(&pinocchio_token::ID) && account.key().ne(&TOKEN_2022_PROGRAM_ID) {
+            return Err(EscrowError::UnsupportedTokenProgram.into());
+        }
+ 
+        Ok(())
+    }
+}
+
+pub struct MemoProgram;
+
+impl AccountCheck for MemoProgram {
+    fn check(account: &AccountInfo) -> Result<(), ProgramError> {
+        if account.key().ne(&MEMO_PROGRAM_ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        Ok(())
+    }
+}
+
 pub struct MintInterface;
  
 impl AccountCheck for MintInterface {
@@ -89,6 +166,47 @@ impl AccountCheck for MintInterface {
         Ok(())
     }
 }
+
+impl MintInterface {
+    /// Checks the mint belongs to `token_program`, so a Token mint can't be
+    /// paired with Token-2022 accounts or the other way round.
+    pub fn check_program(account: &AccountInfo, token_program: &AccountInfo) -> Result<(), ProgramError> {
+        if !account.is_owned_by(token_program.key()) {
+            return Err(PinocchioError::InvalidOwner.into());
+        }
+
+        Ok(())
+    }
+
+    /// Rejects Token-2022 mints with extensions that put escrowed tokens at
+    /// risk: a permanent delegate that can drain the vault, a non-transferable
+    /// mint whose deposit can never leave, and a default account state of
+    /// frozen that bricks newly created vaults. The mint must have passed
+    /// `check` first.
+    pub fn check_safe_extensions(account: &AccountInfo) -> Result<(), ProgramError> {
+        let non_transferable = find_extension(account, EXTENSION_NON_TRANSFERABLE, |_| ())?.is_some();
+        let frozen_by_default = find_extension(account, EXTENSION_DEFAULT_ACCOUNT_STATE, |value| {
+            value.first() == Some(&ACCOUNT_STATE_FROZEN)
+        })?;
+        // An all-zero delegate means none is set
+        let permanent_delegate = find_extension(account, EXTENSION_PERMANENT_DELEGATE, |value| {
+            value.iter().any(|byte| *byte != 0)
+        })?;
+
+        if non_transferable || frozen_by_default == Some(true) || permanent_delegate == Some(true) {
+            return Err(EscrowError::UnsafeMintExtension.into());
+        }
+
+        Ok(())
+    }
+
+    /// Reads the decimals from the base mint layout both token programs share.
+    /// The account must have passed `check` first.
+    pub fn decimals(account: &AccountInfo) -> Result<u8, ProgramError> {
+        let data = account.try_borrow_data()?;
+        Ok(data[MINT_DECIMALS_OFFSET])
+    }
+}
  
 pub struct TokenAccountInterface;
  
@@ -120,6 +238,91 @@ impl AccountCheck for TokenAccountInterface {
         Ok(())
     }
 }
+
+impl TokenAccountInterface {
+    /// Whether the account's MemoTransfer extension requires a memo on
+    /// incoming transfers.
+    pub fn requires_incoming_memo(account: &AccountInfo) -> Result<bool, ProgramError> {
+        Ok(find_extension(account, EXTENSION_MEMO_TRANSFER, |value| value.first() == Some(&1))? == Some(true))
+    }
+
+    /// Whether the account's CpiGuard stops its owner from signing transfers
+    /// through a CPI, which every escrow transfer is.
+    pub fn cpi_guard_enabled(account: &AccountInfo) -> Result<bool, ProgramError> {
+        Ok(find_extension(account, EXTENSION_CPI_GUARD, |value| value.first() == Some(&1))? == Some(true))
+    }
+
+    /// Logs `memo` through the Memo program right before a transfer into
+    /// `account`, when its MemoTransfer extension requires one.
+    pub fn memo_if_required(account: &AccountInfo, memo_program: Option<&AccountInfo>, memo: &[u8]) -> ProgramResult {
+        if !Self::requires_incoming_memo(account)? {
+            return Ok(());
+        }
+        let memo_program = memo_program.ok_or(EscrowError::MemoProgramMissing)?;
+        MemoProgram::check(memo_program)?;
+
+        invoke::<0>(
+            &Instruction {
+                program_id: memo_program.key(),
+                accounts: &[],
+                data: memo,
+            },
+            &[],
+        )
+    }
+
+    /// Moves the transfer fees Token-2022 withheld on `account` to its mint,
+    /// which needs no authority, so the account can be closed. Nothing
+    /// happens when none are withheld, otherwise the mint must be writable.
+    pub fn harvest_withheld_fees(account: &AccountInfo, mint: &AccountInfo, token_program: &AccountInfo) -> ProgramResult {
+        let withheld = find_extension(account, EXTENSION_TRANSFER_FEE_AMOUNT, |value| {
+            value.get(..8).map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        })?;
+        if withheld.unwrap_or(0) == 0 {
+            return Ok(());
+        }
+
+        // TransferFeeExtension::HarvestWithheldTokensToMint
+        invoke::<2>(
+            &Instruction {
+                program_id: token_program.key(),
+                accounts: &[AccountMeta::writable(mint.key()), AccountMeta::writable(account.key())],
+                data: &[26, 4],
+            },
+            &[mint, account],
+        )
+    }
+
+    /// Checks a token account that needn't be an ATA holds `mint` and belongs
+    /// to `token_program`. The account must have passed `check` first.
+    pub fn check_mint(account: &AccountInfo, mint: &AccountInfo, token_program: &AccountInfo) -> Result<(), ProgramError> {
+        if !account.is_owned_by(token_program.key()) {
+            return Err(PinocchioError::InvalidOwner.into());
+        }
+        let data = account.try_borrow_data()?;
+        if data[TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + 32].ne(mint.key()) {
+            return Err(EscrowError::MintMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    /// Reads the amount from the base account layout both token programs
+    /// share. The account must have passed `check` first.
+    pub fn amount(account: &AccountInfo) -> Result<u64, ProgramError> {
+        let data = account.try_borrow_data()?;
+        Ok(u64::from_le_bytes(
+            data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8].try_into().unwrap(),
+        ))
+    }
+
+    /// Reads the owner from the base account layout both token programs
+    /// share. The account must have passed `check` first.
+    pub fn owner(account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+        let data = account.try_borrow_data()?;
+        Ok(data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32].try_into().unwrap())
+    }
+}
  
 
 
@@ -161,6 +364,34 @@ impl ProgramAccountInit for ProgramAccount {
         // Create signer with seeds slice
         let signer = [Signer::from(seeds)];
  
+        // Anyone can send lamports to a PDA before it exists, which would make
+        // CreateAccount fail forever. Top such an account up to rent exemption
+        // and allocate and assign it instead.
+        let current = account.lamports();
+        if current > 0 {
+            log!("Initializing pre-funded program account");
+            if current < lamports {
+                Transfer {
+                    from: payer,
+                    to: account,
+                    lamports: lamports - current,
+                }
+                .invoke()?;
+            }
+            Allocate {
+                account,
+                space: space as u64,
+            }
+            .invoke_signed(&signer)?;
+            Assign {
+                account,
+                owner: &crate::ID,
+            }
+            .invoke_signed(&signer)?;
+            log!("Program account initialized");
+            return Ok(());
+        }
+
         log!("Creating program account");
         // Create the account
         CreateAccount {
@@ -182,6 +413,9 @@ pub trait AccountClose {
  
 impl AccountClose for ProgramAccount {
     fn close(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+        if !account.is_writable() {
+            return Err(ProgramError::Immutable);
+        }
         {
             let mut data = account.try_borrow_mut_data()?;
             data[0] = 0xff;
@@ -193,6 +427,316 @@ impl AccountClose for ProgramAccount {
     }
 }
 
+pub struct ConfigAccount;
+
+impl AccountCheck for ConfigAccount {
+    fn check(account: &AccountInfo) -> Result<(), ProgramError> {
+        if account.key().ne(&Config::ADDRESS) {
+            return Err(EscrowError::ConfigMismatch.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigAccount {
+    /// Fails with `InstructionPaused` when the admin paused `flag`. A config
+    /// nobody initialized yet pauses nothing.
+    pub fn check_not_paused(account: &AccountInfo, flag: u8) -> Result<(), ProgramError> {
+        Self::check(account)?;
+        if account.data_is_empty() {
+            return Ok(());
+        }
+        ProgramAccount::check(account)?;
+        if Config::load(&account.try_borrow_data()?)?.is_paused(flag) {
+            return Err(EscrowError::InstructionPaused.into());
+        }
+        log!("Pause check passed");
+        Ok(())
+    }
+
+    /// The `Config::ENFORCE_*` bits of the enforced policies. The account
+    /// must have passed `check_not_paused` first.
+    pub fn enforcement(account: &AccountInfo) -> Result<u8, ProgramError> {
+        if account.data_is_empty() {
+            return Ok(0);
+        }
+        Ok(Config::load(&account.try_borrow_data()?)?.enforce)
+    }
+
+    /// Checks `account` is the initialized config and `admin` its admin.
+    pub fn check_admin(account: &AccountInfo, admin: &AccountInfo) -> Result<(), ProgramError> {
+        Self::check(account)?;
+        ProgramAccount::check(account)?;
+        if Config::load(&account.try_borrow_data()?)?.admin.ne(admin.key()) {
+            return Err(EscrowError::Unauthorized.into());
+        }
+
+        Ok(())
+    }
+}
+
+pub struct MintPolicyAccount;
+
+impl MintPolicyAccount {
+    /// Checks `account` is the policy PDA of `mint`, returning its bump.
+    pub fn check(account: &AccountInfo, mint: &AccountInfo) -> Result<u8, ProgramError> {
+        let (address, bump) = find_program_address(&[MintPolicy::SEED, mint.key()], &crate::ID);
+        if address.ne(account.key()) {
+            return Err(EscrowError::MintPolicyMismatch.into());
+        }
+
+        Ok(bump)
+    }
+
+    /// Applies the policy of `mint` to `amount` under the config's
+    /// `enforcement` bits. An empty policy account means the mint is unlisted,
+    /// which only fails when `Config::REQUIRE_MINT_POLICY` is set.
+    pub fn enforce(account: Option<&AccountInfo>, mint: &AccountInfo, amount: u64, enforcement: u8) -> Result<(), ProgramError> {
+        if enforcement & Config::ENFORCE_MINT_POLICY == 0 {
+            return Ok(());
+        }
+        let account = account.ok_or(EscrowError::MintPolicyMissing)?;
+        Self::check(account, mint)?;
+
+        if account.data_is_empty() {
+            if enforcement & Config::REQUIRE_MINT_POLICY != 0 {
+                return Err(EscrowError::MintNotAllowed.into());
+            }
+            return Ok(());
+        }
+        ProgramAccount::check(account)?;
+        let data = account.try_borrow_data()?;
+        let policy = MintPolicy::load(&data)?;
+        if !policy.is_allowed() {
+            return Err(EscrowError::MintNotAllowed.into());
+        }
+        if !policy.permits(amount) {
+            return Err(EscrowError::AmountOutOfRange.into());
+        }
+        log!("Mint policy check passed");
+        Ok(())
+    }
+}
+
+pub struct DenylistEntryAccount;
+
+impl DenylistEntryAccount {
+    /// Checks `account` is the denylist PDA of `wallet`, returning its bump.
+    pub fn check(account: &AccountInfo, wallet: &Pubkey) -> Result<u8, ProgramError> {
+        let (address, bump) = find_program_address(&[DenylistEntry::SEED, wallet], &crate::ID);
+        if address.ne(account.key()) {
+            return Err(EscrowError::DenylistEntryMismatch.into());
+        }
+
+        Ok(bump)
+    }
+
+    /// Proves `wallet` isn't denied: its denylist PDA must be an empty
+    /// system account, as every address is until the admin creates the entry.
+    pub fn check_not_denied(account: &AccountInfo, wallet: &AccountInfo) -> Result<(), ProgramError> {
+        Self::check(account, wallet.key())?;
+        if !account.is_owned_by(&pinocchio_system::ID) || !account.data_is_empty() {
+            return Err(EscrowError::WalletDenied.into());
+        }
+        log!("Denylist check passed");
+        Ok(())
+    }
+}
+
+/// SHA-256 of `data`, through the syscall on-chain.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    #[cfg(target_os = "solana")]
+    {
+        let mut hash = [0u8; 32];
+        let values: &[&[u8]] = &[data];
+        unsafe {
+            pinocchio::syscalls::sol_sha256(values as *const _ as *const u8, values.len() as u64, hash.as_mut_ptr());
+        }
+        hash
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(data).into()
+    }
+}
+
+pub struct ReceiptAccount;
+
+impl ReceiptAccount {
+    /// Checks `account` is the receipt PDA of `escrow` and `taker`, returning
+    /// its bump.
+    pub fn check(account: &AccountInfo, escrow: &AccountInfo, taker: &AccountInfo) -> Result<u8, ProgramError> {
+        let (address, bump) = find_program_address(&[Receipt::SEED, escrow.key(), taker.key()], &crate::ID);
+        if address.ne(account.key()) {
+            return Err(EscrowError::ReceiptMismatch.into());
+        }
+
+        Ok(bump)
+    }
+}
+
+pub struct MakerStateAccount;
+
+impl MakerStateAccount {
+    /// Checks `account` is the maker state PDA of `maker`, returning its bump.
+    pub fn check(account: &AccountInfo, maker: &AccountInfo) -> Result<u8, ProgramError> {
+        let (address, bump) = find_program_address(&[MakerState::SEED, maker.key()], &crate::ID);
+        if address.ne(account.key()) {
+            return Err(EscrowError::MakerStateMismatch.into());
+        }
+
+        Ok(bump)
+    }
+
+    /// The maker's current nonce, zero until CancelAll first creates the PDA.
+    pub fn nonce(account: &AccountInfo, maker: &AccountInfo) -> Result<u64, ProgramError> {
+        Self::check(account, maker)?;
+        if account.data_is_empty() {
+            return Ok(0);
+        }
+        ProgramAccount::check(account)?;
+        Ok(MakerState::load(&account.try_borrow_data()?)?.nonce)
+    }
+}
+
+pub struct OrderNonceAccount;
+
+impl OrderNonceAccount {
+    /// Checks `account` is the PDA of `maker`'s order `nonce` and that the
+    /// nonce isn't spent yet, returning its bump.
+    pub fn check_unspent(account: &AccountInfo, maker: &AccountInfo, nonce: u64) -> Result<u8, ProgramError> {
+        let (address, bump) = find_program_address(&[OrderNonce::SEED, maker.key(), &nonce.to_le_bytes()], &crate::ID);
+        if address.ne(account.key()) {
+            return Err(EscrowError::OrderNonceMismatch.into());
+        }
+        // Lamports alone don't spend a nonce, only the account's creation does
+        if !account.data_is_empty() {
+            return Err(EscrowError::OrderNonceInvalid.into());
+        }
+
+        Ok(bump)
+    }
+
+    /// Spends `maker`'s order `nonce` at the maker's current `maker_nonce` by
+    /// creating its PDA, which `check_unspent` must have passed with `bump`.
+    /// `payer` pays the rent.
+    pub fn spend(payer: &AccountInfo, account: &AccountInfo, maker: &AccountInfo, nonce: u64, maker_nonce: u64, bump: u8) -> ProgramResult {
+        let nonce_binding = nonce.to_le_bytes();
+        let bump_binding = [bump];
+        let order_nonce_seeds = [
+            Seed::from(OrderNonce::SEED),
+            Seed::from(maker.key().as_ref()),
+            Seed::from(&nonce_binding),
+            Seed::from(&bump_binding),
+        ];
+        ProgramAccount::init::<OrderNonce>(payer, account, &order_nonce_seeds, OrderNonce::LEN)?;
+        let mut data = account.try_borrow_mut_data()?;
+        OrderNonce::load_mut(data.as_mut())?.set_inner(*maker.key(), nonce, maker_nonce, *payer.key(), bump_binding);
+        log!("Order nonce {} spent", nonce);
+        Ok(())
+    }
+}
+
+// Ed25519SigVerify111111111111111111111111111
+pub const ED25519_PROGRAM_ID: [u8; 32] = [
+    0x03, 0x7d, 0x46, 0xd6, 0x7c, 0x93, 0xfb, 0xbe, 0x12, 0xf9, 0x42, 0x8f, 0x83, 0x8d, 0x40, 0xff,
+    0x05, 0x70, 0x74, 0x49, 0x27, 0xf4, 0x8a, 0x64, 0xfc, 0xca, 0x70, 0x44, 0x80, 0x00, 0x00, 0x00,
+];
+// `Ed25519SignatureOffsets`, after the signature count and a padding byte
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 7 * size_of::<u16>();
+// An offset's instruction index pointing at the Ed25519 instruction itself
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+pub struct Ed25519Instructions;
+
+impl Ed25519Instructions {
+    /// Finds a message `signer` signed through an Ed25519 program instruction
+    /// before the current one, among those `matches` accepts. The runtime
+    /// fails the whole transaction if any of those signatures is invalid, so
+    /// a message found here was signed. Only signatures, public keys and
+    /// messages inside the Ed25519 instruction itself are considered, so the
+    /// signature verified is the one over the message found.
+    pub fn find_signed<const N: usize>(
+        instructions_sysvar: &AccountInfo,
+        signer: &Pubkey,
+        matches: impl Fn(&[u8; N]) -> bool,
+    ) -> Result<Option<[u8; N]>, ProgramError> {
+        let instructions = Instructions::try_from(instructions_sysvar)?;
+        for index in 0..instructions.load_current_index() as usize {
+            let instruction = instructions.load_instruction_at(index)?;
+            if instruction.get_program_id().ne(&ED25519_PROGRAM_ID) {
+                continue;
+            }
+            let data = instruction.get_instruction_data();
+            let count = data.first().copied().unwrap_or(0) as usize;
+            for signature in 0..count {
+                let start = ED25519_OFFSETS_START + signature * ED25519_OFFSETS_LEN;
+                let Some(offsets) = data.get(start..start + ED25519_OFFSETS_LEN) else {
+                    break;
+                };
+                let offset = |field: usize| u16::from_le_bytes([offsets[2 * field], offsets[2 * field + 1]]);
+                let signature_index = offset(1);
+                let (public_key_offset, public_key_index) = (offset(2) as usize, offset(3));
+                let (message_offset, message_len, message_index) = (offset(4) as usize, offset(5) as usize, offset(6));
+                if [signature_index, public_key_index, message_index].iter().any(|index| *index != ED25519_CURRENT_INSTRUCTION) || message_len != N {
+                    continue;
+                }
+                let public_key = data.get(public_key_offset..public_key_offset + size_of::<Pubkey>());
+                let message = data.get(message_offset..message_offset + N).map(|message| <[u8; N]>::try_from(message).unwrap());
+                if let (Some(public_key), Some(message)) = (public_key, message) {
+                    if public_key == signer && matches(&message) {
+                        return Ok(Some(message));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+// BPFLoaderUpgradeab1e11111111111111111111111
+pub const BPF_LOADER_UPGRADEABLE_ID: [u8; 32] = [
+    0x02, 0xa8, 0xf6, 0x91, 0x4e, 0x88, 0xa1, 0xb0, 0xe2, 0x10, 0x15, 0x3e, 0xf7, 0x63, 0xae, 0x2b,
+    0x00, 0xc2, 0xb9, 0x3d, 0x16, 0xc1, 0x24, 0xd2, 0xc0, 0x53, 0x7a, 0x10, 0x04, 0x80, 0x00, 0x00,
+];
+// `UpgradeableLoaderState::ProgramData { slot, upgrade_authority_address }`
+const PROGRAM_DATA_DISCRIMINATOR: u32 = 3;
+const PROGRAM_DATA_AUTHORITY_OFFSET: usize = size_of::<u32>() + size_of::<u64>();
+
+pub struct ProgramDataAccount;
+
+impl ProgramDataAccount {
+    /// Checks `account` is this program's ProgramData and `authority` its
+    /// upgrade authority.
+    pub fn check_upgrade_authority(account: &AccountInfo, authority: &AccountInfo) -> Result<(), ProgramError> {
+        if !account.is_owned_by(&BPF_LOADER_UPGRADEABLE_ID) {
+            return Err(PinocchioError::InvalidOwner.into());
+        }
+        if find_program_address(&[&crate::ID], &BPF_LOADER_UPGRADEABLE_ID).0.ne(account.key()) {
+            return Err(PinocchioError::InvalidAddress.into());
+        }
+
+        let data = account.try_borrow_data()?;
+        if data.len() < PROGRAM_DATA_AUTHORITY_OFFSET + 1 + size_of::<Pubkey>()
+            || u32::from_le_bytes(data[..4].try_into().unwrap()) != PROGRAM_DATA_DISCRIMINATOR
+        {
+            return Err(PinocchioError::InvalidAccountData.into());
+        }
+        // An immutable program has no upgrade authority and can't be administered
+        let authority_address = &data[PROGRAM_DATA_AUTHORITY_OFFSET + 1..PROGRAM_DATA_AUTHORITY_OFFSET + 1 + size_of::<Pubkey>()];
+        if data[PROGRAM_DATA_AUTHORITY_OFFSET] != 1 || authority_address.ne(authority.key()) {
+            return Err(EscrowError::Unauthorized.into());
+        }
+
+        Ok(())
+    }
+}
+
 pub trait AccountInit {
     fn init(account: &AccountInfo, mint: &AccountInfo, payer: &AccountInfo, owner: &[u8; 32]) -> ProgramResult;
     fn init_if_needed(account: &AccountInfo, mint: &AccountInfo, payer: &AccountInfo, owner: &[u8; 32]) -> ProgramResult;
@@ -212,7 +756,10 @@ impl AssociatedTokenAccountCheck for AssociatedTokenAccount {
         mint: &AccountInfo,
         token_program: &AccountInfo,
     ) -> Result<(), ProgramError> {
-        TokenAccount::check(account)?;
+        if !account.is_owned_by(token_program.key()) {
+            return Err(PinocchioError::InvalidOwner.into());
+        }
+        TokenAccountInterface::check(account)?;
  
         if find_program_address(
             &[authority.key(), token_program.key(), mint.key()],
@@ -228,6 +775,24 @@ impl AssociatedTokenAccountCheck for AssociatedTokenAccount {
     }
 }
  
+impl AssociatedTokenAccount {
+    /// Checks only the address of the `authority`'s associated token
+    /// account, for accounts `init_if_needed` may still have to create.
+    pub fn check_address(account: &AccountInfo, authority: &AccountInfo, mint: &AccountInfo, token_program: &AccountInfo) -> Result<(), ProgramError> {
+        if find_program_address(
+            &[authority.key(), token_program.key(), mint.key()],
+            &pinocchio_associated_token_account::ID,
+        )
+        .0
+        .ne(account.key())
+        {
+            return Err(PinocchioError::InvalidAddress.into());
+        }
+
+        Ok(())
+    }
+}
+
 impl AssociatedTokenAccountInit for AssociatedTokenAccount {
     fn init(account: &AccountInfo, mint: &AccountInfo, payer: &AccountInfo, owner: &AccountInfo, system_program: &AccountInfo, token_program: &AccountInfo) -> ProgramResult {
         log!("Invoking Create");
@@ -0,0 +1,95 @@
+use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{helpers::{AccountCheck, ConfigAccount, ProgramAccount, ProgramAccountInit, ProgramDataAccount, SignerAccount}, Config};
+
+pub struct InitializeConfigAccounts<'a> {
+  pub authority: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub program_data: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for InitializeConfigAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [authority, config, program_data, system_program] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Only the upgrade authority may hand out the admin role
+    SignerAccount::check(authority)?;
+    ConfigAccount::check(config)?;
+    ProgramDataAccount::check_upgrade_authority(program_data, authority)?;
+
+    Ok(Self {
+      authority,
+      config,
+      program_data,
+      system_program,
+    })
+  }
+}
+
+pub struct InitializeConfigInstructionData {
+  pub admin: Pubkey,
+}
+
+impl<'a> TryFrom<&'a [u8]> for InitializeConfigInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let admin: Pubkey = data.try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    Ok(Self { admin })
+  }
+}
+
+pub struct InitializeConfig<'a> {
+  pub accounts: InitializeConfigAccounts<'a>,
+  pub instruction_data: InitializeConfigInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for InitializeConfig<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = InitializeConfigAccounts::try_from(accounts)?;
+    let instruction_data = InitializeConfigInstructionData::try_from(data)?;
+
+    let bump_binding = [Config::BUMP];
+    let config_seeds = [
+      Seed::from(Config::SEED),
+      Seed::from(&bump_binding),
+    ];
+
+    // Fails if the config already exists
+    ProgramAccount::init::<Config>(
+      accounts.authority,
+      accounts.config,
+      &config_seeds,
+      Config::LEN
+    )?;
+    log!("Config account initialized");
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+    })
+  }
+}
+
+impl<'a> InitializeConfig<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &3;
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.config.try_borrow_mut_data()?;
+    let config = Config::load_mut(data.as_mut())?;
+
+    // Nothing starts paused or enforced
+    config.set_inner(self.instruction_data.admin, 0, 0, [Config::BUMP]);
+
+    Ok(())
+  }
+}
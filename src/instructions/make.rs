@@ -1,8 +1,8 @@
-use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, pubkey::find_program_address, ProgramResult};
+use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, pubkey::{find_program_address, Pubkey}, sysvars::{clock::Clock, Sysvar}, ProgramResult};
 use pinocchio_log::log;
-use pinocchio_token::instructions::Transfer;
+use pinocchio_token_2022::instructions::TransferChecked;
 
-use crate::{helpers::{AccountCheck, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, MintInterface, ProgramAccount, ProgramAccountInit, SignerAccount}, Escrow};
+use crate::{errors::EscrowError, helpers::{AccountCheck, AssociatedTokenAccount, ConfigAccount, DenylistEntryAccount, AssociatedTokenAccountCheck, MakerStateAccount, AssociatedTokenAccountInit, MintInterface, MintPolicyAccount, ProgramAccount, ProgramAccountInit, SignerAccount, TokenAccountInterface, TokenProgram}, events::MakeEvent, Config, Escrow};
 
 
 pub struct MakeAccounts<'a> {
@@ -14,6 +14,12 @@ pub struct MakeAccounts<'a> {
   pub vault: &'a AccountInfo,
   pub system_program: &'a AccountInfo,
   pub token_program: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub maker_denylist_entry: &'a AccountInfo,
+  pub maker_state: &'a AccountInfo,
+  /// Only needed while the admin enforces mint policies
+  pub mint_a_policy: Option<&'a AccountInfo>,
+  pub mint_b_policy: Option<&'a AccountInfo>,
 }
  
 impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
@@ -21,16 +27,25 @@ impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
  
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
 
-    let [maker, escrow, mint_a, mint_b, maker_ata_a, vault, system_program, token_program, _] = accounts else {  
+    let [maker, escrow, mint_a, mint_b, maker_ata_a, vault, system_program, token_program, _, config, maker_denylist_entry, maker_state, remaining @ ..] = accounts else {  
       return Err(ProgramError::NotEnoughAccountKeys);
     };
  
     log!("All accounts provided");
     // Basic Accounts Checks
     SignerAccount::check(maker)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_MAKE)?;
+    DenylistEntryAccount::check_not_denied(maker_denylist_entry, maker)?;
     MintInterface::check(mint_a)?;
     MintInterface::check(mint_b)?;
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    MintInterface::check_program(mint_b, token_program)?;
     AssociatedTokenAccount::check(maker_ata_a, maker, mint_a, token_program)?;
+    // The deposit is a CPI transfer the maker signs for
+    if TokenAccountInterface::cpi_guard_enabled(maker_ata_a)? {
+      return Err(EscrowError::CpiGuardEnabled.into());
+    }
  
     // Return the accounts
     Ok(Self {
@@ -42,6 +57,11 @@ impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
       vault,
       system_program,
       token_program,
+      config,
+      maker_denylist_entry,
+      maker_state,
+      mint_a_policy: remaining.first(),
+      mint_b_policy: remaining.get(1),
     })
   }
 }
@@ -50,22 +70,81 @@ pub struct MakeInstructionData {
   pub seed: u64,
   pub receive: u64,
   pub amount: u64,
+  pub flags: u8,
+  pub max_referral_bps: u16,
+  pub terms_hash: [u8; 32],
+  pub external_ref: [u8; 16],
+  pub hashlock: [u8; 32],
+  pub recipient: Pubkey,
+  pub timeout: i64,
+  pub authorizer: Pubkey,
+  pub arbiter: Pubkey,
+  pub arbiter_fee_bps: u16,
 }
  
 impl<'a> TryFrom<&'a [u8]> for MakeInstructionData {
   type Error = ProgramError;
  
   fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-    if data.len() != size_of::<u64>() * 3 {
-      return Err(ProgramError::InvalidInstructionData);
-    }
- 
+    // The trailing flags byte is optional and defaults to none set, the
+    // maximum referral fee after it to no referrals, the terms after that
+    // to none, the hashlock after that to an escrow Take settles, the
+    // authorizer after that to none, and the arbiter last to none
+    const TERMS: usize = 3;
+    const HASHLOCK: usize = TERMS + MakeInstructionData::TERMS_LEN;
+    const AUTHORIZER: usize = HASHLOCK + MakeInstructionData::HASHLOCK_LEN;
+    const ARBITER: usize = AUTHORIZER + MakeInstructionData::AUTHORIZER_LEN;
+    let tail = match data.len().checked_sub(Self::LEN) {
+      Some(len) if [0, 1, TERMS, HASHLOCK, AUTHORIZER, ARBITER, ARBITER + Self::ARBITER_LEN].contains(&len) => &data[Self::LEN..],
+      _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    let flags = tail.first().copied().unwrap_or(0);
+    let max_referral_bps = tail.get(1..TERMS).map_or(0, |bytes| u16::from_le_bytes(bytes.try_into().unwrap()));
+    let (terms_hash, external_ref) = match tail.get(TERMS..HASHLOCK) {
+      Some(terms) => (terms[..32].try_into().unwrap(), terms[32..].try_into().unwrap()),
+      None => ([0; 32], [0; 16]),
+    };
+    let (hashlock, recipient, timeout) = match tail.get(HASHLOCK..AUTHORIZER) {
+      Some(htlc) => (
+        htlc[..32].try_into().unwrap(),
+        htlc[32..64].try_into().unwrap(),
+        i64::from_le_bytes(htlc[64..].try_into().unwrap()),
+      ),
+      None => ([0; 32], [0; 32], 0),
+    };
+    let authorizer = tail.get(AUTHORIZER..ARBITER).map_or([0; 32], |bytes| bytes.try_into().unwrap());
+    let (arbiter, arbiter_fee_bps) = match tail.get(ARBITER..) {
+      Some(arbiter) if !arbiter.is_empty() => (
+        arbiter[..32].try_into().unwrap(),
+        u16::from_le_bytes(arbiter[32..].try_into().unwrap()),
+      ),
+      _ => ([0; 32], 0),
+    };
+
     let seed = u64::from_le_bytes(data[0..8].try_into().unwrap());
     let receive = u64::from_le_bytes(data[8..16].try_into().unwrap());
     let amount = u64::from_le_bytes(data[16..24].try_into().unwrap());
  
     // Instruction Checks
     if amount == 0 {
+      return Err(EscrowError::ZeroDeposit.into());
+    }
+    if flags & !Self::ALLOW_UNSAFE_MINT_EXTENSIONS != 0 || max_referral_bps > Escrow::MAX_BPS {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+    // A hashlock of zeros would make the escrow takeable, and nobody could
+    // claim it. An all-zero hashlock segment only pads before the authorizer.
+    if hashlock == [0; 32] && (recipient != [0; 32] || timeout != 0) {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+    // Nor could anyone claim a hashlock for the zero address before the
+    // timeout hands it back to the maker
+    if hashlock != [0; 32] && recipient == [0; 32] {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+    // Claim pays a hashlocked escrow out without a taker to hold a payment
+    // from, and a fee needs an arbiter to pay it to
+    if (arbiter != [0; 32] && hashlock != [0; 32]) || (arbiter == [0; 32] && arbiter_fee_bps != 0) || arbiter_fee_bps > Escrow::MAX_BPS {
       return Err(ProgramError::InvalidInstructionData);
     }
     log!("Instruction data checks passed");
@@ -73,14 +152,54 @@ impl<'a> TryFrom<&'a [u8]> for MakeInstructionData {
       seed,
       receive,
       amount,
+      flags,
+      max_referral_bps,
+      terms_hash,
+      external_ref,
+      hashlock,
+      recipient,
+      timeout,
+      authorizer,
+      arbiter,
+      arbiter_fee_bps,
     })
   }
 }
 
+impl MakeInstructionData {
+  pub const LEN: usize = size_of::<u64>() * 3;
+  /// The optional terms hash and external reference
+  pub const TERMS_LEN: usize = size_of::<[u8; 32]>() + size_of::<[u8; 16]>();
+  /// The optional hashlock, recipient and timeout of a hash-time-locked escrow
+  pub const HASHLOCK_LEN: usize = size_of::<[u8; 32]>() + size_of::<Pubkey>() + size_of::<i64>();
+  /// The optional authorizer, whose signed release Take then requires
+  pub const AUTHORIZER_LEN: usize = size_of::<Pubkey>();
+  /// The optional arbiter and its fee, which make Take hold the taker's
+  /// payment until Release or Resolve
+  pub const ARBITER_LEN: usize = size_of::<Pubkey>() + size_of::<u16>();
+  /// The maker accepts mints with Token-2022 extensions that put the deposit
+  /// or the payment at risk, see `MintInterface::check_safe_extensions`.
+  pub const ALLOW_UNSAFE_MINT_EXTENSIONS: u8 = 1 << 0;
+
+  pub fn is_hashlocked(&self) -> bool {
+    self.hashlock != [0; 32]
+  }
+
+  pub fn is_arbitrated(&self) -> bool {
+    self.arbiter != [0; 32]
+  }
+
+  pub fn allows_unsafe_mint_extensions(&self) -> bool {
+    self.flags & Self::ALLOW_UNSAFE_MINT_EXTENSIONS != 0
+  }
+}
+
 pub struct Make<'a> {
   pub accounts: MakeAccounts<'a>,
   pub instruction_data: MakeInstructionData,
   pub bump: u8,
+  /// The maker's current `MakerState` nonce
+  pub maker_nonce: u64,
 }
  
 impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Make<'a> {
@@ -90,8 +209,27 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Make<'a> {
     let accounts = MakeAccounts::try_from(accounts)?;
     let instruction_data = MakeInstructionData::try_from(data)?;
     log!("All accounts and instruction data parsed");
+    // A hashlock that already timed out could never be claimed
+    if instruction_data.is_hashlocked() && Clock::get()?.unix_timestamp >= instruction_data.timeout {
+      return Err(EscrowError::HashlockExpired.into());
+    }
+    let maker_nonce = MakerStateAccount::nonce(accounts.maker_state, accounts.maker)?;
+    let enforcement = ConfigAccount::enforcement(accounts.config)?;
+    MintPolicyAccount::enforce(accounts.mint_a_policy, accounts.mint_a, instruction_data.amount, enforcement)?;
+    MintPolicyAccount::enforce(accounts.mint_b_policy, accounts.mint_b, instruction_data.receive, enforcement)?;
+    if !instruction_data.allows_unsafe_mint_extensions() {
+      MintInterface::check_safe_extensions(accounts.mint_a)?;
+      MintInterface::check_safe_extensions(accounts.mint_b)?;
+    }
     // Initialize the Accounts needed
-    let (_, bump) = find_program_address(&[b"escrow", accounts.maker.key(), &instruction_data.seed.to_le_bytes()], &crate::ID);
+    let (escrow_key, bump) = find_program_address(&[b"escrow", accounts.maker.key(), &instruction_data.seed.to_le_bytes()], &crate::ID);
+    if &escrow_key != accounts.escrow.key() {
+      return Err(EscrowError::EscrowPdaMismatch.into());
+    }
+    // The maker already has an open escrow with this seed
+    if accounts.escrow.is_owned_by(&crate::ID) || accounts.escrow.data_len() != 0 {
+      return Err(EscrowError::SeedCollision.into());
+    }
  
     let seed_binding = instruction_data.seed.to_le_bytes();
     let bump_binding = [bump];
@@ -127,6 +265,7 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Make<'a> {
       accounts,
       instruction_data,
       bump,
+      maker_nonce,
     })
   }
 }
@@ -147,14 +286,33 @@ impl<'a> Make<'a> {
       self.instruction_data.receive,
       [self.bump],
     );
+    escrow.set_max_referral_bps(self.instruction_data.max_referral_bps);
+    escrow.set_terms(self.instruction_data.terms_hash, self.instruction_data.external_ref);
+    escrow.set_hashlock(self.instruction_data.hashlock, self.instruction_data.recipient, self.instruction_data.timeout);
+    escrow.set_authorizer(self.instruction_data.authorizer);
+    escrow.set_maker_nonce(self.maker_nonce);
+    escrow.set_arbiter(self.instruction_data.arbiter, self.instruction_data.arbiter_fee_bps);
  
     // Transfer tokens to vault
-    Transfer {
+    TransferChecked {
       from: self.accounts.maker_ata_a,
+      mint: self.accounts.mint_a,
       to: self.accounts.vault,
       authority: self.accounts.maker,
-      amount: self.instruction_data.amount
+      amount: self.instruction_data.amount,
+      decimals: MintInterface::decimals(self.accounts.mint_a)?,
+      token_program: self.accounts.token_program.key(),
     }.invoke()?;
+
+    MakeEvent {
+      escrow: self.accounts.escrow.key(),
+      maker: self.accounts.maker.key(),
+      seed: self.instruction_data.seed,
+      amount: self.instruction_data.amount,
+      receive: self.instruction_data.receive,
+      terms_hash: &self.instruction_data.terms_hash,
+      external_ref: &self.instruction_data.external_ref,
+    }.emit();
  
     Ok(())
   }
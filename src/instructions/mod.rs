@@ -2,3 +2,17 @@ pub mod make;
 pub mod helpers;
 pub mod take;
 pub mod refund;
+pub mod initialize_config;
+pub mod set_pause;
+pub mod set_enforcement;
+pub mod set_mint_policy;
+pub mod add_denylist_entry;
+pub mod remove_denylist_entry;
+pub mod close_receipt;
+pub mod claim;
+pub mod fill_signed_order;
+pub mod cancel_all;
+pub mod release;
+pub mod resolve;
+pub mod cancel_order;
+pub mod close_order_nonce;
@@ -1,7 +1,7 @@
-use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::create_program_address, ProgramResult};
-use pinocchio_token::{instructions::{CloseAccount, Transfer}, state::TokenAccount};
+use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::create_program_address, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+use pinocchio_token_2022::instructions::{CloseAccount, TransferChecked};
 
-use crate::{helpers::{AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, MintInterface, ProgramAccount, SignerAccount}, Escrow};
+use crate::{errors::EscrowError, helpers::{AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, ConfigAccount, MintInterface, ProgramAccount, SignerAccount, TokenAccountInterface, TokenProgram}, Config, Escrow};
 
 use pinocchio_log::log;
 
@@ -13,26 +13,34 @@ pub struct RefundAccounts<'a> {
     pub maker_ata_a: &'a AccountInfo,
     pub system_program: &'a AccountInfo,
     pub token_program: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    /// Only needed when `maker_ata_a` requires memos
+    pub memo_program: Option<&'a AccountInfo>,
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for RefundAccounts<'a> {
   type Error = ProgramError;
  
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-    let [maker, escrow, mint_a, vault, maker_ata_a, system_program, token_program, _] = accounts else {
+    let [maker, escrow, mint_a, vault, maker_ata_a, system_program, token_program, _, config, remaining @ ..] = accounts else {
       return Err(ProgramError::NotEnoughAccountKeys);
     };
     log!("RefundAccounts: checking maker_ata_a account");
 
     // Basic Accounts Checks
     SignerAccount::check(maker)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_REFUND)?;
     log!("SignerAccount check passed");
     ProgramAccount::check(escrow)?;
     log!("ProgramAccount check passed");
     MintInterface::check(mint_a)?;
     log!("MintInterface check passed");
-    //AssociatedTokenAccount::check(maker_ata_a, maker, mint_a, token_program)?;
-    //log!("AssociatedTokenAccount check passed");
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    // maker_ata_a may not exist yet, so only its address is checked here.
+    // init_if_needed validates the account itself before the transfer.
+    AssociatedTokenAccount::check_address(maker_ata_a, maker, mint_a, token_program)?;
+    log!("maker_ata_a address check passed");
     
     log!("RefundAccounts: checking vault account");
     log!("Vault key: {}", vault.key());
@@ -50,6 +58,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RefundAccounts<'a> {
       maker_ata_a,
       system_program,
       token_program,
+      config,
+      memo_program: remaining.first(),
     })
   }
 }
@@ -82,17 +92,36 @@ impl<'a> TryFrom<&'a [AccountInfo]> for Refund<'a> {
 
 impl<'a> Refund<'a> {
   pub const DISCRIMINATOR: &'a u8 = &2;
+  pub const MEMO: &'a [u8] = b"Escrow refund";
   
   pub fn process(&mut self) -> ProgramResult {
     // Populate the escrow account
     log!("Refund: processing refund");
+    // Escrows made before the layout grew must still be refundable
     let data = self.accounts.escrow.try_borrow_data()?;
-    let escrow = Escrow::load(&data)?;
+    let escrow = Escrow::read(&data)?;
     
+    // Check the escrow belongs to this maker and holds this mint
+    if escrow.maker.ne(self.accounts.maker.key()) {
+      return Err(EscrowError::MakerMismatch.into());
+    }
+    if escrow.mint_a.ne(self.accounts.mint_a.key()) {
+      return Err(EscrowError::MintMismatch.into());
+    }
+    // The recipient may claim a hashlocked escrow until its timeout
+    if escrow.is_hashlocked() && Clock::get()?.unix_timestamp < escrow.timeout {
+      return Err(EscrowError::HashlockNotExpired.into());
+    }
+    // The taker's payment is in the escrow, only Release or Resolve unwinds it
+    if escrow.is_taken() {
+      return Err(EscrowError::EscrowAlreadyTaken.into());
+    }
+
     // Check if the escrow is valid
-    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)?;
+    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)
+      .map_err(|_| EscrowError::EscrowPdaMismatch)?;
     if &escrow_key != self.accounts.escrow.key() {
-      return Err(ProgramError::InvalidAccountOwner);
+      return Err(EscrowError::EscrowPdaMismatch.into());
     }
     
     let seed_binding = escrow.seed.to_le_bytes();
@@ -107,22 +136,28 @@ impl<'a> Refund<'a> {
     
     let signer = Signer::from(&escrow_seeds);
     // get the amount to be refunded from the vault
-    let amount = TokenAccount::from_account_info(self.accounts.vault)?.amount();
+    let amount = TokenAccountInterface::amount(self.accounts.vault)?;
     
     // Transfer tokens from vault to maker
-    Transfer {
+    TokenAccountInterface::memo_if_required(self.accounts.maker_ata_a, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
       from: self.accounts.vault,
+      mint: self.accounts.mint_a,
       to: self.accounts.maker_ata_a,
       authority: self.accounts.escrow,
       amount,
-    }.invoke_signed(&[signer.clone()])?;
+      decimals: MintInterface::decimals(self.accounts.mint_a)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
 
-    // Close the Vault
+    // Close the Vault, once any transfer fees withheld on it are harvested
+    TokenAccountInterface::harvest_withheld_fees(self.accounts.vault, self.accounts.mint_a, self.accounts.token_program)?;
     CloseAccount {
       account: self.accounts.vault,
       destination: self.accounts.maker,
       authority: self.accounts.escrow,
-    }.invoke_signed(&[signer.clone()])?;
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
     
     // Close the Escrow
     drop(data);
@@ -0,0 +1,201 @@
+use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::create_program_address, ProgramResult};
+use pinocchio_log::log;
+use pinocchio_token_2022::instructions::{CloseAccount, TransferChecked};
+
+use crate::{errors::{EscrowError, PinocchioError}, events::ReleaseEvent, helpers::{AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, ConfigAccount, MintInterface, ProgramAccount, SignerAccount, TokenAccountInterface, TokenProgram}, Config, Escrow};
+
+pub struct ReleaseAccounts<'a> {
+  pub maker: &'a AccountInfo,
+  pub taker: &'a AccountInfo,
+  pub escrow: &'a AccountInfo,
+  pub mint_a: &'a AccountInfo,
+  pub mint_b: &'a AccountInfo,
+  pub vault_a: &'a AccountInfo,
+  pub vault_b: &'a AccountInfo,
+  pub maker_ata_b: &'a AccountInfo,
+  pub taker_ata_a: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+  pub token_program: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  /// Only needed when `maker_ata_b` or `taker_ata_a` requires memos
+  pub memo_program: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ReleaseAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [maker, taker, escrow, mint_a, mint_b, vault_a, vault_b, maker_ata_b, taker_ata_a, system_program, token_program, _, config, remaining @ ..] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Basic Accounts Checks. Releasing takes both sides of the trade.
+    SignerAccount::check(maker)?;
+    SignerAccount::check(taker)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_TAKE)?;
+    ProgramAccount::check(escrow)?;
+    MintInterface::check(mint_a)?;
+    MintInterface::check(mint_b)?;
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    MintInterface::check_program(mint_b, token_program)?;
+    AssociatedTokenAccount::check(vault_a, escrow, mint_a, token_program)?;
+    AssociatedTokenAccount::check(vault_b, escrow, mint_b, token_program)?;
+    // maker_ata_b and taker_ata_a may not exist yet, so only their addresses
+    // are checked here
+    AssociatedTokenAccount::check_address(maker_ata_b, maker, mint_b, token_program)?;
+    AssociatedTokenAccount::check_address(taker_ata_a, taker, mint_a, token_program)?;
+
+    Ok(Self {
+      maker,
+      taker,
+      escrow,
+      mint_a,
+      mint_b,
+      vault_a,
+      vault_b,
+      maker_ata_b,
+      taker_ata_a,
+      system_program,
+      token_program,
+      config,
+      memo_program: remaining.first(),
+    })
+  }
+}
+
+pub struct Release<'a> {
+  pub accounts: ReleaseAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for Release<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let accounts = ReleaseAccounts::try_from(accounts)?;
+
+    // Each side pays for the account it receives in
+    AssociatedTokenAccount::init_if_needed(
+      accounts.taker_ata_a,
+      accounts.mint_a,
+      accounts.taker,
+      accounts.taker,
+      accounts.system_program,
+      accounts.token_program,
+    )?;
+
+    AssociatedTokenAccount::init_if_needed(
+      accounts.maker_ata_b,
+      accounts.mint_b,
+      accounts.maker,
+      accounts.maker,
+      accounts.system_program,
+      accounts.token_program,
+    )?;
+
+    Ok(Self { accounts })
+  }
+}
+
+impl<'a> Release<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &13;
+  pub const MEMO: &'a [u8] = b"Escrow release";
+
+  pub fn process(&mut self) -> ProgramResult {
+    log!("Release: process started");
+    let data = self.accounts.escrow.try_borrow_data()?;
+    let escrow = Escrow::load(&data)?;
+
+    // Check the escrow is an arbitrated one these two traded through
+    if escrow.maker.ne(self.accounts.maker.key()) {
+      return Err(EscrowError::MakerMismatch.into());
+    }
+    if escrow.mint_a.ne(self.accounts.mint_a.key()) || escrow.mint_b.ne(self.accounts.mint_b.key()) {
+      return Err(EscrowError::MintMismatch.into());
+    }
+    if !escrow.is_arbitrated() {
+      return Err(EscrowError::NotArbitrated.into());
+    }
+    if !escrow.is_taken() {
+      return Err(EscrowError::EscrowNotTaken.into());
+    }
+    if escrow.taker.ne(self.accounts.taker.key()) {
+      return Err(PinocchioError::InvalidAddress.into());
+    }
+
+    // Check if the escrow is valid
+    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)
+      .map_err(|_| EscrowError::EscrowPdaMismatch)?;
+    if &escrow_key != self.accounts.escrow.key() {
+      return Err(EscrowError::EscrowPdaMismatch.into());
+    }
+
+    let seed_binding = escrow.seed.to_le_bytes();
+    let bump_binding = escrow.bump;
+    let escrow_seeds = [
+      Seed::from(b"escrow"),
+      Seed::from(self.accounts.maker.key().as_ref()),
+      Seed::from(&seed_binding),
+      Seed::from(&bump_binding),
+    ];
+    let signer = Signer::from(&escrow_seeds);
+
+    // Transfer the deposit to the Taker
+    let amount_a = TokenAccountInterface::amount(self.accounts.vault_a)?;
+    TokenAccountInterface::memo_if_required(self.accounts.taker_ata_a, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
+      from: self.accounts.vault_a,
+      mint: self.accounts.mint_a,
+      to: self.accounts.taker_ata_a,
+      authority: self.accounts.escrow,
+      amount: amount_a,
+      decimals: MintInterface::decimals(self.accounts.mint_a)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    // Transfer the payment to the Maker
+    let amount_b = TokenAccountInterface::amount(self.accounts.vault_b)?;
+    TokenAccountInterface::memo_if_required(self.accounts.maker_ata_b, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
+      from: self.accounts.vault_b,
+      mint: self.accounts.mint_b,
+      to: self.accounts.maker_ata_b,
+      authority: self.accounts.escrow,
+      amount: amount_b,
+      decimals: MintInterface::decimals(self.accounts.mint_b)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    // Close both Vaults, each back to whoever paid its rent, once any
+    // transfer fees withheld on them are harvested
+    TokenAccountInterface::harvest_withheld_fees(self.accounts.vault_a, self.accounts.mint_a, self.accounts.token_program)?;
+    CloseAccount {
+      account: self.accounts.vault_a,
+      destination: self.accounts.maker,
+      authority: self.accounts.escrow,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    TokenAccountInterface::harvest_withheld_fees(self.accounts.vault_b, self.accounts.mint_b, self.accounts.token_program)?;
+    CloseAccount {
+      account: self.accounts.vault_b,
+      destination: self.accounts.taker,
+      authority: self.accounts.escrow,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    ReleaseEvent {
+      escrow: self.accounts.escrow.key(),
+      maker: self.accounts.maker.key(),
+      taker: self.accounts.taker.key(),
+      amount_a,
+      amount_b,
+    }.emit();
+
+    // Close the Escrow, whose rent the maker paid
+    drop(data);
+    ProgramAccount::close(self.accounts.escrow, self.accounts.maker)?;
+
+    Ok(())
+  }
+}
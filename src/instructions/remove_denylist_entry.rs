@@ -0,0 +1,59 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{add_denylist_entry::DenylistEntryInstructionData, helpers::{AccountCheck, AccountClose, ConfigAccount, DenylistEntryAccount, ProgramAccount, SignerAccount}};
+
+pub struct RemoveDenylistEntryAccounts<'a> {
+  pub admin: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub denylist_entry: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for RemoveDenylistEntryAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [admin, config, denylist_entry] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(admin)?;
+    ConfigAccount::check_admin(config, admin)?;
+    ProgramAccount::check(denylist_entry)?;
+
+    Ok(Self {
+      admin,
+      config,
+      denylist_entry,
+    })
+  }
+}
+
+pub struct RemoveDenylistEntry<'a> {
+  pub accounts: RemoveDenylistEntryAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for RemoveDenylistEntry<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = RemoveDenylistEntryAccounts::try_from(accounts)?;
+    let instruction_data = DenylistEntryInstructionData::try_from(data)?;
+    DenylistEntryAccount::check(accounts.denylist_entry, &instruction_data.wallet)?;
+
+    Ok(Self { accounts })
+  }
+}
+
+impl<'a> RemoveDenylistEntry<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &8;
+
+  pub fn process(&mut self) -> ProgramResult {
+    // Closing the entry leaves an empty system account, which proves the
+    // wallet isn't denied again
+    ProgramAccount::close(self.accounts.denylist_entry, self.accounts.admin)?;
+    log!("Denylist entry removed");
+
+    Ok(())
+  }
+}
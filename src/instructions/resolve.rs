@@ -0,0 +1,251 @@
+use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::create_program_address, ProgramResult};
+use pinocchio_log::log;
+use pinocchio_token_2022::instructions::{CloseAccount, TransferChecked};
+
+use crate::{errors::{EscrowError, PinocchioError}, events::ResolveEvent, helpers::{AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, ConfigAccount, MintInterface, ProgramAccount, SignerAccount, TokenAccountInterface, TokenProgram}, Config, Escrow};
+
+pub struct ResolveAccounts<'a> {
+  pub arbiter: &'a AccountInfo,
+  pub maker: &'a AccountInfo,
+  pub taker: &'a AccountInfo,
+  pub escrow: &'a AccountInfo,
+  pub mint_a: &'a AccountInfo,
+  pub mint_b: &'a AccountInfo,
+  pub vault_a: &'a AccountInfo,
+  pub vault_b: &'a AccountInfo,
+  pub maker_ata_a: &'a AccountInfo,
+  pub maker_ata_b: &'a AccountInfo,
+  pub taker_ata_a: &'a AccountInfo,
+  pub taker_ata_b: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+  pub token_program: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  /// Only needed when a token account paid requires memos
+  pub memo_program: Option<&'a AccountInfo>,
+  /// The arbiter's associated token account for mint B, created if needed.
+  /// Only needed when the escrow pays the arbiter a fee
+  pub arbiter_ata_b: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ResolveAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [arbiter, maker, taker, escrow, mint_a, mint_b, vault_a, vault_b, maker_ata_a, maker_ata_b, taker_ata_a, taker_ata_b, system_program, token_program, _, config, remaining @ ..] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Basic Accounts Checks
+    SignerAccount::check(arbiter)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_TAKE)?;
+    ProgramAccount::check(escrow)?;
+    MintInterface::check(mint_a)?;
+    MintInterface::check(mint_b)?;
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    MintInterface::check_program(mint_b, token_program)?;
+    AssociatedTokenAccount::check(vault_a, escrow, mint_a, token_program)?;
+    AssociatedTokenAccount::check(vault_b, escrow, mint_b, token_program)?;
+    // The maker's and taker's accounts may not exist yet, so only their
+    // addresses are checked here
+    AssociatedTokenAccount::check_address(maker_ata_a, maker, mint_a, token_program)?;
+    AssociatedTokenAccount::check_address(maker_ata_b, maker, mint_b, token_program)?;
+    AssociatedTokenAccount::check_address(taker_ata_a, taker, mint_a, token_program)?;
+    AssociatedTokenAccount::check_address(taker_ata_b, taker, mint_b, token_program)?;
+
+    Ok(Self {
+      arbiter,
+      maker,
+      taker,
+      escrow,
+      mint_a,
+      mint_b,
+      vault_a,
+      vault_b,
+      maker_ata_a,
+      maker_ata_b,
+      taker_ata_a,
+      taker_ata_b,
+      system_program,
+      token_program,
+      config,
+      memo_program: remaining.first(),
+      arbiter_ata_b: remaining.get(1),
+    })
+  }
+}
+
+pub struct ResolveInstructionData {
+  /// Share of the trade that completes, in basis points: that share of the
+  /// deposit goes to the taker and of the payment to the maker, the rest of
+  /// each back to whoever put it in
+  pub settle_bps: u16,
+}
+
+impl<'a> TryFrom<&'a [u8]> for ResolveInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let settle_bps = u16::from_le_bytes(data.try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+    if settle_bps > Escrow::MAX_BPS {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self { settle_bps })
+  }
+}
+
+impl ResolveInstructionData {
+  pub const LEN: usize = size_of::<u16>();
+  /// Rules for the taker: the trade completes as Release would
+  pub const TO_TAKER: u16 = Escrow::MAX_BPS;
+  /// Rules for the maker: the trade unwinds and each side gets its tokens back
+  pub const TO_MAKER: u16 = 0;
+}
+
+pub struct Resolve<'a> {
+  pub accounts: ResolveAccounts<'a>,
+  pub instruction_data: ResolveInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Resolve<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = ResolveAccounts::try_from(accounts)?;
+    let instruction_data = ResolveInstructionData::try_from(data)?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+    })
+  }
+}
+
+impl<'a> Resolve<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &14;
+  pub const MEMO: &'a [u8] = b"Escrow resolve";
+
+  /// Pays `amount` out of `vault` to `owner`'s associated token account,
+  /// which the arbiter creates if needed. Nothing happens for zero.
+  fn pay(&self, vault: &AccountInfo, mint: &AccountInfo, to: &AccountInfo, owner: &AccountInfo, amount: u64, signer: &Signer) -> ProgramResult {
+    if amount == 0 {
+      return Ok(());
+    }
+    AssociatedTokenAccount::init_if_needed(
+      to,
+      mint,
+      self.accounts.arbiter,
+      owner,
+      self.accounts.system_program,
+      self.accounts.token_program,
+    )?;
+    TokenAccountInterface::memo_if_required(to, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
+      from: vault,
+      mint,
+      to,
+      authority: self.accounts.escrow,
+      amount,
+      decimals: MintInterface::decimals(mint)?,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(signer))
+  }
+
+  pub fn process(&mut self) -> ProgramResult {
+    log!("Resolve: process started");
+    let data = self.accounts.escrow.try_borrow_data()?;
+    let escrow = Escrow::load(&data)?;
+
+    // Check the escrow is a taken one this arbiter rules on
+    if escrow.maker.ne(self.accounts.maker.key()) {
+      return Err(EscrowError::MakerMismatch.into());
+    }
+    if escrow.mint_a.ne(self.accounts.mint_a.key()) || escrow.mint_b.ne(self.accounts.mint_b.key()) {
+      return Err(EscrowError::MintMismatch.into());
+    }
+    if !escrow.is_arbitrated() {
+      return Err(EscrowError::NotArbitrated.into());
+    }
+    if escrow.arbiter.ne(self.accounts.arbiter.key()) {
+      return Err(EscrowError::Unauthorized.into());
+    }
+    if !escrow.is_taken() {
+      return Err(EscrowError::EscrowNotTaken.into());
+    }
+    if escrow.taker.ne(self.accounts.taker.key()) {
+      return Err(PinocchioError::InvalidAddress.into());
+    }
+
+    // Check if the escrow is valid
+    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)
+      .map_err(|_| EscrowError::EscrowPdaMismatch)?;
+    if &escrow_key != self.accounts.escrow.key() {
+      return Err(EscrowError::EscrowPdaMismatch.into());
+    }
+
+    let seed_binding = escrow.seed.to_le_bytes();
+    let bump_binding = escrow.bump;
+    let escrow_seeds = [
+      Seed::from(b"escrow"),
+      Seed::from(self.accounts.maker.key().as_ref()),
+      Seed::from(&seed_binding),
+      Seed::from(&bump_binding),
+    ];
+    let signer = Signer::from(&escrow_seeds);
+
+    // The arbiter's fee comes off the part of the payment the maker gets, so
+    // a trade that unwinds costs the taker nothing, then both vaults split
+    let settle_bps = self.instruction_data.settle_bps;
+    let deposit = TokenAccountInterface::amount(self.accounts.vault_a)?;
+    let payment = TokenAccountInterface::amount(self.accounts.vault_b)?;
+    let to_taker_a = Escrow::share(deposit, settle_bps);
+    let settled_b = Escrow::share(payment, settle_bps);
+    let arbiter_fee = escrow.arbiter_fee(settled_b);
+    let to_maker_b = settled_b - arbiter_fee;
+
+    if arbiter_fee > 0 {
+      let arbiter_ata_b = self.accounts.arbiter_ata_b.ok_or(EscrowError::ArbiterAccountMissing)?;
+      AssociatedTokenAccount::check_address(arbiter_ata_b, self.accounts.arbiter, self.accounts.mint_b, self.accounts.token_program)?;
+      self.pay(self.accounts.vault_b, self.accounts.mint_b, arbiter_ata_b, self.accounts.arbiter, arbiter_fee, &signer)?;
+    }
+    self.pay(self.accounts.vault_a, self.accounts.mint_a, self.accounts.taker_ata_a, self.accounts.taker, to_taker_a, &signer)?;
+    self.pay(self.accounts.vault_a, self.accounts.mint_a, self.accounts.maker_ata_a, self.accounts.maker, deposit - to_taker_a, &signer)?;
+    self.pay(self.accounts.vault_b, self.accounts.mint_b, self.accounts.maker_ata_b, self.accounts.maker, to_maker_b, &signer)?;
+    self.pay(self.accounts.vault_b, self.accounts.mint_b, self.accounts.taker_ata_b, self.accounts.taker, payment - settled_b, &signer)?;
+    log!("Resolve: settled {} bps, paid the arbiter {}", settle_bps, arbiter_fee);
+
+    // Close both Vaults, each back to whoever paid its rent, once any
+    // transfer fees withheld on them are harvested
+    TokenAccountInterface::harvest_withheld_fees(self.accounts.vault_a, self.accounts.mint_a, self.accounts.token_program)?;
+    CloseAccount {
+      account: self.accounts.vault_a,
+      destination: self.accounts.maker,
+      authority: self.accounts.escrow,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    TokenAccountInterface::harvest_withheld_fees(self.accounts.vault_b, self.accounts.mint_b, self.accounts.token_program)?;
+    CloseAccount {
+      account: self.accounts.vault_b,
+      destination: self.accounts.taker,
+      authority: self.accounts.escrow,
+      token_program: self.accounts.token_program.key(),
+    }.invoke_signed(core::slice::from_ref(&signer))?;
+
+    ResolveEvent {
+      escrow: self.accounts.escrow.key(),
+      arbiter: self.accounts.arbiter.key(),
+      settle_bps,
+      amount_a: to_taker_a,
+      amount_b: to_maker_b,
+      arbiter_fee,
+    }.emit();
+
+    // Close the Escrow, whose rent the maker paid
+    drop(data);
+    ProgramAccount::close(self.accounts.escrow, self.accounts.maker)?;
+
+    Ok(())
+  }
+}
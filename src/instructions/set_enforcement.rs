@@ -0,0 +1,80 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{helpers::{AccountCheck, ConfigAccount, SignerAccount}, Config};
+
+pub struct SetEnforcementAccounts<'a> {
+  pub admin: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetEnforcementAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [admin, config] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(admin)?;
+    ConfigAccount::check_admin(config, admin)?;
+
+    Ok(Self { admin, config })
+  }
+}
+
+pub struct SetEnforcementInstructionData {
+  /// `Config::ENFORCE_*` bits of the policies to enforce, every other
+  /// policy is lifted
+  pub enforce: u8,
+}
+
+impl<'a> TryFrom<&'a [u8]> for SetEnforcementInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let [enforce] = data else {
+      return Err(ProgramError::InvalidInstructionData);
+    };
+    if enforce & !Config::ENFORCE_ALL != 0 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+    // Requiring policies means nothing unless they're enforced
+    if enforce & Config::REQUIRE_MINT_POLICY != 0 && enforce & Config::ENFORCE_MINT_POLICY == 0 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self { enforce: *enforce })
+  }
+}
+
+pub struct SetEnforcement<'a> {
+  pub accounts: SetEnforcementAccounts<'a>,
+  pub instruction_data: SetEnforcementInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SetEnforcement<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = SetEnforcementAccounts::try_from(accounts)?;
+    let instruction_data = SetEnforcementInstructionData::try_from(data)?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+    })
+  }
+}
+
+impl<'a> SetEnforcement<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &5;
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.config.try_borrow_mut_data()?;
+    Config::load_mut(data.as_mut())?.set_enforce(self.instruction_data.enforce);
+    log!("Enforced policies: {}", self.instruction_data.enforce);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,133 @@
+use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{helpers::{AccountCheck, ConfigAccount, MintInterface, MintPolicyAccount, ProgramAccount, ProgramAccountInit, SignerAccount}, MintPolicy};
+
+pub struct SetMintPolicyAccounts<'a> {
+  pub admin: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub mint: &'a AccountInfo,
+  pub mint_policy: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetMintPolicyAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [admin, config, mint, mint_policy, system_program] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(admin)?;
+    ConfigAccount::check_admin(config, admin)?;
+    MintInterface::check(mint)?;
+
+    Ok(Self {
+      admin,
+      config,
+      mint,
+      mint_policy,
+      system_program,
+    })
+  }
+}
+
+pub struct SetMintPolicyInstructionData {
+  pub allowed: u8,
+  pub min_amount: u64,
+  pub max_amount: u64,
+}
+
+impl<'a> TryFrom<&'a [u8]> for SetMintPolicyInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    if data.len() != Self::LEN {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let allowed = data[0];
+    let min_amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+    let max_amount = u64::from_le_bytes(data[9..17].try_into().unwrap());
+
+    if allowed > 1 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+    if max_amount != 0 && min_amount > max_amount {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self {
+      allowed,
+      min_amount,
+      max_amount,
+    })
+  }
+}
+
+impl SetMintPolicyInstructionData {
+  pub const LEN: usize = size_of::<u8>() + size_of::<u64>() * 2;
+}
+
+pub struct SetMintPolicy<'a> {
+  pub accounts: SetMintPolicyAccounts<'a>,
+  pub instruction_data: SetMintPolicyInstructionData,
+  pub bump: u8,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SetMintPolicy<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = SetMintPolicyAccounts::try_from(accounts)?;
+    let instruction_data = SetMintPolicyInstructionData::try_from(data)?;
+    let bump = MintPolicyAccount::check(accounts.mint_policy, accounts.mint)?;
+
+    // The first policy for a mint creates its account, later ones update it
+    if accounts.mint_policy.data_is_empty() {
+      let bump_binding = [bump];
+      let policy_seeds = [
+        Seed::from(MintPolicy::SEED),
+        Seed::from(accounts.mint.key().as_ref()),
+        Seed::from(&bump_binding),
+      ];
+
+      ProgramAccount::init::<MintPolicy>(
+        accounts.admin,
+        accounts.mint_policy,
+        &policy_seeds,
+        MintPolicy::LEN
+      )?;
+      log!("Mint policy account initialized");
+    } else {
+      ProgramAccount::check(accounts.mint_policy)?;
+    }
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+      bump,
+    })
+  }
+}
+
+impl<'a> SetMintPolicy<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &6;
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.mint_policy.try_borrow_mut_data()?;
+    let policy = MintPolicy::load_mut(data.as_mut())?;
+
+    policy.set_inner(
+      *self.accounts.mint.key(),
+      self.instruction_data.allowed,
+      self.instruction_data.min_amount,
+      self.instruction_data.max_amount,
+      [self.bump],
+    );
+    log!("Mint policy set, allowed: {}", self.instruction_data.allowed);
+
+    Ok(())
+  }
+}
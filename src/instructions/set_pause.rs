@@ -0,0 +1,76 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+use crate::{helpers::{AccountCheck, ConfigAccount, SignerAccount}, Config};
+
+pub struct SetPauseAccounts<'a> {
+  pub admin: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetPauseAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [admin, config] = accounts else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    SignerAccount::check(admin)?;
+    ConfigAccount::check_admin(config, admin)?;
+
+    Ok(Self { admin, config })
+  }
+}
+
+pub struct SetPauseInstructionData {
+  /// `Config::PAUSE_*` bits of the instructions to pause, every other
+  /// instruction is unpaused
+  pub paused: u8,
+}
+
+impl<'a> TryFrom<&'a [u8]> for SetPauseInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    let [paused] = data else {
+      return Err(ProgramError::InvalidInstructionData);
+    };
+    if paused & !Config::PAUSE_ALL != 0 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self { paused: *paused })
+  }
+}
+
+pub struct SetPause<'a> {
+  pub accounts: SetPauseAccounts<'a>,
+  pub instruction_data: SetPauseInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SetPause<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = SetPauseAccounts::try_from(accounts)?;
+    let instruction_data = SetPauseInstructionData::try_from(data)?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+    })
+  }
+}
+
+impl<'a> SetPause<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &4;
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.config.try_borrow_mut_data()?;
+    Config::load_mut(data.as_mut())?.set_paused(self.instruction_data.paused);
+    log!("Paused instructions: {}", self.instruction_data.paused);
+
+    Ok(())
+  }
+}
@@ -1,8 +1,8 @@
-use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::create_program_address, ProgramResult};
+use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::{create_program_address, Pubkey}, sysvars::{clock::Clock, Sysvar}, ProgramResult};
 use pinocchio_log::log;
-use pinocchio_token::{instructions::{CloseAccount, Transfer}, state::TokenAccount};
+use pinocchio_token_2022::instructions::{CloseAccount, TransferChecked};
 
-use crate::{helpers::{AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, MintInterface, ProgramAccount, SignerAccount}, Escrow};
+use crate::{errors::EscrowError, helpers::{sha256, AccountCheck, AccountClose, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, ConfigAccount, DenylistEntryAccount, Ed25519Instructions, MakerStateAccount, MintInterface, ProgramAccount, ProgramAccountInit, ReceiptAccount, SignerAccount, TokenAccountInterface, TokenProgram}, events::TakeEvent, Config, Escrow, Receipt};
 
 pub struct TakeAccounts<'a> {
   pub taker: &'a AccountInfo,
@@ -16,23 +16,47 @@ pub struct TakeAccounts<'a> {
   pub maker_ata_b: &'a AccountInfo,
   pub system_program: &'a AccountInfo,
   pub token_program: &'a AccountInfo,
+  pub config: &'a AccountInfo,
+  pub taker_denylist_entry: &'a AccountInfo,
+  pub maker_state: &'a AccountInfo,
+  /// Only needed when `taker_ata_a`, `maker_ata_b` or `referrer_ata_b`
+  /// requires memos
+  pub memo_program: Option<&'a AccountInfo>,
+  /// Only needed when the taker pays a referral fee
+  pub referrer_ata_b: Option<&'a AccountInfo>,
+  /// Only needed when the taker asks for a receipt
+  pub receipt: Option<&'a AccountInfo>,
+  /// Only needed when the escrow has an authorizer
+  pub instructions_sysvar: Option<&'a AccountInfo>,
+  /// The escrow's token account for mint B, holding the payment until
+  /// Release or Resolve. Only needed when the escrow has an arbiter
+  pub vault_b: Option<&'a AccountInfo>,
 }
  
 impl<'a> TryFrom<&'a [AccountInfo]> for TakeAccounts<'a> {
   type Error = ProgramError;
  
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-    let [taker, maker, escrow, mint_a, mint_b, vault, taker_ata_a, taker_ata_b, maker_ata_b, system_program, token_program, _] = accounts else {
+    let [taker, maker, escrow, mint_a, mint_b, vault, taker_ata_a, taker_ata_b, maker_ata_b, system_program, token_program, _, config, taker_denylist_entry, maker_state, remaining @ ..] = accounts else {
       return Err(ProgramError::NotEnoughAccountKeys);
     };
  
     // Basic Accounts Checks
     SignerAccount::check(taker)?;
+    ConfigAccount::check_not_paused(config, Config::PAUSE_TAKE)?;
+    DenylistEntryAccount::check_not_denied(taker_denylist_entry, taker)?;
     ProgramAccount::check(escrow)?;
     MintInterface::check(mint_a)?;
     MintInterface::check(mint_b)?;
+    TokenProgram::check(token_program)?;
+    MintInterface::check_program(mint_a, token_program)?;
+    MintInterface::check_program(mint_b, token_program)?;
     AssociatedTokenAccount::check(taker_ata_b, taker, mint_b, token_program)?;
     AssociatedTokenAccount::check(vault, escrow, mint_a, token_program)?;
+    // The taker's payment is a CPI transfer the taker signs for
+    if TokenAccountInterface::cpi_guard_enabled(taker_ata_b)? {
+      return Err(EscrowError::CpiGuardEnabled.into());
+    }
  
     // Return the accounts
     Ok(Self {
@@ -47,30 +71,83 @@ impl<'a> TryFrom<&'a [AccountInfo]> for TakeAccounts<'a> {
       vault,
       system_program,
       token_program,
+      config,
+      taker_denylist_entry,
+      maker_state,
+      memo_program: remaining.first(),
+      referrer_ata_b: remaining.get(1),
+      receipt: remaining.get(2),
+      instructions_sysvar: remaining.get(3),
+      vault_b: remaining.get(4),
+    })
+  }
+}
+
+pub struct TakeInstructionData {
+  pub referral_bps: u16,
+  pub flags: u8,
+}
+
+impl<'a> TryFrom<&'a [u8]> for TakeInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    // The referral fee is optional and defaults to none, as does the flags
+    // byte after it
+    let (referral_bps, flags) = match data.len() {
+      0 => (0, 0),
+      Self::LEN => (u16::from_le_bytes(data.try_into().unwrap()), 0),
+      len if len == Self::LEN + 1 => (u16::from_le_bytes(data[..Self::LEN].try_into().unwrap()), data[Self::LEN]),
+      _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    if flags & !Self::CREATE_RECEIPT != 0 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self {
+      referral_bps,
+      flags,
     })
   }
 }
 
+impl TakeInstructionData {
+  pub const LEN: usize = size_of::<u16>();
+  /// The taker pays for a `Receipt` recording the trade.
+  pub const CREATE_RECEIPT: u8 = 1 << 0;
+
+  pub fn creates_receipt(&self) -> bool {
+    self.flags & Self::CREATE_RECEIPT != 0
+  }
+}
+
 pub struct Take<'a> {
   pub accounts: TakeAccounts<'a>,
+  pub instruction_data: TakeInstructionData,
+  /// Set when the taker asked for a receipt
+  pub receipt_bump: Option<u8>,
 }
  
-impl<'a> TryFrom<&'a [AccountInfo]> for Take<'a> {
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Take<'a> {
   type Error = ProgramError;
   
-  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
     let accounts = TakeAccounts::try_from(accounts)?;
+    let instruction_data = TakeInstructionData::try_from(data)?;
+
+    // The referrer may hold any token account for mint B but the taker's,
+    // which would turn the fee into a discount on the maker's price
+    if instruction_data.referral_bps > 0 {
+      let referrer_ata_b = accounts.referrer_ata_b.ok_or(EscrowError::ReferrerMissing)?;
+      TokenAccountInterface::check(referrer_ata_b)?;
+      TokenAccountInterface::check_mint(referrer_ata_b, accounts.mint_b, accounts.token_program)?;
+      if TokenAccountInterface::owner(referrer_ata_b)?.eq(accounts.taker.key()) {
+        return Err(EscrowError::SelfReferral.into());
+      }
+    }
  
-    // Initialize necessary accounts
-    AssociatedTokenAccount::init_if_needed(
-      accounts.taker_ata_a,
-      accounts.mint_a,
-      accounts.taker,
-      accounts.taker,
-      accounts.system_program,
-      accounts.token_program,
-    )?;
- 
+    // Initialize necessary accounts. taker_ata_a waits for process, since an
+    // arbitrated escrow doesn't pay the taker yet.
     AssociatedTokenAccount::init_if_needed(
       accounts.maker_ata_b,
       accounts.mint_b,
@@ -80,24 +157,105 @@ impl<'a> TryFrom<&'a [AccountInfo]> for Take<'a> {
       accounts.token_program,
     )?;
  
+    let receipt_bump = if instruction_data.creates_receipt() {
+      let receipt = accounts.receipt.ok_or(EscrowError::ReceiptMissing)?;
+      let bump = ReceiptAccount::check(receipt, accounts.escrow, accounts.taker)?;
+      let bump_binding = [bump];
+      let receipt_seeds = [
+        Seed::from(Receipt::SEED),
+        Seed::from(accounts.escrow.key().as_ref()),
+        Seed::from(accounts.taker.key().as_ref()),
+        Seed::from(&bump_binding),
+      ];
+      ProgramAccount::init::<Receipt>(
+        accounts.taker,
+        receipt,
+        &receipt_seeds,
+        Receipt::LEN
+      )?;
+      Some(bump)
+    } else {
+      None
+    };
+
     Ok(Self {
       accounts,
+      instruction_data,
+      receipt_bump,
     })
   }
 }
 
 impl<'a> Take<'a> {
   pub const DISCRIMINATOR: &'a u8 = &1;
+  pub const MEMO: &'a [u8] = b"Escrow take";
+
+  /// Checks an earlier Ed25519 instruction in the transaction carries the
+  /// authorizer's unexpired release of this escrow, holding `escrow_data`,
+  /// to this taker.
+  fn check_authorization(&self, authorizer: &Pubkey, escrow_data: &[u8]) -> ProgramResult {
+    let instructions_sysvar = self.accounts.instructions_sysvar.ok_or(EscrowError::AuthorizationMissing)?;
+    let (escrow, taker) = (self.accounts.escrow.key(), self.accounts.taker.key());
+    let escrow_hash = sha256(escrow_data);
+    let message = Ed25519Instructions::find_signed::<{ Escrow::AUTHORIZATION_LEN }>(instructions_sysvar, authorizer, |message| {
+      message[..32].eq(escrow) && message[32..64].eq(taker) && message[64..96].eq(&escrow_hash)
+    })?
+    .ok_or(EscrowError::AuthorizationMissing)?;
+
+    let expiry = i64::from_le_bytes(message[96..].try_into().unwrap());
+    if Clock::get()?.unix_timestamp >= expiry {
+      return Err(EscrowError::AuthorizationExpired.into());
+    }
+    log!("Take: release authorized");
+    Ok(())
+  }
   
   pub fn process(&mut self) -> ProgramResult {
     log!("Take: process started");
     let data = self.accounts.escrow.try_borrow_data()?;
-    let escrow = Escrow::load(&data)?;
+    let escrow = Escrow::read(&data)?;
  
+    // Check the escrow belongs to this maker and trades these mints
+    if escrow.maker.ne(self.accounts.maker.key()) {
+      return Err(EscrowError::MakerMismatch.into());
+    }
+    if escrow.mint_a.ne(self.accounts.mint_a.key()) || escrow.mint_b.ne(self.accounts.mint_b.key()) {
+      return Err(EscrowError::MintMismatch.into());
+    }
+    if escrow.is_hashlocked() {
+      return Err(EscrowError::HashlockedEscrow.into());
+    }
+    if escrow.is_taken() {
+      return Err(EscrowError::EscrowAlreadyTaken.into());
+    }
+    // CancelAll moved the maker past this escrow, only Refund settles it now
+    if escrow.maker_nonce < MakerStateAccount::nonce(self.accounts.maker_state, self.accounts.maker)? {
+      return Err(EscrowError::EscrowCancelled.into());
+    }
+    // Release or Resolve may still unwind an arbitrated trade, so Take can't
+    // record it as completed
+    if self.receipt_bump.is_some() && escrow.is_arbitrated() {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+    let referral_bps = self.instruction_data.referral_bps;
+    if referral_bps > escrow.max_referral_bps {
+      return Err(EscrowError::ReferralFeeTooHigh.into());
+    }
+    // Resolve may still unwind an arbitrated trade, and a fee paid now
+    // couldn't be returned, so those escrows take none
+    if referral_bps > 0 && escrow.is_arbitrated() {
+      return Err(EscrowError::ReferralFeeTooHigh.into());
+    }
+
     // Check if the escrow is valid
-    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)?;
+    // A bump that derives no address can't be the escrow's either
+    let escrow_key = create_program_address(&[b"escrow", self.accounts.maker.key(), &escrow.seed.to_le_bytes(), &escrow.bump], &crate::ID)
+      .map_err(|_| EscrowError::EscrowPdaMismatch)?;
     if &escrow_key != self.accounts.escrow.key() {
-      return Err(ProgramError::InvalidAccountOwner);
+      return Err(EscrowError::EscrowPdaMismatch.into());
+    }
+    if escrow.requires_authorization() {
+      self.check_authorization(&escrow.authorizer, &data)?;
     }
     
     let seed_binding = escrow.seed.to_le_bytes();
@@ -110,33 +268,122 @@ impl<'a> Take<'a> {
     ];
     let signer = Signer::from(&escrow_seeds);
     
-    //let amount = TokenAccount::get_amount(self.accounts.vault);
-    let amount = TokenAccount::from_account_info(self.accounts.vault)?.amount();
-    // Transfer from the Vault to the Taker
-    Transfer {
-      from: self.accounts.vault,
-      to: self.accounts.taker_ata_a,
-      authority: self.accounts.escrow,
-      amount,
-    }.invoke_signed(&[signer.clone()])?;
- 
-    // Close the Vault
-    CloseAccount {
-      account: self.accounts.vault,
-      destination: self.accounts.maker,
-      authority: self.accounts.escrow,
-    }.invoke_signed(&[signer.clone()])?;
+    let amount = TokenAccountInterface::amount(self.accounts.vault)?;
+    // Never let the taker pay for an empty vault
+    if amount == 0 {
+      return Err(EscrowError::VaultBalanceMismatch.into());
+    }
+    // An arbitrated escrow keeps the deposit, and holds the payment in a
+    // second vault until Release or Resolve
+    let payee_ata_b = if escrow.is_arbitrated() {
+      let vault_b = self.accounts.vault_b.ok_or(EscrowError::PaymentVaultMissing)?;
+      AssociatedTokenAccount::init_if_needed(
+        vault_b,
+        self.accounts.mint_b,
+        self.accounts.taker,
+        self.accounts.escrow,
+        self.accounts.system_program,
+        self.accounts.token_program,
+      )?;
+      vault_b
+    } else {
+      AssociatedTokenAccount::init_if_needed(
+        self.accounts.taker_ata_a,
+        self.accounts.mint_a,
+        self.accounts.taker,
+        self.accounts.taker,
+        self.accounts.system_program,
+        self.accounts.token_program,
+      )?;
+
+      // Transfer from the Vault to the Taker
+      TokenAccountInterface::memo_if_required(self.accounts.taker_ata_a, self.accounts.memo_program, Self::MEMO)?;
+      TransferChecked {
+        from: self.accounts.vault,
+        mint: self.accounts.mint_a,
+        to: self.accounts.taker_ata_a,
+        authority: self.accounts.escrow,
+        amount,
+        decimals: MintInterface::decimals(self.accounts.mint_a)?,
+        token_program: self.accounts.token_program.key(),
+      }.invoke_signed(core::slice::from_ref(&signer))?;
+
+      // Close the Vault, once any transfer fees withheld on it are harvested
+      TokenAccountInterface::harvest_withheld_fees(self.accounts.vault, self.accounts.mint_a, self.accounts.token_program)?;
+      CloseAccount {
+        account: self.accounts.vault,
+        destination: self.accounts.maker,
+        authority: self.accounts.escrow,
+        token_program: self.accounts.token_program.key(),
+      }.invoke_signed(core::slice::from_ref(&signer))?;
+      self.accounts.maker_ata_b
+    };
  
-    // Transfer from the Taker to the Maker
-    Transfer {
+    // Transfer from the Taker to the Maker, less the referral fee
+    let referral_fee = escrow.referral_fee(referral_bps);
+    let decimals_b = MintInterface::decimals(self.accounts.mint_b)?;
+    TokenAccountInterface::memo_if_required(payee_ata_b, self.accounts.memo_program, Self::MEMO)?;
+    TransferChecked {
       from: self.accounts.taker_ata_b,
-      to: self.accounts.maker_ata_b,
+      mint: self.accounts.mint_b,
+      to: payee_ata_b,
       authority: self.accounts.taker,
-      amount: escrow.receive,
+      amount: escrow.receive - referral_fee,
+      decimals: decimals_b,
+      token_program: self.accounts.token_program.key(),
     }.invoke()?;
+
+    // Transfer the referral fee from the Taker to the Referrer
+    if referral_fee > 0 {
+      let referrer_ata_b = self.accounts.referrer_ata_b.ok_or(EscrowError::ReferrerMissing)?;
+      TokenAccountInterface::memo_if_required(referrer_ata_b, self.accounts.memo_program, Self::MEMO)?;
+      TransferChecked {
+        from: self.accounts.taker_ata_b,
+        mint: self.accounts.mint_b,
+        to: referrer_ata_b,
+        authority: self.accounts.taker,
+        amount: referral_fee,
+        decimals: decimals_b,
+        token_program: self.accounts.token_program.key(),
+      }.invoke()?;
+    }
+    log!("Take: paid {} to the maker and {} to the referrer ({} bps)", escrow.receive - referral_fee, referral_fee, referral_bps);
+    TakeEvent {
+      escrow: self.accounts.escrow.key(),
+      maker: self.accounts.maker.key(),
+      taker: self.accounts.taker.key(),
+      amount_a: amount,
+      amount_b: escrow.receive,
+      referral_fee,
+      terms_hash: &escrow.terms_hash,
+      external_ref: &escrow.external_ref,
+    }.emit();
  
-    // Close the Escrow
+    // Record the trade
+    if let (Some(receipt), Some(bump)) = (self.accounts.receipt, self.receipt_bump) {
+      let mut receipt_data = receipt.try_borrow_mut_data()?;
+      let receipt = Receipt::load_mut(receipt_data.as_mut())?;
+      receipt.set_inner(
+        escrow.seed,
+        escrow.maker,
+        *self.accounts.taker.key(),
+        escrow.mint_a,
+        escrow.mint_b,
+        [bump],
+      );
+      receipt.set_trade(amount, escrow.receive, referral_fee, Clock::get()?.slot);
+      log!("Take: receipt recorded");
+    }
+
+    // Close the Escrow, or record whose payment it now holds
+    let arbitrated = escrow.is_arbitrated();
     drop(data);
+    if arbitrated {
+      let mut data = self.accounts.escrow.try_borrow_mut_data()?;
+      Escrow::load_mut(data.as_mut())?.set_taker(*self.accounts.taker.key());
+      log!("Take: payment held for release");
+      return Ok(());
+    }
     ProgramAccount::close(self.accounts.escrow, self.accounts.taker)?;
  
     Ok(())
@@ -10,7 +10,12 @@ pub use state::*;
 pub mod errors;
 pub use errors::*;
 
-use crate::instructions::{make::Make, take::Take, refund::Refund};
+pub mod events;
+
+#[cfg(kani)]
+mod verification;
+
+use crate::instructions::{make::Make, take::Take, refund::Refund, initialize_config::InitializeConfig, set_pause::SetPause, set_enforcement::SetEnforcement, set_mint_policy::SetMintPolicy, add_denylist_entry::AddDenylistEntry, remove_denylist_entry::RemoveDenylistEntry, close_receipt::CloseReceipt, claim::Claim, fill_signed_order::FillSignedOrder, cancel_all::CancelAll, release::Release, resolve::Resolve, cancel_order::CancelOrder, close_order_nonce::CloseOrderNonce};
  
 // 22222222222222222222222222222222222222222222
 pub const ID: Pubkey = [
@@ -27,8 +32,22 @@ fn process_instruction(
 ) -> ProgramResult {
     match instruction_data.split_first() {
         Some((Make::DISCRIMINATOR, data)) => Make::try_from((data, accounts))?.process(),
-        Some((Take::DISCRIMINATOR, _)) => Take::try_from(accounts)?.process(),
-        Some((Refund::DISCRIMINATOR, _)) => Refund::try_from(accounts)?.process(),
+        Some((Take::DISCRIMINATOR, data)) => Take::try_from((data, accounts))?.process(),
+        Some((Refund::DISCRIMINATOR, [])) => Refund::try_from(accounts)?.process(),
+        Some((InitializeConfig::DISCRIMINATOR, data)) => InitializeConfig::try_from((data, accounts))?.process(),
+        Some((SetPause::DISCRIMINATOR, data)) => SetPause::try_from((data, accounts))?.process(),
+        Some((SetEnforcement::DISCRIMINATOR, data)) => SetEnforcement::try_from((data, accounts))?.process(),
+        Some((SetMintPolicy::DISCRIMINATOR, data)) => SetMintPolicy::try_from((data, accounts))?.process(),
+        Some((AddDenylistEntry::DISCRIMINATOR, data)) => AddDenylistEntry::try_from((data, accounts))?.process(),
+        Some((RemoveDenylistEntry::DISCRIMINATOR, data)) => RemoveDenylistEntry::try_from((data, accounts))?.process(),
+        Some((CloseReceipt::DISCRIMINATOR, [])) => CloseReceipt::try_from(accounts)?.process(),
+        Some((Claim::DISCRIMINATOR, data)) => Claim::try_from((data, accounts))?.process(),
+        Some((FillSignedOrder::DISCRIMINATOR, data)) => FillSignedOrder::try_from((data, accounts))?.process(),
+        Some((CancelAll::DISCRIMINATOR, [])) => CancelAll::try_from(accounts)?.process(),
+        Some((Release::DISCRIMINATOR, [])) => Release::try_from(accounts)?.process(),
+        Some((Resolve::DISCRIMINATOR, data)) => Resolve::try_from((data, accounts))?.process(),
+        Some((CancelOrder::DISCRIMINATOR, data)) => CancelOrder::try_from((data, accounts))?.process(),
+        Some((CloseOrderNonce::DISCRIMINATOR, [])) => CloseOrderNonce::try_from(accounts)?.process(),
         _ => Err(ProgramError::InvalidInstructionData)
     }
 }
\ No newline at end of file
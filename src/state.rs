@@ -1,23 +1,63 @@
 use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
 use core::mem::size_of;
  
-#[repr(C)]
+// Packed so the struct has alignment 1 and no padding, which lets `load`
+// cast any `Escrow::LEN` byte slice, whatever its alignment.
+#[repr(C, packed)]
 pub struct Escrow {
     pub seed: u64,        // Random seed for PDA derivation
     pub maker: Pubkey,    // Creator of the escrow
     pub mint_a: Pubkey,   // Token being deposited
     pub mint_b: Pubkey,   // Token being requested
     pub receive: u64,     // Amount of token B wanted
-    pub bump: [u8;1]      // PDA bump seed
+    pub bump: [u8;1],     // PDA bump seed
+    pub max_referral_bps: u16, // Largest share of `receive` Take may pay a referrer, in basis points
+    pub terms_hash: [u8;32], // SHA-256 of the off-chain agreement the escrow settles, zero if none
+    pub external_ref: [u8;16], // The maker's reference for the escrow, such as an invoice id
+    pub hashlock: [u8;32], // SHA-256 of the preimage Claim needs, zero for an escrow Take settles
+    pub recipient: Pubkey, // Wallet Claim pays the deposit to
+    pub timeout: i64,      // Unix timestamp from which only Refund can settle a hashlocked escrow
+    pub authorizer: Pubkey, // Signer whose Ed25519 release Take requires, zero if none
+    pub maker_nonce: u64,  // The maker's `MakerState` nonce at Make, Take rejects it once CancelAll moves past it
+    pub arbiter: Pubkey,   // Wallet that may Resolve the escrow once taken, zero for an escrow Take settles at once
+    pub arbiter_fee_bps: u16, // Share of the payment Resolve settles for the maker that it pays the arbiter, in basis points
+    pub taker: Pubkey,     // Wallet whose payment an arbitrated escrow holds, zero until Take
 }
 
+const _: () = assert!(size_of::<Escrow>() == Escrow::LEN && core::mem::align_of::<Escrow>() == 1);
+
 impl Escrow {
     pub const LEN: usize = size_of::<u64>() 
     + size_of::<Pubkey>() 
     + size_of::<Pubkey>() 
     + size_of::<Pubkey>() 
     + size_of::<u64>()
-    + size_of::<[u8;1]>();
+    + size_of::<[u8;1]>()
+    + size_of::<u16>()
+    + size_of::<[u8;32]>()
+    + size_of::<[u8;16]>()
+    + size_of::<[u8;32]>()
+    + size_of::<Pubkey>()
+    + size_of::<i64>()
+    + size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<Pubkey>()
+    + size_of::<u16>()
+    + size_of::<Pubkey>();
+
+    /// The message an authorizer signs to release an escrow to a taker: the
+    /// escrow address, the taker, the SHA-256 hash of the escrow's account
+    /// data and the unix timestamp the release expires at
+    pub const AUTHORIZATION_LEN: usize = size_of::<Pubkey>() * 2 + size_of::<[u8; 32]>() + size_of::<i64>();
+
+    /// Basis points in a whole, the most a referral fee can take
+    pub const MAX_BPS: u16 = 10_000;
+
+    /// Lengths of every layout escrows were ever made with, oldest first.
+    /// Each layout only appended fields whose zero value means none to the
+    /// one before, so a shorter escrow is a prefix of the current layout.
+    /// New fields must keep to that and add the new length here.
+    pub const LAYOUT_LENS: [usize; 7] = [113, 115, 163, 235, 267, 275, Self::LEN];
  
     #[inline(always)]
     pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
@@ -35,6 +75,19 @@ impl Escrow {
         Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
     }
  
+    /// Copies out an escrow of the current or any earlier layout, with the
+    /// fields its layout lacks zeroed. For the instructions that must still
+    /// settle escrows made before the layout grew: Take, Claim and Refund.
+    #[inline(always)]
+    pub fn read(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if !Self::LAYOUT_LENS.contains(&bytes.len()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut current = [0u8; Self::LEN];
+        current[..bytes.len()].copy_from_slice(bytes);
+        Ok(unsafe { core::ptr::read(current.as_ptr() as *const Self) })
+    }
+
     #[inline(always)]
     pub fn set_seed(&mut self, seed: u64) {
         self.seed = seed;
@@ -65,6 +118,121 @@ impl Escrow {
         self.bump = bump;
     }
  
+    #[inline(always)]
+    pub fn set_max_referral_bps(&mut self, max_referral_bps: u16) {
+        self.max_referral_bps = max_referral_bps;
+    }
+
+    #[inline(always)]
+    pub fn set_terms(&mut self, terms_hash: [u8;32], external_ref: [u8;16]) {
+        self.terms_hash = terms_hash;
+        self.external_ref = external_ref;
+    }
+
+    #[inline(always)]
+    pub fn set_hashlock(&mut self, hashlock: [u8;32], recipient: Pubkey, timeout: i64) {
+        self.hashlock = hashlock;
+        self.recipient = recipient;
+        self.timeout = timeout;
+    }
+
+    #[inline(always)]
+    pub fn set_authorizer(&mut self, authorizer: Pubkey) {
+        self.authorizer = authorizer;
+    }
+
+    #[inline(always)]
+    pub fn set_maker_nonce(&mut self, maker_nonce: u64) {
+        self.maker_nonce = maker_nonce;
+    }
+
+    #[inline(always)]
+    pub fn set_arbiter(&mut self, arbiter: Pubkey, arbiter_fee_bps: u16) {
+        self.arbiter = arbiter;
+        self.arbiter_fee_bps = arbiter_fee_bps;
+    }
+
+    #[inline(always)]
+    pub fn set_taker(&mut self, taker: Pubkey) {
+        self.taker = taker;
+    }
+
+    /// Whether Take holds the taker's payment in the escrow, for Release or
+    /// Resolve to settle.
+    #[inline(always)]
+    pub fn is_arbitrated(&self) -> bool {
+        self.arbiter != [0; 32]
+    }
+
+    /// Whether Take already put the taker's payment in the escrow.
+    #[inline(always)]
+    pub fn is_taken(&self) -> bool {
+        self.taker != [0; 32]
+    }
+
+    /// Whether Take needs the authorizer's signed release.
+    #[inline(always)]
+    pub fn requires_authorization(&self) -> bool {
+        self.authorizer != [0; 32]
+    }
+
+    /// Whether the escrow is a hash-time-locked contract, settled by Claim
+    /// before `timeout` and by Refund from then on.
+    #[inline(always)]
+    pub fn is_hashlocked(&self) -> bool {
+        self.hashlock != [0; 32]
+    }
+
+    /// The terms hash Make expects for `document`, its SHA-256 hash. For
+    /// clients, the program never reads the document.
+    #[cfg(not(target_os = "solana"))]
+    pub fn hash_terms(document: &[u8]) -> [u8;32] {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(document).into()
+    }
+
+    /// Whether `document` is the agreement the escrow was made for.
+    #[cfg(not(target_os = "solana"))]
+    pub fn terms_match(&self, document: &[u8]) -> bool {
+        Self::hash_terms(document) == self.terms_hash
+    }
+
+    /// The message the authorizer signs to release `escrow`, whose account
+    /// holds `escrow_data`, to `taker` until `expiry`. The data's hash ties the
+    /// release to this escrow's terms and maker nonce, so it doesn't carry
+    /// over to a different escrow later made with the same seed. For clients,
+    /// Take finds it in an Ed25519 instruction.
+    #[cfg(not(target_os = "solana"))]
+    pub fn authorization_message(escrow: &Pubkey, escrow_data: &[u8], taker: &Pubkey, expiry: i64) -> [u8; Self::AUTHORIZATION_LEN] {
+        let mut message = [0; Self::AUTHORIZATION_LEN];
+        message[..32].copy_from_slice(escrow);
+        message[32..64].copy_from_slice(taker);
+        message[64..96].copy_from_slice(&crate::helpers::sha256(escrow_data));
+        message[96..].copy_from_slice(&expiry.to_le_bytes());
+        message
+    }
+
+    /// The part of `receive` a referrer gets for `referral_bps`, rounded
+    /// down. Never more than `receive` while `referral_bps <= MAX_BPS`.
+    #[inline(always)]
+    pub fn referral_fee(&self, referral_bps: u16) -> u64 {
+        Self::share(self.receive, referral_bps)
+    }
+
+    /// The part of the payment Resolve settles for the maker, `settled`,
+    /// that it pays the arbiter instead, rounded down.
+    #[inline(always)]
+    pub fn arbiter_fee(&self, settled: u64) -> u64 {
+        Self::share(settled, self.arbiter_fee_bps)
+    }
+
+    /// `bps` basis points of `amount`, rounded down. Never more than
+    /// `amount` while `bps <= MAX_BPS`.
+    #[inline(always)]
+    pub fn share(amount: u64, bps: u16) -> u64 {
+        (amount as u128 * bps as u128 / Self::MAX_BPS as u128) as u64
+    }
+ 
     #[inline(always)]
     pub fn set_inner(&mut self, seed: u64, maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, receive: u64, bump: [u8;1]) {
         self.seed = seed;
@@ -74,4 +242,364 @@ impl Escrow {
         self.receive = receive;
         self.bump = bump;
     }
-}
\ No newline at end of file
+}
+
+/// Program-wide settings, stored at the `[b"config"]` PDA. Until the upgrade
+/// authority initializes it, nothing is paused.
+#[repr(C, packed)]
+pub struct Config {
+    pub admin: Pubkey,    // May pause and unpause instructions
+    pub paused: u8,       // `PAUSE_*` bits of the paused instructions
+    pub enforce: u8,      // `ENFORCE_*` bits of the enforced policies
+    pub bump: [u8;1]      // PDA bump seed
+}
+
+const _: () = assert!(size_of::<Config>() == Config::LEN && core::mem::align_of::<Config>() == 1);
+
+impl Config {
+    pub const LEN: usize = size_of::<Pubkey>()
+    + size_of::<u8>()
+    + size_of::<u8>()
+    + size_of::<[u8;1]>();
+
+    pub const SEED: &'static [u8] = b"config";
+    // AGHbrRhTgoeyN3QwTpQxNv2s8n8BS2zP6Sfa6z57tRxG
+    pub const ADDRESS: Pubkey = [
+        0x89, 0xa3, 0xaa, 0x48, 0x97, 0xa1, 0xff, 0x91,
+        0xe2, 0x2a, 0x40, 0xec, 0xbd, 0xe8, 0x08, 0x79,
+        0xa0, 0xdc, 0xa4, 0x5a, 0xaf, 0xeb, 0x9e, 0x02,
+        0x87, 0xbb, 0xef, 0x0b, 0x53, 0x84, 0x44, 0x7d,
+    ];
+    pub const BUMP: u8 = 251;
+
+    pub const PAUSE_MAKE: u8 = 1 << 0;
+    pub const PAUSE_TAKE: u8 = 1 << 1;
+    pub const PAUSE_REFUND: u8 = 1 << 2;
+    pub const PAUSE_ALL: u8 = Self::PAUSE_MAKE | Self::PAUSE_TAKE | Self::PAUSE_REFUND;
+
+    /// Make rejects blocked mints and amounts outside their policy's limits
+    pub const ENFORCE_MINT_POLICY: u8 = 1 << 0;
+    /// Make also rejects mints without a policy allowing them
+    pub const REQUIRE_MINT_POLICY: u8 = 1 << 1;
+    pub const ENFORCE_ALL: u8 = Self::ENFORCE_MINT_POLICY | Self::REQUIRE_MINT_POLICY;
+
+    #[inline(always)]
+    pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if bytes.len() != Config::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
+        if bytes.len() != Config::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.paused & flag != 0
+    }
+
+    #[inline(always)]
+    pub fn set_paused(&mut self, paused: u8) {
+        self.paused = paused;
+    }
+
+    #[inline(always)]
+    pub fn set_enforce(&mut self, enforce: u8) {
+        self.enforce = enforce;
+    }
+
+    #[inline(always)]
+    pub fn set_inner(&mut self, admin: Pubkey, paused: u8, enforce: u8, bump: [u8;1]) {
+        self.admin = admin;
+        self.paused = paused;
+        self.enforce = enforce;
+        self.bump = bump;
+    }
+}
+
+/// The admin's policy for one mint, stored at the `[b"mint_policy", mint]`
+/// PDA and enforced by Make while `Config::ENFORCE_MINT_POLICY` is set.
+#[repr(C, packed)]
+pub struct MintPolicy {
+    pub mint: Pubkey,     // Mint the policy applies to
+    pub allowed: u8,      // 1 if the mint may be escrowed, 0 if it's blocked
+    pub min_amount: u64,  // Smallest amount of the mint an escrow may hold or ask for
+    pub max_amount: u64,  // Largest amount, 0 for no limit
+    pub bump: [u8;1]      // PDA bump seed
+}
+
+const _: () = assert!(size_of::<MintPolicy>() == MintPolicy::LEN && core::mem::align_of::<MintPolicy>() == 1);
+
+impl MintPolicy {
+    pub const LEN: usize = size_of::<Pubkey>()
+    + size_of::<u8>()
+    + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<[u8;1]>();
+
+    pub const SEED: &'static [u8] = b"mint_policy";
+
+    #[inline(always)]
+    pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if bytes.len() != MintPolicy::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
+        if bytes.len() != MintPolicy::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn is_allowed(&self) -> bool {
+        self.allowed == 1
+    }
+
+    /// Whether `amount` is within the policy's limits.
+    #[inline(always)]
+    pub fn permits(&self, amount: u64) -> bool {
+        let (min_amount, max_amount) = (self.min_amount, self.max_amount);
+        amount >= min_amount && (max_amount == 0 || amount <= max_amount)
+    }
+
+    #[inline(always)]
+    pub fn set_inner(&mut self, mint: Pubkey, allowed: u8, min_amount: u64, max_amount: u64, bump: [u8;1]) {
+        self.mint = mint;
+        self.allowed = allowed;
+        self.min_amount = min_amount;
+        self.max_amount = max_amount;
+        self.bump = bump;
+    }
+}
+
+/// Marks a wallet the admin denied, stored at the `[b"denylist", wallet]`
+/// PDA. Make and Take prove the maker or taker isn't denied by passing this
+/// PDA still empty.
+#[repr(C, packed)]
+pub struct DenylistEntry {
+    pub wallet: Pubkey,   // Wallet that may not make or take escrows
+    pub bump: [u8;1]      // PDA bump seed
+}
+
+const _: () = assert!(size_of::<DenylistEntry>() == DenylistEntry::LEN && core::mem::align_of::<DenylistEntry>() == 1);
+
+impl DenylistEntry {
+    pub const LEN: usize = size_of::<Pubkey>()
+    + size_of::<[u8;1]>();
+
+    pub const SEED: &'static [u8] = b"denylist";
+
+    #[inline(always)]
+    pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if bytes.len() != DenylistEntry::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
+        if bytes.len() != DenylistEntry::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn set_inner(&mut self, wallet: Pubkey, bump: [u8;1]) {
+        self.wallet = wallet;
+        self.bump = bump;
+    }
+}
+
+/// Proof of a completed Take, stored at the `[b"receipt", escrow, taker]` PDA
+/// when the taker asks for one. The taker pays its rent and gets it back when
+/// the maker or the taker closes it. The taker is part of the seeds because a
+/// maker can reopen an escrow at the same address, and an earlier taker's
+/// open receipt mustn't keep later takers from getting theirs.
+#[repr(C, packed)]
+pub struct Receipt {
+    pub seed: u64,        // Seed of the escrow that was taken
+    pub maker: Pubkey,    // Creator of the escrow
+    pub taker: Pubkey,    // Wallet that took the escrow and paid the rent
+    pub mint_a: Pubkey,   // Token the taker received
+    pub mint_b: Pubkey,   // Token the taker paid
+    pub amount_a: u64,    // Amount of token A sent out of the vault
+    pub amount_b: u64,    // Amount of token B the taker paid, referral fee included
+    pub referral_fee: u64, // Part of `amount_b` paid to the referrer
+    pub slot: u64,        // Slot of the Take
+    pub bump: [u8;1]      // PDA bump seed
+}
+
+const _: () = assert!(size_of::<Receipt>() == Receipt::LEN && core::mem::align_of::<Receipt>() == 1);
+
+impl Receipt {
+    pub const LEN: usize = size_of::<u64>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<[u8;1]>();
+
+    pub const SEED: &'static [u8] = b"receipt";
+
+    #[inline(always)]
+    pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if bytes.len() != Receipt::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
+        if bytes.len() != Receipt::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn set_trade(&mut self, amount_a: u64, amount_b: u64, referral_fee: u64, slot: u64) {
+        self.amount_a = amount_a;
+        self.amount_b = amount_b;
+        self.referral_fee = referral_fee;
+        self.slot = slot;
+    }
+
+    #[inline(always)]
+    pub fn set_inner(&mut self, seed: u64, maker: Pubkey, taker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, bump: [u8;1]) {
+        self.seed = seed;
+        self.maker = maker;
+        self.taker = taker;
+        self.mint_a = mint_a;
+        self.mint_b = mint_b;
+        self.bump = bump;
+    }
+}
+
+/// A spent signed-order nonce, stored at the `[b"order_nonce", maker, nonce]`
+/// PDA. FillSignedOrder creates it when it fills an order, paid by the taker,
+/// and CancelOrder when the maker cancels one, so no order with the nonce
+/// fills after. Once CancelAll moves the maker past the nonce it was spent
+/// at, those orders can't fill anyway, and CloseOrderNonce returns the rent
+/// to the payer. The nonce is then free for orders signed at a later maker
+/// nonce.
+#[repr(C, packed)]
+pub struct OrderNonce {
+    pub maker: Pubkey,    // Maker who signed the orders the nonce voids
+    pub nonce: u64,       // The spent nonce
+    pub maker_nonce: u64, // Maker's CancelAll nonce when it was spent
+    pub payer: Pubkey,    // Paid the rent, and gets it back on close
+    pub bump: [u8;1]      // PDA bump seed
+}
+
+const _: () = assert!(size_of::<OrderNonce>() == OrderNonce::LEN && core::mem::align_of::<OrderNonce>() == 1);
+
+impl OrderNonce {
+    pub const LEN: usize = size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<Pubkey>()
+    + size_of::<[u8;1]>();
+
+    pub const SEED: &'static [u8] = b"order_nonce";
+
+    #[inline(always)]
+    pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if bytes.len() != OrderNonce::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
+        if bytes.len() != OrderNonce::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+    }
+
+    /// Whether CancelAll moved the maker past the nonce this was spent at,
+    /// so no order it guards can fill any more.
+    #[inline(always)]
+    pub fn is_stale(&self, maker_nonce: u64) -> bool {
+        self.maker_nonce < maker_nonce
+    }
+
+    #[inline(always)]
+    pub fn set_inner(&mut self, maker: Pubkey, nonce: u64, maker_nonce: u64, payer: Pubkey, bump: [u8;1]) {
+        self.maker = maker;
+        self.nonce = nonce;
+        self.maker_nonce = maker_nonce;
+        self.payer = payer;
+        self.bump = bump;
+    }
+}
+
+/// A maker's cancellation nonce, stored at the `[b"maker_state", maker]` PDA.
+/// Make records the current nonce in each escrow, and CancelAll advances it,
+/// so Take rejects every escrow made before. Until the maker's first
+/// CancelAll creates it the nonce is zero.
+#[repr(C, packed)]
+pub struct MakerState {
+    pub maker: Pubkey,    // Maker whose escrows the nonce guards
+    pub nonce: u64,       // Only escrows made at this nonce can be taken
+    pub bump: [u8;1]      // PDA bump seed
+}
+
+const _: () = assert!(size_of::<MakerState>() == MakerState::LEN && core::mem::align_of::<MakerState>() == 1);
+
+impl MakerState {
+    pub const LEN: usize = size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<[u8;1]>();
+
+    pub const SEED: &'static [u8] = b"maker_state";
+
+    #[inline(always)]
+    pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if bytes.len() != MakerState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+    }
+
+    #[inline(always)]
+    pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
+        if bytes.len() != MakerState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+    }
+
+    /// Cancels every escrow made at the current nonce or before. Never wraps
+    /// around, which would revive them.
+    #[inline(always)]
+    pub fn advance(&mut self) {
+        self.nonce = self.nonce.saturating_add(1);
+    }
+
+    #[inline(always)]
+    pub fn set_inner(&mut self, maker: Pubkey, nonce: u64, bump: [u8;1]) {
+        self.maker = maker;
+        self.nonce = nonce;
+        self.bump = bump;
+    }
+}
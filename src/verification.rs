@@ -0,0 +1,297 @@
+//! Kani proof harnesses for account state loading and instruction decoding.
+//!
+//! Run with `cargo kani` from the `src` directory. Each harness checks the
+//! property for every possible input of the given bounded length.
+
+use crate::{instructions::{make::MakeInstructionData, set_pause::SetPauseInstructionData}, Config, DenylistEntry, Escrow, MakerState, MintPolicy, OrderNonce, Receipt};
+
+// Longest input the harnesses try, one byte past the longest valid encoding
+const MAX_LEN: usize = Escrow::LEN + 1;
+
+#[kani::proof]
+fn escrow_load_never_reads_out_of_bounds() {
+    let bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_LEN);
+
+    match Escrow::load(&bytes[..len]) {
+        Ok(escrow) => {
+            assert!(len == Escrow::LEN);
+            // Reading every field must stay within the slice
+            let _ = (escrow.seed, escrow.maker, escrow.mint_a, escrow.mint_b, escrow.receive, escrow.bump, escrow.max_referral_bps);
+            let _ = (escrow.terms_hash, escrow.external_ref, escrow.hashlock, escrow.recipient, escrow.timeout);
+        }
+        Err(_) => assert!(len != Escrow::LEN),
+    }
+}
+
+#[kani::proof]
+fn escrow_read_accepts_every_layout() {
+    let bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_LEN);
+
+    match Escrow::read(&bytes[..len]) {
+        Ok(escrow) => {
+            assert!(Escrow::LAYOUT_LENS.contains(&len));
+            // Fields past the layout read as zero
+            if len < Escrow::LEN {
+                assert!(escrow.taker == [0; 32]);
+            }
+        }
+        Err(_) => assert!(!Escrow::LAYOUT_LENS.contains(&len)),
+    }
+}
+
+#[kani::proof]
+fn escrow_load_mut_never_writes_out_of_bounds() {
+    let mut bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_LEN);
+
+    match Escrow::load_mut(&mut bytes[..len]) {
+        Ok(escrow) => {
+            assert!(len == Escrow::LEN);
+            escrow.set_inner(kani::any(), kani::any(), kani::any(), kani::any(), kani::any(), kani::any());
+        }
+        Err(_) => assert!(len != Escrow::LEN),
+    }
+}
+
+#[kani::proof]
+fn escrow_set_inner_round_trips() {
+    let seed: u64 = kani::any();
+    let maker: [u8; 32] = kani::any();
+    let mint_a: [u8; 32] = kani::any();
+    let mint_b: [u8; 32] = kani::any();
+    let receive: u64 = kani::any();
+    let bump: [u8; 1] = kani::any();
+
+    let mut bytes = [0u8; Escrow::LEN];
+    Escrow::load_mut(&mut bytes).unwrap().set_inner(seed, maker, mint_a, mint_b, receive, bump);
+
+    let escrow = Escrow::load(&bytes).unwrap();
+    assert!({ escrow.seed } == seed);
+    assert!(escrow.maker == maker);
+    assert!(escrow.mint_a == mint_a);
+    assert!(escrow.mint_b == mint_b);
+    assert!({ escrow.receive } == receive);
+    assert!(escrow.bump == bump);
+
+    // The on-chain layout is the little-endian fields back to back
+    assert!(bytes[0..8] == seed.to_le_bytes());
+    assert!(bytes[104..112] == receive.to_le_bytes());
+    assert!(bytes[112] == bump[0]);
+}
+
+#[kani::proof]
+fn escrow_decode_then_set_inner_is_identity() {
+    let bytes: [u8; Escrow::LEN] = kani::any();
+    let escrow = Escrow::load(&bytes).unwrap();
+
+    let mut copy = [0u8; Escrow::LEN];
+    let copied = Escrow::load_mut(&mut copy).unwrap();
+    copied.set_inner(
+        escrow.seed,
+        escrow.maker,
+        escrow.mint_a,
+        escrow.mint_b,
+        escrow.receive,
+        escrow.bump,
+    );
+    copied.set_max_referral_bps(escrow.max_referral_bps);
+    copied.set_terms(escrow.terms_hash, escrow.external_ref);
+    copied.set_hashlock(escrow.hashlock, escrow.recipient, escrow.timeout);
+    copied.set_authorizer(escrow.authorizer);
+    copied.set_maker_nonce(escrow.maker_nonce);
+    copied.set_arbiter(escrow.arbiter, escrow.arbiter_fee_bps);
+    copied.set_taker(escrow.taker);
+    assert!(copy == bytes);
+}
+
+#[kani::proof]
+fn referral_fee_never_exceeds_receive() {
+    let mut bytes = [0u8; Escrow::LEN];
+    let escrow = Escrow::load_mut(&mut bytes).unwrap();
+    escrow.set_receive(kani::any());
+    let referral_bps: u16 = kani::any();
+    kani::assume(referral_bps <= Escrow::MAX_BPS);
+
+    let fee = escrow.referral_fee(referral_bps);
+    assert!(fee <= { escrow.receive });
+    if referral_bps == Escrow::MAX_BPS {
+        assert!(fee == { escrow.receive });
+    }
+}
+
+#[kani::proof]
+fn resolve_split_never_exceeds_the_held_amounts() {
+    let mut bytes = [0u8; Escrow::LEN];
+    let escrow = Escrow::load_mut(&mut bytes).unwrap();
+    let arbiter_fee_bps: u16 = kani::any();
+    let settle_bps: u16 = kani::any();
+    kani::assume(arbiter_fee_bps <= Escrow::MAX_BPS && settle_bps <= Escrow::MAX_BPS);
+    escrow.set_arbiter([1; 32], arbiter_fee_bps);
+    let (deposit, payment): (u64, u64) = (kani::any(), kani::any());
+
+    // Resolve pays out every token it holds, and never more
+    let to_taker = Escrow::share(deposit, settle_bps);
+    let settled = Escrow::share(payment, settle_bps);
+    let fee = escrow.arbiter_fee(settled);
+    assert!(to_taker <= deposit && settled <= payment && fee <= settled);
+    // Nothing settles for the maker, so nothing pays the arbiter
+    assert!(settle_bps != 0 || fee == 0);
+}
+
+#[kani::proof]
+fn config_load_never_reads_out_of_bounds() {
+    let bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_LEN);
+
+    match Config::load(&bytes[..len]) {
+        Ok(config) => {
+            assert!(len == Config::LEN);
+            let _ = (config.admin, config.paused, config.enforce, config.bump);
+        }
+        Err(_) => assert!(len != Config::LEN),
+    }
+}
+
+#[kani::proof]
+fn mint_policy_load_never_reads_out_of_bounds() {
+    let bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_LEN);
+
+    match MintPolicy::load(&bytes[..len]) {
+        Ok(policy) => {
+            assert!(len == MintPolicy::LEN);
+            let _ = (policy.mint, policy.allowed, policy.min_amount, policy.max_amount, policy.bump);
+        }
+        Err(_) => assert!(len != MintPolicy::LEN),
+    }
+}
+
+#[kani::proof]
+fn denylist_entry_load_never_reads_out_of_bounds() {
+    let bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_LEN);
+
+    match DenylistEntry::load(&bytes[..len]) {
+        Ok(entry) => {
+            assert!(len == DenylistEntry::LEN);
+            let _ = (entry.wallet, entry.bump);
+        }
+        Err(_) => assert!(len != DenylistEntry::LEN),
+    }
+}
+
+#[kani::proof]
+fn receipt_load_never_reads_out_of_bounds() {
+    let bytes: [u8; Receipt::LEN + 1] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= bytes.len());
+
+    match Receipt::load(&bytes[..len]) {
+        Ok(receipt) => {
+            assert!(len == Receipt::LEN);
+            let _ = (receipt.seed, receipt.maker, receipt.taker, receipt.mint_a, receipt.mint_b);
+            let _ = (receipt.amount_a, receipt.amount_b, receipt.referral_fee, receipt.slot, receipt.bump);
+        }
+        Err(_) => assert!(len != Receipt::LEN),
+    }
+}
+
+#[kani::proof]
+fn order_nonce_records_the_spent_nonce() {
+    let mut bytes: [u8; OrderNonce::LEN] = kani::any();
+    let (maker, nonce, maker_nonce, payer, bump): ([u8; 32], u64, u64, [u8; 32], u8) = (kani::any(), kani::any(), kani::any(), kani::any(), kani::any());
+
+    OrderNonce::load_mut(&mut bytes).unwrap().set_inner(maker, nonce, maker_nonce, payer, [bump]);
+    assert!(bytes[..32] == maker && bytes[32..40] == nonce.to_le_bytes() && bytes[40..48] == maker_nonce.to_le_bytes());
+    assert!(bytes[48..80] == payer && bytes[80] == bump);
+    assert!({ OrderNonce::load(&bytes).unwrap().nonce } == nonce);
+}
+
+#[kani::proof]
+fn order_nonce_closes_only_once_the_maker_moved_past_it() {
+    let bytes: [u8; OrderNonce::LEN] = kani::any();
+    let current: u64 = kani::any();
+    let order_nonce = OrderNonce::load(&bytes).unwrap();
+
+    // A nonce spent at the current maker nonce still guards fillable orders
+    let spent_at = order_nonce.maker_nonce;
+    assert!(order_nonce.is_stale(current) == (spent_at < current));
+    assert!(!order_nonce.is_stale(spent_at));
+}
+
+#[kani::proof]
+fn maker_state_advance_never_revives_escrows() {
+    let bytes: [u8; MakerState::LEN] = kani::any();
+    let mut copy = bytes;
+    let maker_state = MakerState::load_mut(&mut copy).unwrap();
+    let escrow_nonce: u64 = kani::any();
+    let cancelled = escrow_nonce < { maker_state.nonce };
+
+    maker_state.advance();
+    assert!({ maker_state.nonce } >= u64::from_le_bytes(bytes[32..40].try_into().unwrap()));
+    assert!(!cancelled || escrow_nonce < { maker_state.nonce });
+}
+
+#[kani::proof]
+fn set_pause_instruction_data_only_accepts_known_flags() {
+    let bytes: [u8; 2] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= bytes.len());
+
+    if let Ok(data) = SetPauseInstructionData::try_from(&bytes[..len]) {
+        assert!(len == 1);
+        assert!(data.paused == bytes[0]);
+        assert!(data.paused & !Config::PAUSE_ALL == 0);
+    }
+}
+
+#[kani::proof]
+fn make_instruction_data_never_panics() {
+    let bytes: [u8; 214] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= bytes.len());
+
+    if let Ok(data) = MakeInstructionData::try_from(&bytes[..len]) {
+        assert!(len == 24 || len == 25 || len == 27 || len == 75 || len == 147 || len == 179 || len == 213);
+        assert!(data.flags == if len >= 25 { bytes[24] } else { 0 });
+        assert!(data.max_referral_bps == if len >= 27 { u16::from_le_bytes([bytes[25], bytes[26]]) } else { 0 });
+        if len >= 75 {
+            assert!(data.terms_hash == bytes[27..59] && data.external_ref == bytes[59..75]);
+        } else {
+            assert!(data.terms_hash == [0; 32] && data.external_ref == [0; 16]);
+        }
+        if len >= 147 {
+            assert!(data.hashlock == bytes[75..107] && data.recipient == bytes[107..139]);
+            assert!(data.timeout == i64::from_le_bytes(bytes[139..147].try_into().unwrap()));
+            // Without a hashlock the segment is all zeros
+            assert!(data.is_hashlocked() || (data.recipient == [0; 32] && data.timeout == 0));
+        } else {
+            assert!(!data.is_hashlocked());
+        }
+        assert!(data.authorizer == if len >= 179 { bytes[147..179].try_into().unwrap() } else { [0; 32] });
+        if len == 213 {
+            assert!(data.arbiter == bytes[179..211]);
+            assert!(data.arbiter_fee_bps == u16::from_le_bytes([bytes[211], bytes[212]]));
+            // An arbiter never comes with a hashlock, a fee never without an arbiter
+            assert!(!data.is_hashlocked() || !data.is_arbitrated());
+            assert!(data.is_arbitrated() || data.arbiter_fee_bps == 0);
+        } else {
+            assert!(!data.is_arbitrated() && data.arbiter_fee_bps == 0);
+        }
+        assert!(data.arbiter_fee_bps <= Escrow::MAX_BPS);
+        assert!(data.max_referral_bps <= Escrow::MAX_BPS);
+        assert!(data.flags & !MakeInstructionData::ALLOW_UNSAFE_MINT_EXTENSIONS == 0);
+        assert!(data.seed == u64::from_le_bytes(bytes[0..8].try_into().unwrap()));
+        assert!(data.receive == u64::from_le_bytes(bytes[8..16].try_into().unwrap()));
+        assert!(data.amount == u64::from_le_bytes(bytes[16..24].try_into().unwrap()));
+        assert!(data.amount != 0);
+    }
+}
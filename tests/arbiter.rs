@@ -0,0 +1,257 @@
+//! Arbitrated escrows: Take holds the taker's payment in a second vault, and
+//! Release by the maker and the taker, or Resolve by the arbiter, settles
+//! both vaults.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{resolve::ResolveInstructionData, Config, EscrowError, PinocchioError},
+    mollusk_svm::{result::{Check, ProgramResult}, Mollusk},
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+const DEPOSIT: u64 = 1_500;
+const RECEIVE: u64 = 2_000;
+
+/// An escrow `arbiter` rules on for `arbiter_fee_bps`, already taken, with
+/// the arbiter's wallet and token account for mint B.
+fn taken_scenario(mollusk: &Mollusk, arbiter: &Pubkey, arbiter_fee_bps: u16) -> EscrowScenario {
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, DEPOSIT)
+        .balance(Ata::TakerB, 3_000)
+        .arbiter(*arbiter, arbiter_fee_bps)
+        .build();
+    scenario.set_account(*arbiter, Account::new(10_000_000, 0, &solana_system_program::id()));
+    scenario.set_account(derive_associated_token_account(arbiter, &scenario.mint_b), create_token_account(arbiter, &scenario.mint_b, 0));
+    let instruction = with_associated_token_program(scenario.take_into_escrow_instruction());
+    run(mollusk, &mut scenario, instruction, ProgramResult::Success);
+    scenario
+}
+
+/// Runs Resolve for `arbiter`, passing its token account for the fee.
+fn resolve(mollusk: &Mollusk, scenario: &mut EscrowScenario, arbiter: &Pubkey, settle_bps: u16, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let instruction = with_associated_token_program(scenario.resolve_instruction(arbiter, settle_bps));
+    let instruction = with_arbiter_account(instruction, &derive_associated_token_account(arbiter, &scenario.mint_b));
+    run(mollusk, scenario, instruction, expected)
+}
+
+#[test]
+fn test_make_records_arbiter() {
+    let mollusk = setup_mollusk();
+    let scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let arbiter = Pubkey::new_unique();
+
+    let instruction = with_associated_token_program(with_arbiter(scenario.make_instruction(750, 400), &arbiter, 250));
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+    let state = scenario.escrow_state(&result.resulting_accounts).unwrap();
+    assert_eq!((state.arbiter, state.arbiter_fee_bps, state.taker), (arbiter, 250, Pubkey::default()));
+
+    // A fee without an arbiter, a fee above the whole, or an arbiter on a
+    // hashlocked escrow
+    let invalid = [
+        with_arbiter(scenario.make_instruction(750, 400), &Pubkey::default(), 250),
+        with_arbiter(scenario.make_instruction(750, 400), &arbiter, 10_001),
+        with_arbiter(with_hashlock(scenario.make_instruction(750, 400), &[7; 32], &scenario.taker, i64::MAX), &arbiter, 0),
+    ];
+    for instruction in invalid {
+        let instruction = with_associated_token_program(instruction);
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &scenario.accounts_for(&instruction),
+            &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidInstructionData))],
+        );
+    }
+}
+
+#[test]
+fn test_take_holds_the_payment_in_the_escrow() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, DEPOSIT)
+        .balance(Ata::TakerB, 3_000)
+        .arbiter(Pubkey::new_unique(), 0)
+        .build();
+
+    // Without the payment vault there is nowhere to hold the payment
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::PaymentVaultMissing));
+
+    let instruction = with_associated_token_program(scenario.take_into_escrow_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::Vault), DEPOSIT);
+    assert_eq!(scenario.balance(&accounts, Ata::PaymentVault), RECEIVE);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 0);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 0);
+    assert_eq!(scenario.escrow_state(&accounts).unwrap().taker, scenario.taker);
+
+    // Neither a second Take nor the maker's Refund unwinds a held payment
+    let instruction = with_associated_token_program(scenario.take_into_escrow_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::EscrowAlreadyTaken));
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::EscrowAlreadyTaken));
+}
+
+#[test]
+fn test_take_on_arbitrated_escrows_records_no_trade_yet() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, DEPOSIT)
+        .balance(Ata::TakerB, 3_000)
+        .missing(Ata::TakerA)
+        .arbiter(Pubkey::new_unique(), 0)
+        .build();
+
+    // Release or Resolve may still unwind the trade, so there is no receipt
+    let instruction = with_receipt(with_associated_token_program(scenario.take_instruction()), &scenario.escrow);
+    let instruction = with_payment_vault(instruction, &scenario.ata(Ata::PaymentVault));
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::InvalidInstructionData));
+
+    // and no token A moves, so the taker's account for it isn't created
+    let instruction = with_associated_token_program(scenario.take_into_escrow_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert!(scenario.token_account(&accounts, Ata::TakerA).is_none());
+}
+
+#[test]
+fn test_take_pays_no_referral_fee_on_arbitrated_escrows() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, DEPOSIT)
+        .balance(Ata::TakerB, 3_000)
+        .max_referral_bps(500)
+        .arbiter(Pubkey::new_unique(), 0)
+        .build();
+    let referrer_ata = Pubkey::new_unique();
+    scenario.set_account(referrer_ata, create_token_account(&Pubkey::new_unique(), &scenario.mint_b, 0));
+
+    // Resolve could still unwind the trade after the referrer was paid
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &referrer_ata, 250);
+    let instruction = with_payment_vault(instruction, &scenario.ata(Ata::PaymentVault));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ReferralFeeTooHigh));
+}
+
+#[test]
+fn test_release_completes_the_trade() {
+    let mollusk = setup_mollusk();
+    let arbiter = Pubkey::new_unique();
+    let mut scenario = taken_scenario(&mollusk, &arbiter, 100);
+
+    // Both sides must sign
+    let mut instruction = with_associated_token_program(scenario.release_instruction());
+    instruction.accounts[1].is_signer = false;
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::Custom(PinocchioError::NotSigner as u32)));
+
+    // No arbiter fee when nobody disputes
+    let instruction = with_associated_token_program(scenario.release_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), DEPOSIT);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), RECEIVE);
+    assert!(scenario.token_account(&accounts, Ata::Vault).is_none());
+    assert!(scenario.token_account(&accounts, Ata::PaymentVault).is_none());
+    assert!(scenario.escrow_state(&accounts).is_none());
+}
+
+#[test]
+fn test_release_needs_a_taken_arbitrated_escrow() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, DEPOSIT)
+        .arbiter(Pubkey::new_unique(), 0)
+        .build();
+    // Release checks both vaults before it reads the escrow, so an empty
+    // payment vault stands in for the one Take creates
+    scenario.set_account(scenario.ata(Ata::PaymentVault), create_token_account(&scenario.escrow, &scenario.mint_b, 0));
+    let instruction = with_associated_token_program(scenario.release_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::EscrowNotTaken));
+
+    let mut scenario = EscrowScenario::builder().open(RECEIVE, DEPOSIT).build();
+    scenario.set_account(scenario.ata(Ata::PaymentVault), create_token_account(&scenario.escrow, &scenario.mint_b, 0));
+    let instruction = with_associated_token_program(scenario.release_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::NotArbitrated));
+}
+
+#[test]
+fn test_resolve_rules_for_either_side_or_splits() {
+    let mollusk = setup_mollusk();
+    let arbiter = Pubkey::new_unique();
+
+    // For the taker the trade completes, less the arbiter's 1% of the payment
+    let mut scenario = taken_scenario(&mollusk, &arbiter, 100);
+    let accounts = resolve(&mollusk, &mut scenario, &arbiter, ResolveInstructionData::TO_TAKER, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), DEPOSIT);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 1_980);
+    assert_eq!(get_token_account_amount(&scenario.accounts[&derive_associated_token_account(&arbiter, &scenario.mint_b)]), 20);
+    assert!(scenario.escrow_state(&accounts).is_none());
+
+    // For the maker both sides get all their tokens back, and the arbiter
+    // takes nothing from an unwound trade
+    let mut scenario = taken_scenario(&mollusk, &arbiter, 100);
+    let accounts = resolve(&mollusk, &mut scenario, &arbiter, ResolveInstructionData::TO_MAKER, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), DEPOSIT);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerB), 3_000);
+    assert_eq!(get_token_account_amount(&scenario.accounts[&derive_associated_token_account(&arbiter, &scenario.mint_b)]), 0);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 0);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 0);
+
+    // A quarter of the trade completes, and the maker pays 1% of that quarter
+    let mut scenario = taken_scenario(&mollusk, &arbiter, 100);
+    let accounts = resolve(&mollusk, &mut scenario, &arbiter, 2_500, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 375);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 1_125);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 495);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerB), 2_500);
+    assert_eq!(get_token_account_amount(&scenario.accounts[&derive_associated_token_account(&arbiter, &scenario.mint_b)]), 5);
+    assert!(scenario.token_account(&accounts, Ata::Vault).is_none());
+    assert!(scenario.token_account(&accounts, Ata::PaymentVault).is_none());
+}
+
+#[test]
+fn test_release_and_resolve_are_paused_with_take() {
+    let mollusk = setup_mollusk();
+    let arbiter = Pubkey::new_unique();
+    let mut scenario = taken_scenario(&mollusk, &arbiter, 100);
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(&Pubkey::new_unique(), Config::PAUSE_TAKE, 0));
+
+    let instruction = with_associated_token_program(scenario.release_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::InstructionPaused));
+    resolve(&mollusk, &mut scenario, &arbiter, ResolveInstructionData::TO_MAKER, escrow_error(EscrowError::InstructionPaused));
+
+    // An empty account in place of the paused config must not bypass the pause
+    let mut instruction = with_associated_token_program(scenario.release_instruction());
+    let config = instruction.accounts.iter_mut().find(|meta| meta.pubkey == CONFIG_ADDRESS).unwrap();
+    config.pubkey = Pubkey::new_unique();
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ConfigMismatch));
+
+    // Both settle once the admin unpauses
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(&Pubkey::new_unique(), Config::PAUSE_MAKE | Config::PAUSE_REFUND, 0));
+    let accounts = resolve(&mollusk, &mut scenario, &arbiter, ResolveInstructionData::TO_MAKER, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), DEPOSIT);
+}
+
+#[test]
+fn test_resolve_rejects_other_arbiters() {
+    let mollusk = setup_mollusk();
+    let arbiter = Pubkey::new_unique();
+    let mut scenario = taken_scenario(&mollusk, &arbiter, 100);
+
+    let impostor = Pubkey::new_unique();
+    scenario.set_account(impostor, Account::new(10_000_000, 0, &solana_system_program::id()));
+    let instruction = with_associated_token_program(scenario.resolve_instruction(&impostor, ResolveInstructionData::TO_TAKER));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+
+    // The fee needs somewhere to go, and a ruling can't complete more than
+    // the whole trade
+    let instruction = with_associated_token_program(scenario.resolve_instruction(&arbiter, ResolveInstructionData::TO_TAKER));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ArbiterAccountMissing));
+
+    // The fee only goes to the arbiter's associated token account
+    let other_account = Pubkey::new_unique();
+    scenario.set_account(other_account, create_token_account(&arbiter, &scenario.mint_b, 0));
+    let instruction = with_associated_token_program(scenario.resolve_instruction(&arbiter, ResolveInstructionData::TO_TAKER));
+    let instruction = with_arbiter_account(instruction, &other_account);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)));
+    resolve(&mollusk, &mut scenario, &arbiter, 10_001, ProgramResult::Failure(ProgramError::InvalidInstructionData));
+}
@@ -0,0 +1,110 @@
+//! Authorized releases: Take on an escrow with an authorizer needs an earlier
+//! Ed25519 instruction in which the authorizer signed the escrow, the taker,
+//! the escrow's state and an expiry.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{Escrow, EscrowError},
+    mollusk_svm::{result::{Check, ProgramResult}, Mollusk},
+    solana_instruction::Instruction,
+    solana_keypair::Keypair,
+    solana_pubkey::Pubkey,
+    solana_signer::Signer,
+};
+
+const NOW: i64 = 1_000;
+const EXPIRY: i64 = 2_000;
+
+fn authorized_scenario(authorizer: &Keypair) -> EscrowScenario {
+    EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .authorizer(authorizer.pubkey())
+        .build()
+}
+
+/// Runs Take in a transaction made of `preceding`, then Take.
+fn take_after(mollusk: &Mollusk, scenario: &mut EscrowScenario, preceding: &[Instruction], expected: ProgramResult) {
+    let take = with_instructions_sysvar(with_associated_token_program(scenario.take_instruction()));
+    let mut transaction: Vec<&Instruction> = preceding.iter().collect();
+    transaction.push(&take);
+    scenario.set_account(solana_instructions_sysvar::ID, create_instructions_sysvar_account(&transaction, preceding.len() as u16));
+
+    mollusk.process_and_validate_instruction(&take, &scenario.accounts_for(&take), &[Check::program_result(expected)]);
+}
+
+/// The authorizer's release of `escrow`, in the state `scenario` holds, to `taker`.
+fn release(authorizer: &Keypair, scenario: &EscrowScenario, escrow: &Pubkey, taker: &Pubkey, expiry: i64) -> Instruction {
+    let escrow_data = &scenario.accounts[&scenario.escrow].data;
+    create_ed25519_instruction(authorizer, &Escrow::authorization_message(&escrow.to_bytes(), escrow_data, &taker.to_bytes(), expiry))
+}
+
+#[test]
+fn test_make_records_authorizer() {
+    let mollusk = mollusk_at(NOW);
+    let scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let authorizer = Pubkey::new_unique();
+    let instruction = with_associated_token_program(with_authorizer(scenario.make_instruction(750, 400), &authorizer));
+
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+    let (_, account) = result.resulting_accounts.iter().find(|(key, _)| *key == scenario.escrow).unwrap();
+    let escrow = Escrow::load(&account.data).unwrap();
+    assert!(escrow.requires_authorization() && !escrow.is_hashlocked());
+    assert_eq!(Pubkey::new_from_array(escrow.authorizer), authorizer);
+}
+
+#[test]
+fn test_take_with_the_authorizers_release() {
+    let mollusk = mollusk_at(NOW);
+    let authorizer = Keypair::new();
+    let mut scenario = authorized_scenario(&authorizer);
+
+    let release = release(&authorizer, &scenario, &scenario.escrow, &scenario.taker, EXPIRY);
+    take_after(&mollusk, &mut scenario, &[release], ProgramResult::Success);
+}
+
+#[test]
+fn test_take_rejects_missing_and_foreign_releases() {
+    let mollusk = mollusk_at(NOW);
+    let authorizer = Keypair::new();
+    let mut scenario = authorized_scenario(&authorizer);
+    let (escrow, taker) = (scenario.escrow, scenario.taker);
+
+    // No Instructions sysvar, or no release before Take
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::program_result(escrow_error(EscrowError::AuthorizationMissing))]);
+    take_after(&mollusk, &mut scenario, &[], escrow_error(EscrowError::AuthorizationMissing));
+
+    // Signed by someone else, for another taker or for another escrow
+    let foreign = [
+        release(&Keypair::new(), &scenario, &escrow, &taker, EXPIRY),
+        release(&authorizer, &scenario, &escrow, &Pubkey::new_unique(), EXPIRY),
+        release(&authorizer, &scenario, &Pubkey::new_unique(), &taker, EXPIRY),
+    ];
+    for release in foreign {
+        take_after(&mollusk, &mut scenario, &[release], escrow_error(EscrowError::AuthorizationMissing));
+    }
+
+    // Signed over an escrow state that the account no longer holds, as when
+    // the seed is reused after a Refund
+    let mut earlier = scenario.accounts[&escrow].data.clone();
+    Escrow::load_mut(&mut earlier).unwrap().receive = 1_000;
+    let stale = create_ed25519_instruction(&authorizer, &Escrow::authorization_message(&escrow.to_bytes(), &earlier, &taker.to_bytes(), EXPIRY));
+    take_after(&mollusk, &mut scenario, &[stale], escrow_error(EscrowError::AuthorizationMissing));
+
+    // The signature itself must sit in the Ed25519 instruction too
+    let mut elsewhere = release(&authorizer, &scenario, &escrow, &taker, EXPIRY);
+    elsewhere.data[4..6].copy_from_slice(&0u16.to_le_bytes()); // signature_instruction_index
+    take_after(&mollusk, &mut scenario, &[elsewhere], escrow_error(EscrowError::AuthorizationMissing));
+}
+
+#[test]
+fn test_take_rejects_expired_releases() {
+    let authorizer = Keypair::new();
+    let mut scenario = authorized_scenario(&authorizer);
+
+    let release = release(&authorizer, &scenario, &scenario.escrow, &scenario.taker, EXPIRY);
+    take_after(&mollusk_at(EXPIRY), &mut scenario, &[release], escrow_error(EscrowError::AuthorizationExpired));
+}
@@ -0,0 +1,216 @@
+//! Compute unit benchmarks for the Make, Take and Refund instructions.
+//!
+//! Run with `cargo bench -p blueshift_escrow_tests`. The results are written to
+//! `tests/benches/compute_units.md`, and the bench fails if any scenario uses
+//! more compute units than its entry in `THRESHOLDS`.
+
+#[allow(dead_code)]
+#[path = "../helpers.rs"]
+mod helpers;
+#[allow(dead_code)]
+#[path = "../scenario.rs"]
+mod scenario;
+
+use {
+    helpers::*,
+    mollusk_svm::{program::keyed_account_for_system_program, result::ProgramResult},
+    mollusk_svm_bencher::MolluskComputeUnitBencher,
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+};
+
+/// Maximum compute units allowed per scenario, about 10% above what it
+/// costs. Raise a value only together with the change that makes the
+/// instruction more expensive, and lower it with one that makes it cheaper.
+const THRESHOLDS: &[(&str, u64)] = &[
+    ("make_existing_vault", 27_000),
+    ("make_new_vault", 46_000),
+    ("take_existing_atas", 53_000),
+    ("take_new_atas", 87_000),
+    ("refund_existing_ata", 27_000),
+    ("refund_new_ata", 50_000),
+];
+
+struct Scenario {
+    name: &'static str,
+    instruction: Instruction,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+fn loader_owned_account() -> Account {
+    Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))
+}
+
+fn make_scenario(name: &'static str, existing_vault: bool) -> Scenario {
+    let seed = 1u64;
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, _bump) = derive_escrow_pda(&maker, seed);
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+
+    let instruction = with_associated_token_program(create_make_instruction(
+        &maker, &escrow, &mint_a, &mint_b, &maker_ata_a, &vault, seed, 1_000, 500,
+    ));
+
+    let vault_account = if existing_vault {
+        create_token_account(&escrow, &mint_a, 0)
+    } else {
+        Account::new(0, 0, &solana_system_program::id())
+    };
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, Account::new(0, 0, &solana_system_program::id())),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (mint_b, create_mint_account(&mint_authority, 6)),
+        (maker_ata_a, create_token_account(&maker, &mint_a, 1_000)),
+        (vault, vault_account),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        keyed_denylist_entry_account(&maker),
+        keyed_maker_state_account(&maker),
+        (spl_token::ID, loader_owned_account()),
+        (ATOKEN_PROGRAM_ID, loader_owned_account()),
+    ];
+
+    Scenario { name, instruction, accounts }
+}
+
+fn take_scenario(name: &'static str, existing_atas: bool) -> Scenario {
+    let seed = 2u64;
+    let maker = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let taker_ata_a = derive_associated_token_account(&taker, &mint_a);
+    let taker_ata_b = derive_associated_token_account(&taker, &mint_b);
+    let maker_ata_b = derive_associated_token_account(&maker, &mint_b);
+
+    let instruction = with_associated_token_program(create_take_instruction(
+        &taker, &maker, &escrow, &mint_a, &mint_b, &vault, &taker_ata_a, &taker_ata_b, &maker_ata_b,
+    ));
+
+    let (taker_ata_a_account, maker_ata_b_account) = if existing_atas {
+        (create_token_account(&taker, &mint_a, 0), create_token_account(&maker, &mint_b, 0))
+    } else {
+        (
+            Account::new(0, 0, &solana_system_program::id()),
+            Account::new(0, 0, &solana_system_program::id()),
+        )
+    };
+
+    let accounts = vec![
+        (taker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, 2_000, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (mint_b, create_mint_account(&mint_authority, 6)),
+        (vault, create_token_account(&escrow, &mint_a, 1_500)),
+        (taker_ata_a, taker_ata_a_account),
+        (taker_ata_b, create_token_account(&taker, &mint_b, 3_000)),
+        (maker_ata_b, maker_ata_b_account),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        keyed_denylist_entry_account(&taker),
+        keyed_maker_state_account(&maker),
+        (spl_token::ID, loader_owned_account()),
+        (ATOKEN_PROGRAM_ID, loader_owned_account()),
+    ];
+
+    Scenario { name, instruction, accounts }
+}
+
+fn refund_scenario(name: &'static str, existing_ata: bool) -> Scenario {
+    let seed = 3u64;
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+
+    let instruction = with_associated_token_program(create_refund_instruction(
+        &maker, &escrow, &mint_a, &vault, &maker_ata_a,
+    ));
+
+    let maker_ata_a_account = if existing_ata {
+        create_token_account(&maker, &mint_a, 0)
+    } else {
+        Account::new(0, 0, &solana_system_program::id())
+    };
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, 2_000, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint_a, 1_500)),
+        (maker_ata_a, maker_ata_a_account),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, loader_owned_account()),
+        (ATOKEN_PROGRAM_ID, loader_owned_account()),
+    ];
+
+    Scenario { name, instruction, accounts }
+}
+
+fn main() {
+    let scenarios = vec![
+        make_scenario("make_existing_vault", true),
+        make_scenario("make_new_vault", false),
+        take_scenario("take_existing_atas", true),
+        take_scenario("take_new_atas", false),
+        refund_scenario("refund_existing_ata", true),
+        refund_scenario("refund_new_ata", false),
+    ];
+
+    // Record the markdown table first so the numbers are available even when a
+    // threshold check below fails.
+    let mut bencher = MolluskComputeUnitBencher::new(setup_mollusk()).must_pass(true);
+    for scenario in &scenarios {
+        bencher = bencher.bench((scenario.name, &scenario.instruction, &scenario.accounts));
+    }
+    bencher.execute();
+
+    let mollusk = setup_mollusk();
+    let mut regressions = Vec::new();
+    for scenario in &scenarios {
+        let result = mollusk.process_instruction(&scenario.instruction, &scenario.accounts);
+        assert!(
+            matches!(result.program_result, ProgramResult::Success),
+            "{} failed: {:?}",
+            scenario.name,
+            result.program_result
+        );
+
+        let threshold = THRESHOLDS
+            .iter()
+            .find(|(name, _)| *name == scenario.name)
+            .map(|(_, threshold)| *threshold)
+            .unwrap_or_else(|| panic!("no threshold defined for {}", scenario.name));
+
+        if result.compute_units_consumed > threshold {
+            regressions.push(format!(
+                "{}: {} CUs exceeds threshold of {}",
+                scenario.name, result.compute_units_consumed, threshold
+            ));
+        }
+    }
+
+    assert!(
+        regressions.is_empty(),
+        "compute unit regressions:\n{}",
+        regressions.join("\n")
+    );
+}
@@ -0,0 +1,108 @@
+//! Bulk cancellation: CancelAll advances the maker's nonce, and Take rejects
+//! every escrow made under an older one. Refund still returns their deposits.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{EscrowError, MakerState, PinocchioError},
+    mollusk_svm::result::ProgramResult,
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+fn maker_nonce(accounts: &[(Pubkey, Account)], maker: &Pubkey) -> u64 {
+    let (_, account) = accounts.iter().find(|(key, _)| *key == derive_maker_state_pda(maker).0).unwrap();
+    MakerState::load(&account.data).unwrap().nonce
+}
+
+#[test]
+fn test_cancel_all_advances_the_maker_nonce() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder().build();
+    let maker = scenario.maker;
+
+    // The first CancelAll creates the maker state, paid by the maker
+    let accounts = run(&mollusk, &mut scenario, create_cancel_all_instruction(&maker), ProgramResult::Success);
+    assert_eq!(maker_nonce(&accounts, &maker), 1);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == derive_maker_state_pda(&maker).0).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    assert_eq!(MakerState::load(&account.data).unwrap().bump[0], derive_maker_state_pda(&maker).1);
+
+    let accounts = run(&mollusk, &mut scenario, create_cancel_all_instruction(&maker), ProgramResult::Success);
+    assert_eq!(maker_nonce(&accounts, &maker), 2);
+
+    // Only the maker can cancel
+    let mut instruction = create_cancel_all_instruction(&maker);
+    instruction.accounts[0].is_signer = false;
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::Custom(PinocchioError::NotSigner as u32)));
+}
+
+#[test]
+fn test_cancel_all_with_prefunded_maker_state() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder().build();
+    let maker = scenario.maker;
+    let maker_state = derive_maker_state_pda(&maker).0;
+
+    // Lamports sent to the maker state ahead of time don't stop the maker
+    // from cancelling
+    scenario.set_account(maker_state, Account::new(1_000, 0, &solana_system_program::id()));
+    let accounts = run(&mollusk, &mut scenario, create_cancel_all_instruction(&maker), ProgramResult::Success);
+    assert_eq!(maker_nonce(&accounts, &maker), 1);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == maker_state).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    assert_eq!(account.lamports, mollusk.sysvars.rent.minimum_balance(MakerState::LEN));
+}
+
+#[test]
+fn test_take_rejects_escrows_made_before_cancel_all() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    let maker = scenario.maker;
+    run(&mollusk, &mut scenario, create_cancel_all_instruction(&maker), ProgramResult::Success);
+
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::EscrowCancelled));
+
+    // The maker refunds the cancelled escrow at leisure
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 1_500);
+}
+
+#[test]
+fn test_escrows_made_after_cancel_all_can_be_taken() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    scenario.set_account(derive_maker_state_pda(&scenario.maker).0, create_maker_state_account(&scenario.maker, 3));
+
+    let instruction = with_associated_token_program(scenario.make_instruction(750, 400));
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.escrow_state(&accounts).unwrap().maker_nonce, 3);
+
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 400);
+}
+
+#[test]
+fn test_take_requires_the_makers_state_pda() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+
+    // Another maker's state, which CancelAll never advanced
+    let mut instruction = with_associated_token_program(scenario.take_instruction());
+    let maker_state = derive_maker_state_pda(&scenario.maker).0;
+    let meta = instruction.accounts.iter_mut().find(|meta| meta.pubkey == maker_state).unwrap();
+    meta.pubkey = derive_maker_state_pda(&Pubkey::new_unique()).0;
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::MakerStateMismatch));
+}
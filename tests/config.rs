@@ -0,0 +1,157 @@
+//! The program-wide `Config`: its initialization by the upgrade authority,
+//! the admin's pause flags and how Make, Take and Refund honour them.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{Config, EscrowError},
+    mollusk_svm::result::ProgramResult,
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+fn paused_scenario(admin: &Pubkey, paused: u8) -> EscrowScenario {
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(admin, paused, 0));
+    scenario
+}
+
+fn config_state(accounts: &[(Pubkey, Account)]) -> (Pubkey, u8) {
+    let (_, account) = accounts.iter().find(|(key, _)| *key == CONFIG_ADDRESS).unwrap();
+    let config = Config::load(&account.data).unwrap();
+    (Pubkey::new_from_array(config.admin), config.paused)
+}
+
+#[test]
+fn test_config_address_is_the_config_pda() {
+    assert_eq!(
+        Pubkey::find_program_address(&[Config::SEED], &PROGRAM_ID),
+        (CONFIG_ADDRESS, Config::BUMP),
+    );
+}
+
+#[test]
+fn test_initialize_config_by_upgrade_authority() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder().build();
+    let (authority, admin) = (scenario.maker, Pubkey::new_unique());
+    let (program_data, account) = create_program_data_account(Some(&authority));
+    scenario.set_account(program_data, account);
+
+    let accounts = run(&mollusk, &mut scenario, create_initialize_config_instruction(&authority, &admin), ProgramResult::Success);
+    assert_eq!(config_state(&accounts), (admin, 0));
+
+    // The config can only be initialized once
+    run(
+        &mollusk,
+        &mut scenario,
+        create_initialize_config_instruction(&authority, &Pubkey::new_unique()),
+        ProgramResult::Failure(ProgramError::Custom(0)), // SystemError::AccountAlreadyInUse
+    );
+}
+
+#[test]
+fn test_initialize_config_that_was_prefunded() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder().build();
+    let (authority, admin) = (scenario.maker, Pubkey::new_unique());
+    let (program_data, account) = create_program_data_account(Some(&authority));
+    scenario.set_account(program_data, account);
+
+    // Lamports sent to the config address ahead of time don't block it
+    scenario.set_account(CONFIG_ADDRESS, Account::new(1_000, 0, &solana_system_program::id()));
+    let accounts = run(&mollusk, &mut scenario, create_initialize_config_instruction(&authority, &admin), ProgramResult::Success);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == CONFIG_ADDRESS).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    assert_eq!(account.lamports, mollusk.sysvars.rent.minimum_balance(Config::LEN));
+    assert_eq!(config_state(&accounts), (admin, 0));
+}
+
+#[test]
+fn test_initialize_config_rejects_other_signers() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder().build();
+    let (program_data, account) = create_program_data_account(Some(&Pubkey::new_unique()));
+    scenario.set_account(program_data, account);
+
+    let instruction = create_initialize_config_instruction(&scenario.maker, &scenario.maker);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+
+    // An immutable program has no upgrade authority to hand out the admin role
+    let (program_data, account) = create_program_data_account(None);
+    scenario.set_account(program_data, account);
+    let instruction = create_initialize_config_instruction(&scenario.maker, &scenario.maker);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+}
+
+#[test]
+fn test_set_pause_by_admin() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = paused_scenario(&admin, 0);
+
+    let accounts = run(&mollusk, &mut scenario, create_set_pause_instruction(&admin, Config::PAUSE_ALL), ProgramResult::Success);
+    assert_eq!(config_state(&accounts), (admin, Config::PAUSE_ALL));
+
+    let accounts = run(&mollusk, &mut scenario, create_set_pause_instruction(&admin, 0), ProgramResult::Success);
+    assert_eq!(config_state(&accounts), (admin, 0));
+
+    // Unknown bits are rejected
+    let instruction = create_set_pause_instruction(&admin, Config::PAUSE_ALL + 1);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::InvalidInstructionData));
+}
+
+#[test]
+fn test_set_pause_rejects_other_signers() {
+    let mollusk = setup_mollusk();
+    let mut scenario = paused_scenario(&Pubkey::new_unique(), 0);
+
+    let instruction = create_set_pause_instruction(&scenario.maker, Config::PAUSE_MAKE);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+}
+
+#[test]
+fn test_paused_instructions_fail() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+
+    let mut scenario = paused_scenario(&admin, Config::PAUSE_MAKE);
+    let instruction = with_associated_token_program(scenario.make_instruction(500, 400));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::InstructionPaused));
+
+    let mut scenario = paused_scenario(&admin, Config::PAUSE_TAKE);
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::InstructionPaused));
+
+    let mut scenario = paused_scenario(&admin, Config::PAUSE_REFUND);
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::InstructionPaused));
+}
+
+#[test]
+fn test_refund_works_while_make_and_take_are_paused() {
+    let mollusk = setup_mollusk();
+    let mut scenario = paused_scenario(&Pubkey::new_unique(), Config::PAUSE_MAKE | Config::PAUSE_TAKE);
+
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 2_500);
+}
+
+#[test]
+fn test_instructions_reject_another_config_account() {
+    let mollusk = setup_mollusk();
+    let mut scenario = paused_scenario(&Pubkey::new_unique(), Config::PAUSE_ALL);
+
+    // An empty account in place of the paused config must not bypass the pause
+    for instruction in [scenario.take_instruction(), scenario.refund_instruction()] {
+        let mut instruction = with_associated_token_program(instruction);
+        let config = instruction.accounts.iter_mut().find(|meta| meta.pubkey == CONFIG_ADDRESS).unwrap();
+        config.pubkey = Pubkey::new_unique();
+        run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ConfigMismatch));
+    }
+}
@@ -0,0 +1,89 @@
+//! Replays conformance fixtures against the built program.
+//!
+//! Fixtures are Mollusk's protobuf instruction fixtures: the input accounts,
+//! instruction, sysvars and feature set of one instruction, plus the effects
+//! it had. Record them from the example tests with
+//!
+//! ```text
+//! EJECT_FUZZ_FIXTURES=../fixtures cargo test          # Mollusk format
+//! EJECT_FUZZ_FIXTURES_FD=../fixtures-fd cargo test    # Firedancer format
+//! ```
+//!
+//! and replay a directory of Mollusk fixtures, from any runtime, with
+//!
+//! ```text
+//! CONFORMANCE_FIXTURES=../fixtures cargo test conformance -- --nocapture
+//! ```
+//!
+//! Every fixture is run, and each difference in program result, return data
+//! or resulting accounts is printed before the test fails. Compute units are
+//! not compared, since they differ between runtimes.
+//!
+//! A fixture is written for every instruction run through
+//! `process_and_validate_instruction`, which includes the shared `run` helper
+//! behind the scenario suites. Some suites are deliberately left out:
+//!
+//! - `invariants` and `substitution` generate thousands of random cases per
+//!   run, which would swamp the fixture directory with near-duplicates.
+//! - `transactions` runs whole transactions in LiteSVM, which has no
+//!   instruction fixture format.
+
+use {
+    crate::helpers::*,
+    mollusk_svm::result::{Compare, InstructionResult},
+    mollusk_svm_fuzz_fixture::Fixture,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+fn fixture_files(dir: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("can't read fixture directory {dir}: {err}"))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "fix" || ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn load(path: &Path) -> Fixture {
+    let path = path.to_str().unwrap();
+    if path.ends_with(".json") {
+        Fixture::load_from_json_file(path)
+    } else {
+        Fixture::load_from_blob_file(path)
+    }
+}
+
+#[test]
+fn test_replay_conformance_fixtures() {
+    let Ok(dir) = std::env::var("CONFORMANCE_FIXTURES") else {
+        println!("CONFORMANCE_FIXTURES is not set, no fixtures replayed");
+        return;
+    };
+
+    let files = fixture_files(&dir);
+    assert!(!files.is_empty(), "no .fix or .json fixtures in {dir}");
+
+    let mut mollusk = setup_mollusk();
+    // Report every difference instead of stopping at the first one
+    mollusk.config.panic = false;
+    mollusk.config.verbose = true;
+
+    let mut differing = Vec::new();
+    for path in &files {
+        let fixture = load(path);
+        let expected = InstructionResult::from(&fixture.output);
+        let result = mollusk.process_fixture(&fixture);
+
+        println!("{}", path.display());
+        if !result.compare_with_config(&expected, &Compare::everything_but_cus(), &mollusk.config) {
+            differing.push(path.display().to_string());
+        }
+    }
+
+    println!("{} of {} fixtures match", files.len() - differing.len(), files.len());
+    assert!(differing.is_empty(), "fixtures with differences:\n{}", differing.join("\n"));
+}
@@ -0,0 +1,146 @@
+//! The admin's wallet denylist: entries are PDAs keyed by wallet, and Make
+//! and Take prove the maker or taker isn't denied by passing the PDA empty.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{DenylistEntry, EscrowError},
+    mollusk_svm::result::ProgramResult,
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+// An open escrow, a taker able to pay, and an admin
+fn denylist_scenario(admin: &Pubkey) -> EscrowScenario {
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(admin, 0, 0));
+    scenario.set_account(*admin, Account::new(10_000_000, 0, &solana_system_program::id()));
+    scenario
+}
+
+fn deny(scenario: &mut EscrowScenario, wallet: &Pubkey) {
+    scenario.set_account(derive_denylist_pda(wallet).0, create_denylist_entry_account(wallet));
+}
+
+#[test]
+fn test_add_and_remove_denylist_entry() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = denylist_scenario(&admin);
+    let maker = scenario.maker;
+    let entry = derive_denylist_pda(&maker).0;
+
+    let accounts = run(&mollusk, &mut scenario, create_add_denylist_entry_instruction(&admin, &maker), ProgramResult::Success);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == entry).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    assert_eq!(DenylistEntry::load(&account.data).unwrap().wallet, maker.to_bytes());
+
+    // A wallet can only be denied once
+    run(
+        &mollusk,
+        &mut scenario,
+        create_add_denylist_entry_instruction(&admin, &maker),
+        ProgramResult::Failure(ProgramError::Custom(0)), // SystemError::AccountAlreadyInUse
+    );
+
+    let accounts = run(&mollusk, &mut scenario, create_remove_denylist_entry_instruction(&admin, &maker), ProgramResult::Success);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == entry).unwrap();
+    assert_eq!(account.lamports, 0);
+    assert!(account.data.is_empty());
+}
+
+#[test]
+fn test_deny_wallet_that_prefunded_its_entry() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = denylist_scenario(&admin);
+    let taker = scenario.taker;
+    let entry = derive_denylist_pda(&taker).0;
+
+    // Lamports sent to the entry ahead of time don't keep the taker off the list
+    scenario.set_account(entry, Account::new(1_000, 0, &solana_system_program::id()));
+    let accounts = run(&mollusk, &mut scenario, create_add_denylist_entry_instruction(&admin, &taker), ProgramResult::Success);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == entry).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    assert_eq!(account.lamports, mollusk.sysvars.rent.minimum_balance(DenylistEntry::LEN));
+    assert_eq!(DenylistEntry::load(&account.data).unwrap().wallet, taker.to_bytes());
+
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::WalletDenied));
+}
+
+#[test]
+fn test_denylist_entries_are_admin_only() {
+    let mollusk = setup_mollusk();
+    let mut scenario = denylist_scenario(&Pubkey::new_unique());
+    let (maker, taker) = (scenario.maker, scenario.taker);
+
+    let instruction = create_add_denylist_entry_instruction(&maker, &taker);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+
+    deny(&mut scenario, &maker);
+    let instruction = create_remove_denylist_entry_instruction(&maker, &maker);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+}
+
+#[test]
+fn test_denied_maker_cannot_make() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(&admin, 0, 0));
+    scenario.set_account(admin, Account::new(10_000_000, 0, &solana_system_program::id()));
+    let maker = scenario.maker;
+    let make = with_associated_token_program(scenario.make_instruction(500, 400));
+
+    run(&mollusk, &mut scenario, create_add_denylist_entry_instruction(&admin, &maker), ProgramResult::Success);
+    run(&mollusk, &mut scenario, make.clone(), escrow_error(EscrowError::WalletDenied));
+
+    // Removing the entry lets the maker back in
+    run(&mollusk, &mut scenario, create_remove_denylist_entry_instruction(&admin, &maker), ProgramResult::Success);
+    run(&mollusk, &mut scenario, make, ProgramResult::Success);
+}
+
+#[test]
+fn test_denied_taker_cannot_take() {
+    let mollusk = setup_mollusk();
+    let mut scenario = denylist_scenario(&Pubkey::new_unique());
+    let taker = scenario.taker;
+    deny(&mut scenario, &taker);
+
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::WalletDenied));
+}
+
+#[test]
+fn test_denied_maker_can_still_refund() {
+    let mollusk = setup_mollusk();
+    let mut scenario = denylist_scenario(&Pubkey::new_unique());
+    let maker = scenario.maker;
+    deny(&mut scenario, &maker);
+
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 2_500);
+}
+
+#[test]
+fn test_non_membership_proof_must_be_the_wallets_entry() {
+    let mollusk = setup_mollusk();
+    let mut scenario = denylist_scenario(&Pubkey::new_unique());
+    let taker = scenario.taker;
+    deny(&mut scenario, &taker);
+
+    // The empty entry of another wallet doesn't prove anything about the taker
+    let mut instruction = with_associated_token_program(scenario.take_instruction());
+    let entry = derive_denylist_pda(&scenario.taker).0;
+    let proof = instruction.accounts.iter_mut().find(|meta| meta.pubkey == entry).unwrap();
+    proof.pubkey = derive_denylist_pda(&Pubkey::new_unique()).0;
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::DenylistEntryMismatch));
+}
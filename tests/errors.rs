@@ -0,0 +1,83 @@
+use {
+    blueshift_escrow::{decode_error, EscrowError, PinocchioError},
+    pinocchio::program_error::{ProgramError, ToStr},
+};
+
+const ESCROW_ERRORS: [EscrowError; 44] = [
+    EscrowError::ZeroDeposit,
+    EscrowError::SeedCollision,
+    EscrowError::EscrowPdaMismatch,
+    EscrowError::MintMismatch,
+    EscrowError::MakerMismatch,
+    EscrowError::VaultBalanceMismatch,
+    EscrowError::UnsupportedTokenProgram,
+    EscrowError::UnsafeMintExtension,
+    EscrowError::MemoProgramMissing,
+    EscrowError::CpiGuardEnabled,
+    EscrowError::ConfigMismatch,
+    EscrowError::InstructionPaused,
+    EscrowError::Unauthorized,
+    EscrowError::MintPolicyMissing,
+    EscrowError::MintPolicyMismatch,
+    EscrowError::MintNotAllowed,
+    EscrowError::AmountOutOfRange,
+    EscrowError::DenylistEntryMismatch,
+    EscrowError::WalletDenied,
+    EscrowError::ReferralFeeTooHigh,
+    EscrowError::ReferrerMissing,
+    EscrowError::ReceiptMismatch,
+    EscrowError::ReceiptMissing,
+    EscrowError::HashlockedEscrow,
+    EscrowError::NotHashlocked,
+    EscrowError::PreimageMismatch,
+    EscrowError::HashlockExpired,
+    EscrowError::HashlockNotExpired,
+    EscrowError::AuthorizationMissing,
+    EscrowError::AuthorizationExpired,
+    EscrowError::OrderNonceMismatch,
+    EscrowError::OrderSignatureMissing,
+    EscrowError::OrderExpired,
+    EscrowError::OrderNonceInvalid,
+    EscrowError::MakerStateMismatch,
+    EscrowError::EscrowCancelled,
+    EscrowError::NotArbitrated,
+    EscrowError::EscrowNotTaken,
+    EscrowError::EscrowAlreadyTaken,
+    EscrowError::PaymentVaultMissing,
+    EscrowError::ArbiterAccountMissing,
+    EscrowError::OrderCancelled,
+    EscrowError::SelfReferral,
+    EscrowError::OrderNonceLive,
+];
+
+#[test]
+fn test_escrow_error_codes_round_trip() {
+    for error in ESCROW_ERRORS {
+        let ProgramError::Custom(code) = ProgramError::from(error) else {
+            panic!("{error:?} is not a custom error");
+        };
+        assert_eq!(EscrowError::try_from(code), Ok(error));
+        assert_eq!(decode_error(code), Some(error.to_str::<EscrowError>()));
+        assert_eq!(error.to_str::<EscrowError>(), format!("Error: {error}"));
+    }
+}
+
+#[test]
+fn test_escrow_error_codes_stay_in_reserved_range() {
+    for (i, error) in ESCROW_ERRORS.into_iter().enumerate() {
+        let code = error as u32;
+        assert_eq!(code, EscrowError::FIRST_CODE + i as u32);
+        assert!(code <= EscrowError::LAST_CODE);
+    }
+    assert_eq!(EscrowError::try_from(EscrowError::FIRST_CODE - 1), Err(ProgramError::InvalidArgument));
+    assert_eq!(EscrowError::try_from(EscrowError::LAST_CODE), Err(ProgramError::InvalidArgument));
+}
+
+#[test]
+fn test_decode_error_covers_every_program_error() {
+    assert_eq!(decode_error(PinocchioError::NotSigner as u32), Some(PinocchioError::NotSigner.to_str::<PinocchioError>()));
+    assert_eq!(decode_error(EscrowError::ZeroDeposit as u32), Some("Error: Deposit amount must be greater than zero"));
+    // Token program errors share the low codes, but not the reserved range
+    assert_eq!(decode_error(EscrowError::LAST_CODE), None);
+    assert_eq!(decode_error(u32::MAX), None);
+}
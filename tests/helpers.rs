@@ -1,9 +1,12 @@
 use {
-    mollusk_svm::Mollusk, solana_account::{Account, WritableAccount}, solana_instruction::{AccountMeta, Instruction}, solana_pubkey::Pubkey, solana_system_program, spl_token::solana_program::program_pack::Pack
+    crate::scenario::EscrowScenario, mollusk_svm::{result::{Check, ProgramResult}, Mollusk}, solana_account::{Account, WritableAccount}, solana_instruction::{AccountMeta, BorrowedAccountMeta, BorrowedInstruction, Instruction}, solana_keypair::Keypair, solana_pubkey::Pubkey, solana_signer::Signer, spl_token::solana_program::{program_error::ProgramError, program_pack::Pack}
 };
 
 pub const PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("22222222222222222222222222222222222222222222");
 pub const ATOKEN_PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+pub const MEMO_PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+pub const BPF_LOADER_UPGRADEABLE_ID: Pubkey = solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+pub const CONFIG_ADDRESS: Pubkey = Pubkey::new_from_array(blueshift_escrow::Config::ADDRESS);
 //const TOKEN_PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
 //const TOKEN_2022_PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
 
@@ -14,14 +17,39 @@ pub fn setup_mollusk() -> Mollusk {
     mollusk.add_program(&PROGRAM_ID, "blueshift_escrow", &mollusk_svm::program::loader_keys::LOADER_V3);
     mollusk.add_program(&spl_token::ID, "../../tests/elf/token", &mollusk_svm::program::loader_keys::LOADER_V3);
     mollusk.add_program(&ATOKEN_PROGRAM_ID, "../../tests/elf/associated_token", &mollusk_svm::program::loader_keys::LOADER_V3);
-    // Token 2022 is not needed, but if you want to test with it, uncomment below
-    //mollusk.add_program(&spl_token_2022::ID, "../../tests/elf/token_2022", &mollusk_svm::program::loader_keys::LOADER_V3);
+    mollusk.add_program(&spl_token_2022::ID, "../../tests/elf/token_2022", &mollusk_svm::program::loader_keys::LOADER_V3);
+    mollusk.add_program(&MEMO_PROGRAM_ID, "../../tests/elf/memo", &mollusk_svm::program::loader_keys::LOADER_V2);
     
     mollusk
 }
 
+/// The failure of an instruction that returned `error`.
+pub fn escrow_error(error: blueshift_escrow::EscrowError) -> ProgramResult {
+    ProgramResult::Failure(ProgramError::Custom(error as u32))
+}
+
+/// Runs `instruction` against the scenario's accounts, checks the result and
+/// carries the resulting accounts into the scenario. Like every validated
+/// case, it writes a conformance fixture when `EJECT_FUZZ_FIXTURES` is set.
+pub fn run(mollusk: &Mollusk, scenario: &mut EscrowScenario, instruction: Instruction, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(expected)],
+    );
+    scenario.apply(&result.resulting_accounts);
+    result.resulting_accounts
+}
+
+/// A Mollusk whose clock reads `unix_timestamp`.
+pub fn mollusk_at(unix_timestamp: i64) -> Mollusk {
+    let mut mollusk = setup_mollusk();
+    mollusk.sysvars.clock.unix_timestamp = unix_timestamp;
+    mollusk
+}
 
 // Helper function to create a Make instruction
+#[allow(clippy::too_many_arguments)]
 pub fn create_make_instruction(
     maker: &Pubkey,
     escrow: &Pubkey,
@@ -50,6 +78,9 @@ pub fn create_make_instruction(
             AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
             AccountMeta::new_readonly(spl_token::ID, false), // token_program
             AccountMeta::new_readonly(PROGRAM_ID, false), // rent sysvar (not used but placeholder)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new_readonly(derive_denylist_pda(maker).0, false), // maker's denylist entry (PDA, empty)
+            AccountMeta::new_readonly(derive_maker_state_pda(maker).0, false), // maker state (PDA)
         ],
         data: instruction_data,
     }
@@ -105,7 +136,7 @@ pub fn create_mint_account(mint_authority: &Pubkey, decimals: u8) -> Account {
 ///  - Be transferred from/to by the owner
 ///  - Track the current balance of tokens
 ///  - Be used in token operations like transfers, burns, etc.
-
+///
 ///  Key Differences from Mint Account:
 ///  - Token accounts hold tokens of a specific type and belong to a user
 ///  - Mint accounts define the token type itself and control issuance
@@ -162,6 +193,7 @@ pub fn derive_associated_token_account(owner: &Pubkey, mint: &Pubkey) -> Pubkey
 }
 
 // Helper function to create a Take instruction
+#[allow(clippy::too_many_arguments)]
 pub fn create_take_instruction(
     taker: &Pubkey,
     maker: &Pubkey,
@@ -190,11 +222,614 @@ pub fn create_take_instruction(
             AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
             AccountMeta::new_readonly(spl_token::ID, false), // token_program
             AccountMeta::new_readonly(PROGRAM_ID, false), // additional account (required by take instruction)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new_readonly(derive_denylist_pda(taker).0, false), // taker's denylist entry (PDA, empty)
+            AccountMeta::new_readonly(derive_maker_state_pda(maker).0, false), // maker state (PDA)
+        ],
+        data: instruction_data,
+    }
+}
+
+// Helper function to create a Refund instruction
+pub fn create_refund_instruction(
+    maker: &Pubkey,
+    escrow: &Pubkey,
+    mint_a: &Pubkey,
+    vault: &Pubkey,
+    maker_ata_a: &Pubkey,
+) -> Instruction {
+    let instruction_data = vec![2u8]; // Refund discriminator
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*maker, true),               // maker (signer)
+            AccountMeta::new(*escrow, false),             // escrow (PDA)
+            AccountMeta::new_readonly(*mint_a, false),    // mint_a
+            AccountMeta::new(*vault, false),              // vault (ATA)
+            AccountMeta::new(*maker_ata_a, false),        // maker_ata_a
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+            AccountMeta::new_readonly(spl_token::ID, false), // token_program
+            AccountMeta::new_readonly(PROGRAM_ID, false), // additional account (required by refund instruction)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+        ],
+        data: instruction_data,
+    }
+}
+
+// Helper function to pass the Associated Token program in place of the placeholder account.
+// The program ignores that account, but it must be present for ATA creation via CPI.
+pub fn with_associated_token_program(mut instruction: Instruction) -> Instruction {
+    if let Some(placeholder) = instruction.accounts.iter_mut().find(|meta| meta.pubkey == PROGRAM_ID) {
+        *placeholder = AccountMeta::new_readonly(ATOKEN_PROGRAM_ID, false);
+    }
+    instruction
+}
+
+/// The config PDA nobody initialized yet, which pauses nothing.
+pub fn keyed_config_account() -> (Pubkey, Account) {
+    (CONFIG_ADDRESS, Account::default())
+}
+
+/// The maker state PDA of `maker` before any CancelAll, at nonce zero.
+pub fn keyed_maker_state_account(maker: &Pubkey) -> (Pubkey, Account) {
+    (derive_maker_state_pda(maker).0, Account::default())
+}
+
+/// The empty denylist PDA of `wallet`, which proves it isn't denied.
+pub fn keyed_denylist_entry_account(wallet: &Pubkey) -> (Pubkey, Account) {
+    (derive_denylist_pda(wallet).0, Account::default())
+}
+
+/// An initialized config with `admin`, the `Config::PAUSE_*` bits in `paused`
+/// and the `Config::ENFORCE_*` bits in `enforce`.
+pub fn create_config_account(admin: &Pubkey, paused: u8, enforce: u8) -> Account {
+    let mut data = admin.to_bytes().to_vec();
+    data.extend_from_slice(&[paused, enforce, blueshift_escrow::Config::BUMP]);
+    Account::create(1_000_000, data, PROGRAM_ID, false, 0)
+}
+
+/// The escrow program's ProgramData account, as the upgradeable loader
+/// stores it, with `upgrade_authority` or no authority at all.
+pub fn create_program_data_account(upgrade_authority: Option<&Pubkey>) -> (Pubkey, Account) {
+    let address = Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &BPF_LOADER_UPGRADEABLE_ID).0;
+    // UpgradeableLoaderState::ProgramData { slot, upgrade_authority_address }
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&0u64.to_le_bytes());
+    match upgrade_authority {
+        Some(authority) => {
+            data.push(1);
+            data.extend_from_slice(authority.as_ref());
+        }
+        None => data.extend_from_slice(&[0; 33]),
+    }
+    (address, Account::create(1_000_000, data, BPF_LOADER_UPGRADEABLE_ID, false, 0))
+}
+
+pub fn create_initialize_config_instruction(authority: &Pubkey, admin: &Pubkey) -> Instruction {
+    let mut instruction_data = vec![3u8]; // InitializeConfig discriminator
+    instruction_data.extend_from_slice(admin.as_ref());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*authority, true),           // upgrade authority (signer, payer)
+            AccountMeta::new(CONFIG_ADDRESS, false),      // config (PDA)
+            AccountMeta::new_readonly(create_program_data_account(None).0, false), // program data
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
         ],
         data: instruction_data,
     }
 }
 
+pub fn create_set_pause_instruction(admin: &Pubkey, paused: u8) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),      // admin (signer)
+            AccountMeta::new(CONFIG_ADDRESS, false),      // config (PDA)
+        ],
+        data: vec![4u8, paused], // SetPause discriminator, paused instructions
+    }
+}
+
+pub fn create_set_enforcement_instruction(admin: &Pubkey, enforce: u8) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*admin, true),      // admin (signer)
+            AccountMeta::new(CONFIG_ADDRESS, false),      // config (PDA)
+        ],
+        data: vec![5u8, enforce], // SetEnforcement discriminator, enforced policies
+    }
+}
+
+// Helper function to derive a mint's policy PDA
+pub fn derive_mint_policy_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"mint_policy", mint.as_ref()], &PROGRAM_ID)
+}
+
+/// A mint policy account, `allowed` or blocked, with amount limits.
+pub fn create_mint_policy_account(mint: &Pubkey, allowed: bool, min_amount: u64, max_amount: u64) -> Account {
+    let mut data = mint.to_bytes().to_vec();
+    data.push(allowed as u8);
+    data.extend_from_slice(&min_amount.to_le_bytes());
+    data.extend_from_slice(&max_amount.to_le_bytes());
+    data.push(derive_mint_policy_pda(mint).1);
+    Account::create(1_000_000, data, PROGRAM_ID, false, 0)
+}
+
+pub fn create_set_mint_policy_instruction(admin: &Pubkey, mint: &Pubkey, allowed: bool, min_amount: u64, max_amount: u64) -> Instruction {
+    let mut instruction_data = vec![6u8, allowed as u8]; // SetMintPolicy discriminator
+    instruction_data.extend_from_slice(&min_amount.to_le_bytes());
+    instruction_data.extend_from_slice(&max_amount.to_le_bytes());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*admin, true),               // admin (signer, payer)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new_readonly(*mint, false),      // mint
+            AccountMeta::new(derive_mint_policy_pda(mint).0, false), // mint policy (PDA)
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+        ],
+        data: instruction_data,
+    }
+}
+
+// Helper function to derive a wallet's denylist PDA
+pub fn derive_denylist_pda(wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"denylist", wallet.as_ref()], &PROGRAM_ID)
+}
+
+/// The denylist entry the admin created for `wallet`.
+pub fn create_denylist_entry_account(wallet: &Pubkey) -> Account {
+    let mut data = wallet.to_bytes().to_vec();
+    data.push(derive_denylist_pda(wallet).1);
+    Account::create(1_000_000, data, PROGRAM_ID, false, 0)
+}
+
+pub fn create_add_denylist_entry_instruction(admin: &Pubkey, wallet: &Pubkey) -> Instruction {
+    let mut instruction_data = vec![7u8]; // AddDenylistEntry discriminator
+    instruction_data.extend_from_slice(wallet.as_ref());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*admin, true),               // admin (signer, payer)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new(derive_denylist_pda(wallet).0, false), // denylist entry (PDA)
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+        ],
+        data: instruction_data,
+    }
+}
+
+pub fn create_remove_denylist_entry_instruction(admin: &Pubkey, wallet: &Pubkey) -> Instruction {
+    let mut instruction_data = vec![8u8]; // RemoveDenylistEntry discriminator
+    instruction_data.extend_from_slice(wallet.as_ref());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*admin, true),               // admin (signer, receives the rent)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new(derive_denylist_pda(wallet).0, false), // denylist entry (PDA)
+        ],
+        data: instruction_data,
+    }
+}
+
+/// Passes `mint` writable, which settling needs when Token-2022 withheld
+/// transfer fees on a vault the instruction closes.
+pub fn with_writable_mint(mut instruction: Instruction, mint: &Pubkey) -> Instruction {
+    for meta in instruction.accounts.iter_mut().filter(|meta| meta.pubkey == *mint) {
+        meta.is_writable = true;
+    }
+    instruction
+}
+
+/// Appends the policy PDAs of `mint_a` and `mint_b`, which Make takes as
+/// optional last accounts while mint policies are enforced.
+pub fn with_mint_policies(mut instruction: Instruction, mint_a: &Pubkey, mint_b: &Pubkey) -> Instruction {
+    instruction.accounts.push(AccountMeta::new_readonly(derive_mint_policy_pda(mint_a).0, false));
+    instruction.accounts.push(AccountMeta::new_readonly(derive_mint_policy_pda(mint_b).0, false));
+    instruction
+}
+
+/// Appends the Memo program, which Take and Refund take as an optional last
+/// account for destinations that require memos.
+pub fn with_memo_program(mut instruction: Instruction) -> Instruction {
+    instruction.accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    instruction
+}
+
+/// Appends Make's flags byte with `ALLOW_UNSAFE_MINT_EXTENSIONS` set.
+pub fn allow_unsafe_mint_extensions(mut instruction: Instruction) -> Instruction {
+    instruction.data.push(blueshift_escrow::make::MakeInstructionData::ALLOW_UNSAFE_MINT_EXTENSIONS);
+    instruction
+}
+
+/// Appends Make's maximum referral fee, after a flags byte with none set
+/// unless `allow_unsafe_mint_extensions` already added one.
+pub fn with_max_referral_bps(mut instruction: Instruction, max_referral_bps: u16) -> Instruction {
+    if instruction.data.len() == 1 + blueshift_escrow::make::MakeInstructionData::LEN {
+        instruction.data.push(0);
+    }
+    instruction.data.extend_from_slice(&max_referral_bps.to_le_bytes());
+    instruction
+}
+
+/// Appends Make's terms hash and external reference, after a flags byte and
+/// maximum referral fee of zero unless they were already added.
+pub fn with_terms(mut instruction: Instruction, terms_hash: &[u8; 32], external_ref: &[u8; 16]) -> Instruction {
+    if instruction.data.len() < 1 + blueshift_escrow::make::MakeInstructionData::LEN + 3 {
+        instruction = with_max_referral_bps(instruction, 0);
+    }
+    instruction.data.extend_from_slice(terms_hash);
+    instruction.data.extend_from_slice(external_ref);
+    instruction
+}
+
+/// Appends Make's hashlock, recipient and timeout, after zeroed terms unless
+/// `with_terms` already added them.
+pub fn with_hashlock(mut instruction: Instruction, hashlock: &[u8; 32], recipient: &Pubkey, timeout: i64) -> Instruction {
+    if instruction.data.len() < 1 + blueshift_escrow::make::MakeInstructionData::LEN + 3 + blueshift_escrow::make::MakeInstructionData::TERMS_LEN {
+        instruction = with_terms(instruction, &[0; 32], &[0; 16]);
+    }
+    instruction.data.extend_from_slice(hashlock);
+    instruction.data.extend_from_slice(recipient.as_ref());
+    instruction.data.extend_from_slice(&timeout.to_le_bytes());
+    instruction
+}
+
+pub fn with_authorizer(mut instruction: Instruction, authorizer: &Pubkey) -> Instruction {
+    if instruction.data.len() < 1 + blueshift_escrow::make::MakeInstructionData::LEN + 3 + blueshift_escrow::make::MakeInstructionData::TERMS_LEN + blueshift_escrow::make::MakeInstructionData::HASHLOCK_LEN {
+        instruction = with_hashlock(instruction, &[0; 32], &Pubkey::default(), 0); // no hashlock
+    }
+    instruction.data.extend_from_slice(authorizer.as_ref());
+    instruction
+}
+
+/// Appends Make's arbiter and arbiter fee, after a zero authorizer unless
+/// `with_authorizer` already added one.
+pub fn with_arbiter(mut instruction: Instruction, arbiter: &Pubkey, arbiter_fee_bps: u16) -> Instruction {
+    if instruction.data.len() < 1 + blueshift_escrow::make::MakeInstructionData::LEN + 3 + blueshift_escrow::make::MakeInstructionData::TERMS_LEN + blueshift_escrow::make::MakeInstructionData::HASHLOCK_LEN + blueshift_escrow::make::MakeInstructionData::AUTHORIZER_LEN {
+        instruction = with_authorizer(instruction, &Pubkey::default()); // no authorizer
+    }
+    instruction.data.extend_from_slice(arbiter.as_ref());
+    instruction.data.extend_from_slice(&arbiter_fee_bps.to_le_bytes());
+    instruction
+}
+
+/// Passes an arbitrated escrow's payment vault to Take, after placeholders
+/// for the optional accounts before it.
+pub fn with_payment_vault(mut instruction: Instruction, vault_b: &Pubkey) -> Instruction {
+    let optional_accounts = instruction.accounts.iter().skip_while(|meta| meta.pubkey != CONFIG_ADDRESS).skip(3).count();
+    for _ in optional_accounts..4 {
+        instruction.accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+    instruction.accounts.push(AccountMeta::new(*vault_b, false));
+    instruction
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_release_instruction(
+    maker: &Pubkey,
+    taker: &Pubkey,
+    escrow: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    vault_a: &Pubkey,
+    vault_b: &Pubkey,
+    maker_ata_b: &Pubkey,
+    taker_ata_a: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*maker, true),               // maker (signer)
+            AccountMeta::new(*taker, true),               // taker (signer)
+            AccountMeta::new(*escrow, false),             // escrow (PDA)
+            AccountMeta::new_readonly(*mint_a, false),    // mint_a
+            AccountMeta::new_readonly(*mint_b, false),    // mint_b
+            AccountMeta::new(*vault_a, false),            // vault holding the deposit (ATA)
+            AccountMeta::new(*vault_b, false),            // vault holding the payment (ATA)
+            AccountMeta::new(*maker_ata_b, false),        // maker_ata_b
+            AccountMeta::new(*taker_ata_a, false),        // taker_ata_a
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+            AccountMeta::new_readonly(spl_token::ID, false), // token_program
+            AccountMeta::new_readonly(PROGRAM_ID, false), // associated token program (placeholder)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+        ],
+        data: vec![13u8], // Release discriminator
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_resolve_instruction(
+    arbiter: &Pubkey,
+    maker: &Pubkey,
+    taker: &Pubkey,
+    escrow: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    vault_a: &Pubkey,
+    vault_b: &Pubkey,
+    maker_ata_a: &Pubkey,
+    maker_ata_b: &Pubkey,
+    taker_ata_a: &Pubkey,
+    taker_ata_b: &Pubkey,
+    settle_bps: u16,
+) -> Instruction {
+    let mut instruction_data = vec![14u8]; // Resolve discriminator
+    instruction_data.extend_from_slice(&settle_bps.to_le_bytes());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*arbiter, true),             // arbiter (signer)
+            AccountMeta::new(*maker, false),              // maker (receives the rent)
+            AccountMeta::new(*taker, false),              // taker (receives the payment vault's rent)
+            AccountMeta::new(*escrow, false),             // escrow (PDA)
+            AccountMeta::new_readonly(*mint_a, false),    // mint_a
+            AccountMeta::new_readonly(*mint_b, false),    // mint_b
+            AccountMeta::new(*vault_a, false),            // vault holding the deposit (ATA)
+            AccountMeta::new(*vault_b, false),            // vault holding the payment (ATA)
+            AccountMeta::new(*maker_ata_a, false),        // maker_ata_a
+            AccountMeta::new(*maker_ata_b, false),        // maker_ata_b
+            AccountMeta::new(*taker_ata_a, false),        // taker_ata_a
+            AccountMeta::new(*taker_ata_b, false),        // taker_ata_b
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+            AccountMeta::new_readonly(spl_token::ID, false), // token_program
+            AccountMeta::new_readonly(PROGRAM_ID, false), // associated token program (placeholder)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+        ],
+        data: instruction_data,
+    }
+}
+
+/// Appends the arbiter's associated token account for mint B to Resolve, after the
+/// Memo program slot, for escrows that pay the arbiter a fee.
+pub fn with_arbiter_account(mut instruction: Instruction, arbiter_ata_b: &Pubkey) -> Instruction {
+    if instruction.accounts.last().map(|meta| meta.pubkey) != Some(MEMO_PROGRAM_ID) {
+        instruction.accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+    instruction.accounts.push(AccountMeta::new(*arbiter_ata_b, false));
+    instruction
+}
+
+/// Passes the Instructions sysvar to Take, after placeholders for the
+/// optional accounts before it.
+pub fn with_instructions_sysvar(mut instruction: Instruction) -> Instruction {
+    let optional_accounts = instruction.accounts.iter().skip_while(|meta| meta.pubkey != CONFIG_ADDRESS).skip(3).count();
+    for _ in optional_accounts..3 {
+        instruction.accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+    instruction.accounts.push(AccountMeta::new_readonly(solana_instructions_sysvar::ID, false));
+    instruction
+}
+
+/// An Ed25519 program instruction carrying `signer`'s signature of `message`.
+pub fn create_ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    let signature = signer.sign_message(message);
+    solana_ed25519_program::new_ed25519_instruction_with_signature(message, signature.as_array(), &signer.pubkey().to_bytes())
+}
+
+/// The Instructions sysvar of a transaction made of `instructions`, while
+/// the one at `current` executes.
+pub fn create_instructions_sysvar_account(instructions: &[&Instruction], current: u16) -> Account {
+    let borrowed: Vec<BorrowedInstruction> = instructions
+        .iter()
+        .map(|instruction| BorrowedInstruction {
+            program_id: &instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta { pubkey: &meta.pubkey, is_signer: meta.is_signer, is_writable: meta.is_writable })
+                .collect(),
+            data: &instruction.data,
+        })
+        .collect();
+    let mut data = solana_instructions_sysvar::construct_instructions_data(&borrowed);
+    solana_instructions_sysvar::store_current_index_checked(&mut data, current).unwrap();
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner: solana_pubkey::pubkey!("Sysvar1111111111111111111111111111111111111"),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_claim_instruction(
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    maker: &Pubkey,
+    escrow: &Pubkey,
+    mint_a: &Pubkey,
+    vault: &Pubkey,
+    recipient_ata_a: &Pubkey,
+    preimage: &[u8; 32],
+) -> Instruction {
+    let mut instruction_data = vec![10u8]; // Claim discriminator
+    instruction_data.extend_from_slice(preimage);
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),               // payer (signer)
+            AccountMeta::new_readonly(*recipient, false), // recipient
+            AccountMeta::new(*maker, false),              // maker (receives the rent)
+            AccountMeta::new(*escrow, false),             // escrow (PDA)
+            AccountMeta::new_readonly(*mint_a, false),    // mint_a
+            AccountMeta::new(*vault, false),              // vault (ATA)
+            AccountMeta::new(*recipient_ata_a, false),    // recipient_ata_a
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+            AccountMeta::new_readonly(spl_token::ID, false), // token_program
+            AccountMeta::new_readonly(PROGRAM_ID, false), // associated token program (placeholder)
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new_readonly(derive_denylist_pda(recipient).0, false), // recipient's denylist entry (PDA, empty)
+        ],
+        data: instruction_data,
+    }
+}
+
+/// Sets Take's referral fee and appends the referrer's token account, which
+/// comes after the Memo program slot. The Memo program is appended first
+/// when `with_memo_program` hasn't been.
+pub fn with_referrer(mut instruction: Instruction, referrer_ata_b: &Pubkey, referral_bps: u16) -> Instruction {
+    if instruction.accounts.last().map(|meta| meta.pubkey) != Some(MEMO_PROGRAM_ID) {
+        instruction.accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+    instruction.accounts.push(AccountMeta::new(*referrer_ata_b, false));
+    instruction.data.extend_from_slice(&referral_bps.to_le_bytes());
+    instruction
+}
+
+pub fn derive_receipt_pda(escrow: &Pubkey, taker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[blueshift_escrow::Receipt::SEED, escrow.as_ref(), taker.as_ref()], &PROGRAM_ID)
+}
+
+pub fn derive_maker_state_pda(maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[blueshift_escrow::MakerState::SEED, maker.as_ref()], &PROGRAM_ID)
+}
+
+/// The maker state PDA of `maker` after CancelAll moved it to `nonce`.
+pub fn create_maker_state_account(maker: &Pubkey, nonce: u64) -> Account {
+    let mut data = maker.to_bytes().to_vec();
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.push(derive_maker_state_pda(maker).1);
+    Account::create(1_000_000, data, PROGRAM_ID, false, 0)
+}
+
+pub fn create_cancel_all_instruction(maker: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*maker, true),               // maker (signer)
+            AccountMeta::new(derive_maker_state_pda(maker).0, false), // maker state (PDA)
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+        ],
+        data: vec![12u8], // CancelAll discriminator
+    }
+}
+
+pub fn derive_order_nonce_pda(maker: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[blueshift_escrow::OrderNonce::SEED, maker.as_ref(), &nonce.to_le_bytes()], &PROGRAM_ID)
+}
+
+pub fn create_cancel_order_instruction(maker: &Pubkey, nonce: u64) -> Instruction {
+    let mut instruction_data = vec![15u8]; // CancelOrder discriminator
+    instruction_data.extend_from_slice(&nonce.to_le_bytes());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*maker, true),               // maker (signer)
+            AccountMeta::new(derive_order_nonce_pda(maker, nonce).0, false), // order nonce (PDA)
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+            AccountMeta::new_readonly(derive_maker_state_pda(maker).0, false), // maker state (PDA)
+        ],
+        data: instruction_data,
+    }
+}
+
+pub fn create_close_order_nonce_instruction(authority: &Pubkey, maker: &Pubkey, payer: &Pubkey, nonce: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),  // maker or payer (signer)
+            AccountMeta::new_readonly(*maker, false),     // maker
+            AccountMeta::new(*payer, false),              // payer (receives the rent)
+            AccountMeta::new(derive_order_nonce_pda(maker, nonce).0, false), // order nonce (PDA)
+            AccountMeta::new_readonly(derive_maker_state_pda(maker).0, false), // maker state (PDA)
+        ],
+        data: vec![16u8], // CloseOrderNonce discriminator
+    }
+}
+
+pub const DELEGATE_ADDRESS: Pubkey = Pubkey::new_from_array(blueshift_escrow::fill_signed_order::FillSignedOrder::DELEGATE);
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_fill_signed_order_instruction(
+    taker: &Pubkey,
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    maker_ata_a: &Pubkey,
+    maker_ata_b: &Pubkey,
+    taker_ata_a: &Pubkey,
+    taker_ata_b: &Pubkey,
+    amount_a: u64,
+    amount_b: u64,
+    expiry: i64,
+    nonce: u64,
+    maker_nonce: u64,
+) -> Instruction {
+    let mut instruction_data = vec![11u8]; // FillSignedOrder discriminator
+    instruction_data.extend_from_slice(&amount_a.to_le_bytes());
+    instruction_data.extend_from_slice(&amount_b.to_le_bytes());
+    instruction_data.extend_from_slice(&expiry.to_le_bytes());
+    instruction_data.extend_from_slice(&nonce.to_le_bytes());
+    instruction_data.extend_from_slice(&maker_nonce.to_le_bytes());
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*taker, true),               // taker (signer)
+            AccountMeta::new_readonly(*maker, false),     // maker
+            AccountMeta::new_readonly(*mint_a, false),    // mint_a
+            AccountMeta::new_readonly(*mint_b, false),    // mint_b
+            AccountMeta::new(*maker_ata_a, false),        // maker_ata_a (delegated to the program)
+            AccountMeta::new(*maker_ata_b, false),        // maker_ata_b
+            AccountMeta::new(*taker_ata_a, false),        // taker_ata_a
+            AccountMeta::new(*taker_ata_b, false),        // taker_ata_b
+            AccountMeta::new(derive_order_nonce_pda(maker, nonce).0, false), // the order's nonce (PDA)
+            AccountMeta::new_readonly(DELEGATE_ADDRESS, false), // delegate (PDA)
+            AccountMeta::new_readonly(solana_instructions_sysvar::ID, false), // instructions sysvar
+            AccountMeta::new_readonly(solana_system_program::id(), false), // system_program
+            AccountMeta::new_readonly(spl_token::ID, false), // token_program
+            AccountMeta::new_readonly(PROGRAM_ID, false), // associated token program placeholder
+            AccountMeta::new_readonly(CONFIG_ADDRESS, false), // config (PDA)
+            AccountMeta::new_readonly(derive_denylist_pda(maker).0, false), // maker's denylist entry (PDA, empty)
+            AccountMeta::new_readonly(derive_denylist_pda(taker).0, false), // taker's denylist entry (PDA, empty)
+            AccountMeta::new_readonly(derive_maker_state_pda(maker).0, false), // maker state (PDA)
+        ],
+        data: instruction_data,
+    }
+}
+
+/// Sets Take's `CREATE_RECEIPT` flag and appends the receipt PDA of `escrow`
+/// and the instruction's taker, which comes after the Memo program and referrer slots. Missing slots are
+/// filled with the Memo program, which Take ignores when it needs neither.
+pub fn with_receipt(mut instruction: Instruction, escrow: &Pubkey) -> Instruction {
+    let optional_accounts = instruction.accounts.iter().skip_while(|meta| meta.pubkey != CONFIG_ADDRESS).skip(3).count();
+    for _ in optional_accounts..2 {
+        instruction.accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+    let taker = instruction.accounts[0].pubkey;
+    instruction.accounts.push(AccountMeta::new(derive_receipt_pda(escrow, &taker).0, false));
+    if instruction.data.len() == 1 {
+        instruction.data.extend_from_slice(&0u16.to_le_bytes()); // no referral fee
+    }
+    instruction.data.push(blueshift_escrow::take::TakeInstructionData::CREATE_RECEIPT);
+    instruction
+}
+
+pub fn create_close_receipt_instruction(authority: &Pubkey, taker: &Pubkey, escrow: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),  // maker or taker (signer)
+            AccountMeta::new(*taker, false),              // taker (receives the rent)
+            AccountMeta::new(derive_receipt_pda(escrow, taker).0, false), // receipt (PDA)
+        ],
+        data: vec![9u8], // CloseReceipt discriminator
+    }
+}
+
 // Helper function to create an escrow account with initialized data
 pub fn create_escrow_account(
     seed: u64,
@@ -205,7 +840,7 @@ pub fn create_escrow_account(
     bump: u8,
 ) -> Account {
     // Calculate the size needed for Escrow struct
-    const ESCROW_SIZE: usize = 8 + 32 + 32 + 32 + 8 + 1; // u64 + 3*Pubkey + u64 + [u8;1]
+    const ESCROW_SIZE: usize = 8 + 32 + 32 + 32 + 8 + 1 + 2 + 32 + 16 + 32 + 32 + 8 + 32 + 8 + 32 + 2 + 32; // u64 + 3*Pubkey + u64 + [u8;1] + u16 + [u8;32] + [u8;16] + [u8;32] + Pubkey + i64 + Pubkey + u64 + Pubkey + u16 + Pubkey
     let mut escrow_data = vec![0u8; ESCROW_SIZE];
     
     // Manually pack the escrow data in the correct order as defined in state.rs
@@ -234,5 +869,15 @@ pub fn create_escrow_account(
     // bump: [u8; 1]
     escrow_data[offset] = bump;
 
+    // max_referral_bps: u16, left at zero so Take pays no referral fee
+    // terms_hash: [u8; 32] and external_ref: [u8; 16], left at zero for no terms
+    // hashlock: [u8; 32], recipient: Pubkey and timeout: i64, left at zero for an escrow Take settles
+    // arbiter: Pubkey, arbiter_fee_bps: u16 and taker: Pubkey, left at zero for an escrow Take settles at once
+
     Account::create(10_000_000, escrow_data, PROGRAM_ID, false, 0)
-}
\ No newline at end of file
+}
+
+// Helper function to read the token balance out of a token account
+pub fn get_token_account_amount(account: &Account) -> u64 {
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
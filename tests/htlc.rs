@@ -0,0 +1,117 @@
+//! Hash-time-locked escrows: the recipient claims the deposit with the
+//! hashlock's preimage before the timeout, and the maker refunds it after.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{helpers::sha256, Config, Escrow, EscrowError, PinocchioError},
+    mollusk_svm::result::ProgramResult,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+const PREIMAGE: [u8; 32] = [7; 32];
+const NOW: i64 = 1_000;
+const TIMEOUT: i64 = 2_000;
+
+fn hashlocked_scenario() -> EscrowScenario {
+    EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .hashlock(sha256(&PREIMAGE), TIMEOUT)
+        .build()
+}
+
+#[test]
+fn test_make_records_hashlock() {
+    let mollusk = mollusk_at(NOW);
+    let mut scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let (recipient, hashlock) = (scenario.taker, sha256(&PREIMAGE));
+
+    // A zero hashlock, and one that already timed out, could never be claimed
+    let instruction = with_hashlock(scenario.make_instruction(0, 400), &[0; 32], &recipient, TIMEOUT);
+    run(&mollusk, &mut scenario, with_associated_token_program(instruction), ProgramResult::Failure(ProgramError::InvalidInstructionData));
+    let instruction = with_hashlock(scenario.make_instruction(0, 400), &hashlock, &recipient, NOW);
+    run(&mollusk, &mut scenario, with_associated_token_program(instruction), escrow_error(EscrowError::HashlockExpired));
+    // Nor could one for the zero address
+    let instruction = with_hashlock(scenario.make_instruction(0, 400), &hashlock, &Pubkey::default(), TIMEOUT);
+    run(&mollusk, &mut scenario, with_associated_token_program(instruction), ProgramResult::Failure(ProgramError::InvalidInstructionData));
+
+    let instruction = with_hashlock(scenario.make_instruction(0, 400), &hashlock, &recipient, TIMEOUT);
+    let accounts = run(&mollusk, &mut scenario, with_associated_token_program(instruction), ProgramResult::Success);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == scenario.escrow).unwrap();
+    let escrow = Escrow::load(&account.data).unwrap();
+    assert!(escrow.is_hashlocked());
+    assert_eq!((escrow.hashlock, Pubkey::new_from_array(escrow.recipient), { escrow.timeout }), (hashlock, recipient, TIMEOUT));
+}
+
+#[test]
+fn test_claim_pays_the_recipient() {
+    let mollusk = mollusk_at(NOW);
+    let mut scenario = hashlocked_scenario();
+
+    let instruction = with_associated_token_program(scenario.claim_instruction(&PREIMAGE));
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_500);
+    assert_eq!(scenario.escrow_state(&accounts), None);
+    assert_eq!(scenario.token_account(&accounts, Ata::Vault), None);
+}
+
+#[test]
+fn test_claim_rejects_bad_claims() {
+    let mut scenario = hashlocked_scenario();
+
+    let mollusk = mollusk_at(NOW);
+    let instruction = with_associated_token_program(scenario.claim_instruction(&[8; 32]));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::PreimageMismatch));
+
+    // The deposit only goes to the recipient, whoever holds the preimage
+    let mut instruction = with_associated_token_program(scenario.claim_instruction(&PREIMAGE));
+    instruction.accounts[1].pubkey = scenario.maker;
+    instruction.accounts[6].pubkey = scenario.ata(Ata::MakerA);
+    instruction.accounts[11].pubkey = derive_denylist_pda(&scenario.maker).0;
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)));
+
+    let mollusk = mollusk_at(TIMEOUT);
+    let instruction = with_associated_token_program(scenario.claim_instruction(&PREIMAGE));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::HashlockExpired));
+}
+
+#[test]
+fn test_claim_is_paused_with_take_and_checks_the_recipient() {
+    let mollusk = mollusk_at(NOW);
+    let mut scenario = hashlocked_scenario();
+    let instruction = with_associated_token_program(scenario.claim_instruction(&PREIMAGE));
+
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(&Pubkey::new_unique(), Config::PAUSE_TAKE, 0));
+    run(&mollusk, &mut scenario, instruction.clone(), escrow_error(EscrowError::InstructionPaused));
+
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(&Pubkey::new_unique(), 0, 0));
+    scenario.set_account(derive_denylist_pda(&scenario.taker).0, create_denylist_entry_account(&scenario.taker));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::WalletDenied));
+}
+
+#[test]
+fn test_escrows_settle_either_by_take_or_by_claim() {
+    let mollusk = mollusk_at(NOW);
+
+    let mut scenario = hashlocked_scenario();
+    scenario.set_account(scenario.ata(Ata::TakerB), create_token_account(&scenario.taker, &scenario.mint_b, 3_000));
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::HashlockedEscrow));
+
+    let mut scenario = EscrowScenario::builder().open(2_000, 1_500).build();
+    let instruction = with_associated_token_program(scenario.claim_instruction(&PREIMAGE));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::NotHashlocked));
+}
+
+#[test]
+fn test_refund_waits_for_the_timeout() {
+    let mut scenario = hashlocked_scenario();
+
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    run(&mollusk_at(TIMEOUT - 1), &mut scenario, instruction.clone(), escrow_error(EscrowError::HashlockNotExpired));
+
+    let accounts = run(&mollusk_at(TIMEOUT), &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 1_500);
+}
@@ -0,0 +1,306 @@
+//! Property-based tests that run random Make/Take/Refund sequences through
+//! Mollusk and check token and lamport conservation after every step.
+
+use {
+    crate::helpers::*,
+    mollusk_svm::{program::keyed_account_for_system_program, Mollusk},
+    proptest::prelude::*,
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_pack::Pack,
+    std::collections::HashMap,
+};
+
+// Number of distinct escrow seeds a sequence can touch
+const SEEDS: usize = 3;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Make { seed: usize, receive: u64, amount: u64, existing_vault: bool },
+    Take { seed: usize, existing_atas: bool },
+    Refund { seed: usize, existing_ata: bool },
+}
+
+#[derive(Clone, Debug)]
+struct Setup {
+    seeds: [u64; SEEDS],
+    decimals_a: u8,
+    decimals_b: u8,
+    maker_balance_a: u64,
+    taker_balance_b: u64,
+    ops: Vec<Op>,
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0..SEEDS, 0..2_000u64, 0..2_000u64, any::<bool>())
+            .prop_map(|(seed, receive, amount, existing_vault)| Op::Make { seed, receive, amount, existing_vault }),
+        (0..SEEDS, any::<bool>()).prop_map(|(seed, existing_atas)| Op::Take { seed, existing_atas }),
+        (0..SEEDS, any::<bool>()).prop_map(|(seed, existing_ata)| Op::Refund { seed, existing_ata }),
+    ]
+}
+
+fn setup_strategy() -> impl Strategy<Value = Setup> {
+    (
+        any::<[u64; SEEDS]>(),
+        0..=9u8,
+        0..=9u8,
+        0..5_000u64,
+        0..5_000u64,
+        prop::collection::vec(op_strategy(), 1..8),
+    )
+        .prop_map(|(seeds, decimals_a, decimals_b, maker_balance_a, taker_balance_b, ops)| Setup {
+            seeds,
+            decimals_a,
+            decimals_b,
+            maker_balance_a,
+            taker_balance_b,
+            ops,
+        })
+}
+
+// All accounts of a test run, keyed by address. Only successful instructions
+// write their resulting accounts back.
+struct World {
+    mollusk: Mollusk,
+    accounts: HashMap<Pubkey, Account>,
+    maker: Pubkey,
+    taker: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    escrows: Vec<(u64, Pubkey, u8)>,
+    // Lamports added by the test itself when it pre-creates empty ATAs
+    scaffold_lamports: u128,
+}
+
+impl World {
+    fn new(setup: &Setup) -> Self {
+        let maker = Pubkey::new_unique();
+        let taker = Pubkey::new_unique();
+        let mint_authority = Pubkey::new_unique();
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+
+        let mut accounts = HashMap::new();
+        accounts.insert(maker, Account::new(100_000_000, 0, &solana_system_program::id()));
+        accounts.insert(taker, Account::new(100_000_000, 0, &solana_system_program::id()));
+        accounts.insert(mint_a, create_mint_account(&mint_authority, setup.decimals_a));
+        accounts.insert(mint_b, create_mint_account(&mint_authority, setup.decimals_b));
+        accounts.insert(
+            derive_associated_token_account(&maker, &mint_a),
+            create_token_account(&maker, &mint_a, setup.maker_balance_a),
+        );
+        accounts.insert(
+            derive_associated_token_account(&taker, &mint_b),
+            create_token_account(&taker, &mint_b, setup.taker_balance_b),
+        );
+        let (system_program, system_program_account) = keyed_account_for_system_program();
+        accounts.insert(system_program, system_program_account);
+        accounts.insert(spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111")));
+        accounts.insert(ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111")));
+
+        // Seeds may repeat, which simply makes two ops target the same escrow
+        let escrows = setup
+            .seeds
+            .iter()
+            .map(|seed| {
+                let (escrow, bump) = derive_escrow_pda(&maker, *seed);
+                (*seed, escrow, bump)
+            })
+            .collect();
+
+        Self {
+            mollusk: setup_mollusk(),
+            accounts,
+            maker,
+            taker,
+            mint_a,
+            mint_b,
+            escrows,
+            scaffold_lamports: 0,
+        }
+    }
+
+    fn get(&self, key: &Pubkey) -> Account {
+        self.accounts.get(key).cloned().unwrap_or_default()
+    }
+
+    // Make sure an ATA exists (possibly empty) or is absent, as the op asks
+    fn prepare_ata(&mut self, owner: &Pubkey, mint: &Pubkey, existing: bool) -> Pubkey {
+        let ata = derive_associated_token_account(owner, mint);
+        let is_live = self.get(&ata).lamports > 0;
+        if existing && !is_live {
+            let account = create_token_account(owner, mint, 0);
+            self.scaffold_lamports += account.lamports as u128;
+            self.accounts.insert(ata, account);
+        }
+        ata
+    }
+
+    fn instruction_for(&mut self, op: &Op) -> Instruction {
+        match *op {
+            Op::Make { seed, receive, amount, existing_vault } => {
+                let (seed, escrow, _) = self.escrows[seed];
+                let (maker, mint_a, mint_b) = (self.maker, self.mint_a, self.mint_b);
+                let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+                let vault = self.prepare_ata(&escrow, &mint_a, existing_vault);
+                create_make_instruction(&maker, &escrow, &mint_a, &mint_b, &maker_ata_a, &vault, seed, receive, amount)
+            }
+            Op::Take { seed, existing_atas } => {
+                let (_, escrow, _) = self.escrows[seed];
+                let (maker, taker, mint_a, mint_b) = (self.maker, self.taker, self.mint_a, self.mint_b);
+                let vault = derive_associated_token_account(&escrow, &mint_a);
+                let taker_ata_a = self.prepare_ata(&taker, &mint_a, existing_atas);
+                let taker_ata_b = derive_associated_token_account(&taker, &mint_b);
+                let maker_ata_b = self.prepare_ata(&maker, &mint_b, existing_atas);
+                create_take_instruction(&taker, &maker, &escrow, &mint_a, &mint_b, &vault, &taker_ata_a, &taker_ata_b, &maker_ata_b)
+            }
+            Op::Refund { seed, existing_ata } => {
+                let (_, escrow, _) = self.escrows[seed];
+                let (maker, mint_a) = (self.maker, self.mint_a);
+                let vault = derive_associated_token_account(&escrow, &mint_a);
+                let maker_ata_a = self.prepare_ata(&maker, &mint_a, existing_ata);
+                create_refund_instruction(&maker, &escrow, &mint_a, &vault, &maker_ata_a)
+            }
+        }
+    }
+
+    // Run one op, committing its accounts only on success
+    fn apply(&mut self, op: &Op) -> bool {
+        let instruction = with_associated_token_program(self.instruction_for(op));
+        let mut keys: Vec<Pubkey> = instruction.accounts.iter().map(|meta| meta.pubkey).collect();
+        keys.push(ATOKEN_PROGRAM_ID);
+        keys.push(spl_token::ID);
+        keys.sort();
+        keys.dedup();
+        let accounts: Vec<(Pubkey, Account)> = keys.iter().map(|key| (*key, self.get(key))).collect();
+
+        let result = self.mollusk.process_instruction(&instruction, &accounts);
+        if result.program_result.is_err() {
+            return false;
+        }
+        for (key, account) in result.resulting_accounts {
+            self.accounts.insert(key, account);
+        }
+        true
+    }
+
+    fn total_lamports(&self) -> u128 {
+        self.accounts.values().map(|account| account.lamports as u128).sum()
+    }
+
+    // Live token accounts as (address, token owner, mint, amount)
+    fn token_accounts(&self) -> Vec<(Pubkey, Pubkey, Pubkey, u64)> {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| {
+                account.lamports > 0
+                    && account.owner == spl_token::ID
+                    && account.data.len() == spl_token::state::Account::LEN
+            })
+            .map(|(key, account)| {
+                let token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+                (*key, token_account.owner, token_account.mint, token_account.amount)
+            })
+            .collect()
+    }
+
+    fn total_tokens(&self, mint: &Pubkey) -> u128 {
+        self.token_accounts()
+            .iter()
+            .filter(|(_, _, account_mint, _)| account_mint == mint)
+            .map(|(_, _, _, amount)| *amount as u128)
+            .sum()
+    }
+
+    fn is_live_escrow(&self, key: &Pubkey) -> bool {
+        self.escrows.iter().any(|(_, escrow, _)| escrow == key) && self.get(key).lamports > 0
+    }
+
+    fn check_invariants(&self, supply_a: u128, supply_b: u128, lamports: u128) {
+        assert_eq!(self.total_tokens(&self.mint_a), supply_a, "mint_a supply changed");
+        assert_eq!(self.total_tokens(&self.mint_b), supply_b, "mint_b supply changed");
+        assert_eq!(
+            self.total_lamports(),
+            lamports + self.scaffold_lamports,
+            "lamports created or destroyed"
+        );
+
+        // Funds rest with the maker, the taker or an escrow that is still open
+        for (address, owner, _, amount) in self.token_accounts() {
+            let owned_by_party = owner == self.maker || owner == self.taker;
+            assert!(
+                amount == 0 || owned_by_party || self.is_live_escrow(&owner),
+                "{address} holds {amount} tokens for unexpected owner {owner}"
+            );
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_random_sequences_conserve_tokens_and_lamports(setup in setup_strategy()) {
+        let mut world = World::new(&setup);
+        let supply_a = world.total_tokens(&world.mint_a);
+        let supply_b = world.total_tokens(&world.mint_b);
+        let lamports = world.total_lamports();
+
+        for op in &setup.ops {
+            let executed = world.apply(op);
+
+            // Take and Refund close both the vault and the escrow
+            if let (true, Op::Take { seed, .. } | Op::Refund { seed, .. }) = (executed, op) {
+                let (_, escrow, _) = world.escrows[*seed];
+                let vault = derive_associated_token_account(&escrow, &world.mint_a);
+                prop_assert_eq!(world.get(&escrow).lamports, 0);
+                prop_assert_eq!(world.get(&vault).lamports, 0);
+            }
+
+            world.check_invariants(supply_a, supply_b, lamports);
+        }
+    }
+
+    #[test]
+    fn test_make_then_take_moves_exact_amounts(
+        seed in any::<u64>(),
+        decimals_a in 0..=9u8,
+        decimals_b in 0..=9u8,
+        amount in 1..1_000_000u64,
+        receive in 0..1_000_000u64,
+        extra_a in 0..1_000_000u64,
+        extra_b in 0..1_000_000u64,
+    ) {
+        let setup = Setup {
+            seeds: [seed; SEEDS],
+            decimals_a,
+            decimals_b,
+            maker_balance_a: amount + extra_a,
+            taker_balance_b: receive + extra_b,
+            ops: vec![],
+        };
+        let mut world = World::new(&setup);
+        let lamports = world.total_lamports();
+
+        let make = Op::Make { seed: 0, receive, amount, existing_vault: false };
+        let take = Op::Take { seed: 0, existing_atas: false };
+        prop_assert!(world.apply(&make), "make failed");
+        prop_assert!(world.apply(&take), "take failed");
+
+        let balance = |world: &World, owner: &Pubkey, mint: &Pubkey| {
+            get_token_account_amount(&world.get(&derive_associated_token_account(owner, mint)))
+        };
+        let (maker, taker, mint_a, mint_b) = (world.maker, world.taker, world.mint_a, world.mint_b);
+        prop_assert_eq!(balance(&world, &maker, &mint_a), extra_a);
+        prop_assert_eq!(balance(&world, &taker, &mint_a), amount);
+        prop_assert_eq!(balance(&world, &taker, &mint_b), extra_b);
+        prop_assert_eq!(balance(&world, &maker, &mint_b), receive);
+
+        // Every lamport is still accounted for, and the escrow is gone
+        prop_assert_eq!(world.total_lamports(), lamports);
+        let (_, escrow, _) = world.escrows[0];
+        prop_assert_eq!(world.get(&escrow).lamports, 0);
+    }
+}
@@ -5,4 +5,42 @@ mod make;
 #[cfg(test)]
 mod take;
 #[cfg(test)]
-mod helpers;
\ No newline at end of file
+mod refund;
+#[cfg(test)]
+mod invariants;
+#[cfg(test)]
+mod substitution;
+#[cfg(test)]
+mod transactions;
+#[cfg(test)]
+mod helpers;
+#[cfg(test)]
+mod scenario;
+#[cfg(test)]
+mod token_2022;
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+mod errors;
+#[cfg(test)]
+mod config;
+#[cfg(test)]
+mod mint_policy;
+#[cfg(test)]
+mod denylist;
+#[cfg(test)]
+mod referral;
+#[cfg(test)]
+mod receipt;
+#[cfg(test)]
+mod terms;
+#[cfg(test)]
+mod htlc;
+#[cfg(test)]
+mod authorization;
+#[cfg(test)]
+mod signed_order;
+#[cfg(test)]
+mod cancel_all;
+#[cfg(test)]
+mod arbiter;
\ No newline at end of file
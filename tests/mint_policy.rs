@@ -0,0 +1,169 @@
+//! Per-mint policies: the admin allows or blocks mints and bounds their
+//! amounts, and Make enforces them while `Config::ENFORCE_MINT_POLICY` is set.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario, Side}},
+    blueshift_escrow::{Config, EscrowError, MintPolicy},
+    mollusk_svm::{result::ProgramResult, Mollusk},
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+const RECEIVE: u64 = 750;
+const DEPOSIT: u64 = 400;
+
+fn enforcing_scenario(admin: &Pubkey, enforce: u8) -> EscrowScenario {
+    let mut scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    scenario.set_account(CONFIG_ADDRESS, create_config_account(admin, 0, enforce));
+    scenario
+}
+
+fn set_policy(scenario: &mut EscrowScenario, side: Side, allowed: bool, min_amount: u64, max_amount: u64) {
+    let mint = match side {
+        Side::A => scenario.mint_a,
+        Side::B => scenario.mint_b,
+    };
+    scenario.set_account(derive_mint_policy_pda(&mint).0, create_mint_policy_account(&mint, allowed, min_amount, max_amount));
+}
+
+fn make(mollusk: &Mollusk, scenario: &mut EscrowScenario, expected: ProgramResult) {
+    let instruction = with_associated_token_program(scenario.make_instruction(RECEIVE, DEPOSIT));
+    let instruction = with_mint_policies(instruction, &scenario.mint_a, &scenario.mint_b);
+    run(mollusk, scenario, instruction, expected);
+}
+
+#[test]
+fn test_set_mint_policy_creates_then_updates() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = enforcing_scenario(&admin, 0);
+    scenario.set_account(admin, Account::new(10_000_000, 0, &solana_system_program::id()));
+    let (mint, policy) = (scenario.mint_a, derive_mint_policy_pda(&scenario.mint_a).0);
+    let policy_state = |accounts: &[(Pubkey, Account)]| {
+        let (_, account) = accounts.iter().find(|(key, _)| *key == policy).unwrap();
+        let policy = MintPolicy::load(&account.data).unwrap();
+        (Pubkey::new_from_array(policy.mint), policy.is_allowed(), policy.min_amount, policy.max_amount)
+    };
+
+    let instruction = create_set_mint_policy_instruction(&admin, &mint, true, 10, 1_000);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(policy_state(&accounts), (mint, true, 10, 1_000));
+
+    let instruction = create_set_mint_policy_instruction(&admin, &mint, false, 0, 0);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(policy_state(&accounts), (mint, false, 0, 0));
+}
+
+#[test]
+fn test_set_mint_policy_rejects_other_signers_and_bad_limits() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = enforcing_scenario(&admin, 0);
+    scenario.set_account(admin, Account::new(10_000_000, 0, &solana_system_program::id()));
+    let mint = scenario.mint_a;
+
+    let instruction = create_set_mint_policy_instruction(&scenario.maker, &mint, false, 0, 0);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+
+    // The minimum is above the maximum
+    let instruction = create_set_mint_policy_instruction(&admin, &mint, true, 100, 10);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::InvalidInstructionData));
+}
+
+#[test]
+fn test_set_enforcement_requires_enforcing_to_require_policies() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+    let mut scenario = enforcing_scenario(&admin, 0);
+
+    let instruction = create_set_enforcement_instruction(&admin, Config::REQUIRE_MINT_POLICY);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::InvalidInstructionData));
+
+    let instruction = create_set_enforcement_instruction(&admin, Config::ENFORCE_ALL);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    let (_, config) = accounts.iter().find(|(key, _)| *key == CONFIG_ADDRESS).unwrap();
+    assert_eq!(Config::load(&config.data).unwrap().enforce, Config::ENFORCE_ALL);
+}
+
+#[test]
+fn test_make_ignores_policies_unless_enforced() {
+    let mollusk = setup_mollusk();
+    let mut scenario = enforcing_scenario(&Pubkey::new_unique(), 0);
+    set_policy(&mut scenario, Side::A, false, 0, 0);
+
+    make(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_make_rejects_blocked_mints() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_MINT_POLICY);
+    set_policy(&mut scenario, Side::A, false, 0, 0);
+    make(&mollusk, &mut scenario, escrow_error(EscrowError::MintNotAllowed));
+
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_MINT_POLICY);
+    set_policy(&mut scenario, Side::B, false, 0, 0);
+    make(&mollusk, &mut scenario, escrow_error(EscrowError::MintNotAllowed));
+}
+
+#[test]
+fn test_make_enforces_amount_limits() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+
+    // The deposit is checked against mint A's limits
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_MINT_POLICY);
+    set_policy(&mut scenario, Side::A, true, DEPOSIT + 1, 0);
+    make(&mollusk, &mut scenario, escrow_error(EscrowError::AmountOutOfRange));
+
+    // The requested amount is checked against mint B's limits
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_MINT_POLICY);
+    set_policy(&mut scenario, Side::B, true, 0, RECEIVE - 1);
+    make(&mollusk, &mut scenario, escrow_error(EscrowError::AmountOutOfRange));
+
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_MINT_POLICY);
+    set_policy(&mut scenario, Side::A, true, DEPOSIT, DEPOSIT);
+    set_policy(&mut scenario, Side::B, true, RECEIVE, RECEIVE);
+    make(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_make_with_unlisted_mints() {
+    let mollusk = setup_mollusk();
+    let admin = Pubkey::new_unique();
+
+    // Unlisted mints pass a denylist
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_MINT_POLICY);
+    make(&mollusk, &mut scenario, ProgramResult::Success);
+
+    // but not an allowlist
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_ALL);
+    set_policy(&mut scenario, Side::A, true, 0, 0);
+    make(&mollusk, &mut scenario, escrow_error(EscrowError::MintNotAllowed));
+
+    let mut scenario = enforcing_scenario(&admin, Config::ENFORCE_ALL);
+    set_policy(&mut scenario, Side::A, true, 0, 0);
+    set_policy(&mut scenario, Side::B, true, 0, 0);
+    make(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_make_requires_the_policy_accounts_while_enforced() {
+    let mollusk = setup_mollusk();
+    let mut scenario = enforcing_scenario(&Pubkey::new_unique(), Config::ENFORCE_MINT_POLICY);
+    set_policy(&mut scenario, Side::A, false, 0, 0);
+
+    // Leaving out a blocked mint's policy must not get around it
+    let instruction = with_associated_token_program(scenario.make_instruction(RECEIVE, DEPOSIT));
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::MintPolicyMissing));
+
+    // Nor passing another mint's policy in its place
+    let instruction = with_associated_token_program(scenario.make_instruction(RECEIVE, DEPOSIT));
+    let instruction = with_mint_policies(instruction, &scenario.mint_b, &scenario.mint_b);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::MintPolicyMismatch));
+}
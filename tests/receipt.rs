@@ -0,0 +1,135 @@
+//! Trade receipts: Take records the trade in a PDA the taker pays for, and
+//! either party can close it later to return the rent to the taker.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{EscrowError, PinocchioError, Receipt},
+    mollusk_svm::{result::ProgramResult, Mollusk},
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+const SLOT: u64 = 1_234;
+
+fn taken_scenario(mollusk: &Mollusk) -> EscrowScenario {
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    let instruction = with_receipt(with_associated_token_program(scenario.take_instruction()), &scenario.escrow);
+    run(mollusk, &mut scenario, instruction, ProgramResult::Success);
+    scenario
+}
+
+fn lamports(accounts: &[(Pubkey, Account)], key: &Pubkey) -> u64 {
+    accounts.iter().find(|(k, _)| k == key).map(|(_, account)| account.lamports).unwrap()
+}
+
+#[test]
+fn test_take_records_receipt() {
+    let mut mollusk = setup_mollusk();
+    mollusk.warp_to_slot(SLOT);
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .max_referral_bps(500)
+        .build();
+    let referrer_ata = Pubkey::new_unique();
+    scenario.set_account(referrer_ata, create_token_account(&Pubkey::new_unique(), &scenario.mint_b, 0));
+
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &referrer_ata, 250);
+    let instruction = with_receipt(instruction, &scenario.escrow);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+
+    let (receipt_key, bump) = derive_receipt_pda(&scenario.escrow, &scenario.taker);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == receipt_key).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    let receipt = Receipt::load(&account.data).unwrap();
+    assert_eq!({ receipt.seed }, scenario.seed);
+    assert_eq!(Pubkey::new_from_array(receipt.maker), scenario.maker);
+    assert_eq!(Pubkey::new_from_array(receipt.taker), scenario.taker);
+    assert_eq!(Pubkey::new_from_array(receipt.mint_a), scenario.mint_a);
+    assert_eq!(Pubkey::new_from_array(receipt.mint_b), scenario.mint_b);
+    assert_eq!(({ receipt.amount_a }, { receipt.amount_b }, { receipt.referral_fee }), (1_500, 2_000, 50));
+    assert_eq!(({ receipt.slot }, receipt.bump[0]), (SLOT, bump));
+
+    // The escrow itself is still closed
+    assert_eq!(scenario.escrow_state(&accounts), None);
+}
+
+#[test]
+fn test_take_requires_the_escrows_receipt_pda() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+
+    let mut instruction = with_receipt(with_associated_token_program(scenario.take_instruction()), &scenario.escrow);
+    instruction.accounts.last_mut().unwrap().pubkey = derive_receipt_pda(&Pubkey::new_unique(), &scenario.taker).0;
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ReceiptMismatch));
+
+    let mut instruction = with_receipt(with_associated_token_program(scenario.take_instruction()), &scenario.escrow);
+    instruction.accounts.pop();
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ReceiptMissing));
+}
+
+#[test]
+fn test_take_records_receipt_for_a_remade_escrow() {
+    let mollusk = setup_mollusk();
+    let mut scenario = taken_scenario(&mollusk);
+    let (maker, escrow, mint_a, mint_b) = (scenario.maker, scenario.escrow, scenario.mint_a, scenario.mint_b);
+
+    // The maker reopens the escrow at the same address while the first
+    // taker's receipt is still open
+    scenario.set_account(scenario.ata(Ata::MakerA), create_token_account(&maker, &mint_a, 1_500));
+    let instruction = with_associated_token_program(scenario.make_instruction(2_000, 1_500));
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+
+    let taker = Pubkey::new_unique();
+    let (taker_ata_a, taker_ata_b) = (derive_associated_token_account(&taker, &mint_a), derive_associated_token_account(&taker, &mint_b));
+    scenario.set_account(taker, Account::new(10_000_000, 0, &solana_system_program::id()));
+    scenario.set_account(taker_ata_b, create_token_account(&taker, &mint_b, 2_000));
+    let instruction = create_take_instruction(&taker, &maker, &escrow, &mint_a, &mint_b, &scenario.ata(Ata::Vault), &taker_ata_a, &taker_ata_b, &scenario.ata(Ata::MakerB));
+    let instruction = with_receipt(with_associated_token_program(instruction), &escrow);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+
+    // Each taker holds a receipt of their own trade
+    let (_, account) = accounts.iter().find(|(key, _)| *key == derive_receipt_pda(&escrow, &taker).0).unwrap();
+    assert_eq!(Pubkey::new_from_array(Receipt::load(&account.data).unwrap().taker), taker);
+    let first = &scenario.accounts[&derive_receipt_pda(&escrow, &scenario.taker).0];
+    assert_eq!(Pubkey::new_from_array(Receipt::load(&first.data).unwrap().taker), scenario.taker);
+}
+
+#[test]
+fn test_close_receipt_by_either_party_refunds_the_taker() {
+    let mollusk = setup_mollusk();
+    let receipt_key = |scenario: &EscrowScenario| derive_receipt_pda(&scenario.escrow, &scenario.taker).0;
+
+    for closer in [|scenario: &EscrowScenario| scenario.maker, |scenario: &EscrowScenario| scenario.taker] {
+        let mut scenario = taken_scenario(&mollusk);
+        let (taker, escrow) = (scenario.taker, scenario.escrow);
+        let before = scenario.accounts_for(&create_close_receipt_instruction(&taker, &taker, &escrow));
+        let rent = lamports(&before, &receipt_key(&scenario));
+
+        let instruction = create_close_receipt_instruction(&closer(&scenario), &taker, &escrow);
+        let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+        assert_eq!(lamports(&accounts, &receipt_key(&scenario)), 0);
+        assert_eq!(lamports(&accounts, &taker), lamports(&before, &taker) + rent);
+    }
+}
+
+#[test]
+fn test_close_receipt_rejects_strangers_and_other_destinations() {
+    let mollusk = setup_mollusk();
+    let mut scenario = taken_scenario(&mollusk);
+    let (maker, taker, escrow) = (scenario.maker, scenario.taker, scenario.escrow);
+
+    let instruction = create_close_receipt_instruction(&Pubkey::new_unique(), &taker, &escrow);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+
+    // The maker can't take the taker's rent
+    let instruction = create_close_receipt_instruction(&maker, &maker, &escrow);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)));
+}
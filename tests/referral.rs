@@ -0,0 +1,110 @@
+//! Referral fees: the maker caps them on Make, and Take pays the referrer its
+//! share of the token B payment out of what the maker receives.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{Escrow, EscrowError},
+    mollusk_svm::result::ProgramResult,
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+const RECEIVE: u64 = 2_000;
+const MAX_REFERRAL_BPS: u16 = 500;
+
+// An open escrow allowing referral fees up to `MAX_REFERRAL_BPS`, and a
+// referrer token account for `mint`
+fn referral_scenario(mint: fn(&EscrowScenario) -> Pubkey) -> (EscrowScenario, Pubkey) {
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .max_referral_bps(MAX_REFERRAL_BPS)
+        .build();
+    let referrer_ata = Pubkey::new_unique();
+    scenario.set_account(referrer_ata, create_token_account(&Pubkey::new_unique(), &mint(&scenario), 0));
+    (scenario, referrer_ata)
+}
+
+fn token_balance(accounts: &[(Pubkey, Account)], key: &Pubkey) -> u64 {
+    let (_, account) = accounts.iter().find(|(k, _)| k == key).unwrap();
+    get_token_account_amount(account)
+}
+
+#[test]
+fn test_make_records_max_referral_bps() {
+    let mollusk = setup_mollusk();
+    let mut scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+
+    let instruction = with_max_referral_bps(scenario.make_instruction(750, 400), Escrow::MAX_BPS + 1);
+    run(&mollusk, &mut scenario, with_associated_token_program(instruction), ProgramResult::Failure(ProgramError::InvalidInstructionData));
+
+    let instruction = with_max_referral_bps(scenario.make_instruction(750, 400), MAX_REFERRAL_BPS);
+    let accounts = run(&mollusk, &mut scenario, with_associated_token_program(instruction), ProgramResult::Success);
+    assert_eq!(scenario.escrow_state(&accounts).unwrap().max_referral_bps, MAX_REFERRAL_BPS);
+}
+
+#[test]
+fn test_take_splits_payment_with_referrer() {
+    let mollusk = setup_mollusk();
+    let (mut scenario, referrer_ata) = referral_scenario(|scenario| scenario.mint_b);
+
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &referrer_ata, 250);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+
+    // 2.5% of the payment goes to the referrer, and the taker pays no more
+    assert_eq!(token_balance(&accounts, &referrer_ata), 50);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), RECEIVE - 50);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerB), 3_000 - RECEIVE);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_500);
+}
+
+#[test]
+fn test_take_rejects_fees_above_the_makers_maximum() {
+    let mollusk = setup_mollusk();
+    let (mut scenario, referrer_ata) = referral_scenario(|scenario| scenario.mint_b);
+
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &referrer_ata, MAX_REFERRAL_BPS + 1);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ReferralFeeTooHigh));
+
+    // Escrows made without a maximum take no referral fee at all
+    let mut scenario = EscrowScenario::builder()
+        .open(RECEIVE, 1_500)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    scenario.set_account(referrer_ata, create_token_account(&Pubkey::new_unique(), &scenario.mint_b, 0));
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &referrer_ata, 1);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ReferralFeeTooHigh));
+}
+
+#[test]
+fn test_take_requires_a_referrer_token_account_for_mint_b() {
+    let mollusk = setup_mollusk();
+    let (mut scenario, _) = referral_scenario(|scenario| scenario.mint_b);
+
+    let mut instruction = with_memo_program(with_associated_token_program(scenario.take_instruction()));
+    instruction.data.extend_from_slice(&250u16.to_le_bytes());
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::ReferrerMissing));
+
+    let (mut scenario, referrer_ata) = referral_scenario(|scenario| scenario.mint_a);
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &referrer_ata, 250);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::MintMismatch));
+}
+
+#[test]
+fn test_take_rejects_the_taker_as_referrer() {
+    let mollusk = setup_mollusk();
+    let (mut scenario, _) = referral_scenario(|scenario| scenario.mint_b);
+
+    // Neither the taker's paying account nor any other they own
+    let taker_ata_b = scenario.ata(Ata::TakerB);
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &taker_ata_b, 250);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::SelfReferral));
+
+    let other_account = Pubkey::new_unique();
+    scenario.set_account(other_account, create_token_account(&scenario.taker, &scenario.mint_b, 0));
+    let instruction = with_referrer(with_associated_token_program(scenario.take_instruction()), &other_account, 250);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::SelfReferral));
+}
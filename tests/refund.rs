@@ -0,0 +1,330 @@
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}}, blueshift_escrow::{Escrow, EscrowError, PinocchioError}, mollusk_svm::{
+        program::keyed_account_for_system_program,
+        result::{Check, ProgramResult}
+    }, solana_account::Account, solana_pubkey::Pubkey, spl_token::solana_program::program_error::ProgramError
+};
+
+#[test]
+fn test_refund_instruction_success() {
+    let mollusk = setup_mollusk();
+
+    // Test parameters
+    let seed = 24680u64;
+    let receive_amount = 2000u64;
+    let deposit_amount = 1500u64; // Amount maker deposited (vault balance)
+
+    // Generate test keypairs
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    // Derive PDAs and ATAs
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+
+    let instruction = create_refund_instruction(&maker, &escrow, &mint_a, &vault, &maker_ata_a);
+
+    // Setup accounts
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, receive_amount, bump)), // 10_000_000 lamports
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint_a, deposit_amount)), // 2_000_000 lamports
+        (maker_ata_a, create_token_account(&maker, &mint_a, 0)), // Will receive the deposit back
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            // Maker receives the rent of both the vault and the escrow
+            Check::account(&maker)
+                .lamports(10_000_000 + 2_000_000 + 10_000_000)
+                .build(),
+            // Verify vault is closed
+            Check::account(&vault)
+                .lamports(0)
+                .build(),
+            // Verify escrow is closed
+            Check::account(&escrow)
+                .lamports(0)
+                .build(),
+        ],
+    );
+
+    // Verify the deposit was returned to the maker
+    let maker_ata_a_account = result.get_account(&maker_ata_a).unwrap();
+    assert_eq!(get_token_account_amount(maker_ata_a_account), deposit_amount);
+}
+
+#[test]
+fn test_refund_instruction_creates_maker_ata() {
+    let mollusk = setup_mollusk();
+
+    let seed = 13579u64;
+    let receive_amount = 2000u64;
+    let deposit_amount = 1500u64;
+
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+
+    let instruction = with_associated_token_program(create_refund_instruction(
+        &maker, &escrow, &mint_a, &vault, &maker_ata_a,
+    ));
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, receive_amount, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint_a, deposit_amount)),
+        (maker_ata_a, Account::new(0, 0, &solana_system_program::id())), // Does not exist yet
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+        (ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&maker_ata_a)
+                .owner(&spl_token::ID)
+                .build(),
+            Check::account(&vault)
+                .lamports(0)
+                .build(),
+            Check::account(&escrow)
+                .lamports(0)
+                .build(),
+        ],
+    );
+
+    let maker_ata_a_account = result.get_account(&maker_ata_a).unwrap();
+    assert_eq!(get_token_account_amount(maker_ata_a_account), deposit_amount);
+}
+
+// ============================================================================
+// ERROR HANDLING TESTS
+// ============================================================================
+
+#[test]
+fn test_refund_instruction_maker_not_signer() {
+    let mollusk = setup_mollusk();
+
+    let seed = 11111u64;
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+
+    // Maker is passed without a signature
+    let mut instruction = create_refund_instruction(&maker, &escrow, &mint_a, &vault, &maker_ata_a);
+    instruction.accounts[0].is_signer = false;
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, 2000, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint_a, 1500)),
+        (maker_ata_a, create_token_account(&maker, &mint_a, 0)),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(PinocchioError::NotSigner as u32)))],
+    );
+}
+
+#[test]
+fn test_refund_instruction_wrong_maker() {
+    let mollusk = setup_mollusk();
+
+    let seed = 22222u64;
+    let maker = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique(); // Signs in place of the real maker
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let attacker_ata_a = derive_associated_token_account(&attacker, &mint_a);
+
+    let instruction = create_refund_instruction(&attacker, &escrow, &mint_a, &vault, &attacker_ata_a);
+
+    let accounts = vec![
+        (attacker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, 2000, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint_a, 1500)),
+        (attacker_ata_a, create_token_account(&attacker, &mint_a, 0)),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    // The escrow records the real maker, so the attacker fails the maker check
+    // before the escrow address is ever derived from their key
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(EscrowError::MakerMismatch as u32)))],
+    );
+}
+
+#[test]
+fn test_refund_instruction_wrong_vault() {
+    let mollusk = setup_mollusk();
+
+    let seed = 33333u64;
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let (other_escrow, _) = derive_escrow_pda(&maker, seed + 1);
+    let wrong_vault = derive_associated_token_account(&other_escrow, &mint_a); // Belongs to another escrow
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+
+    let instruction = create_refund_instruction(&maker, &escrow, &mint_a, &wrong_vault, &maker_ata_a);
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, 2000, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (wrong_vault, create_token_account(&other_escrow, &mint_a, 1500)),
+        (maker_ata_a, create_token_account(&maker, &mint_a, 0)),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)))],
+    );
+}
+
+#[test]
+fn test_refund_instruction_escrow_already_closed() {
+    let mollusk = setup_mollusk();
+
+    let seed = 44444u64;
+    let maker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+
+    let (escrow, _bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let maker_ata_a = derive_associated_token_account(&maker, &mint_a);
+
+    let instruction = create_refund_instruction(&maker, &escrow, &mint_a, &vault, &maker_ata_a);
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, Account::default()), // Closed: no lamports, no data, system owned
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, Account::default()),
+        (maker_ata_a, create_token_account(&maker, &mint_a, 1500)),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidOwner as u32)))],
+    );
+}
+
+#[test]
+fn test_refund_instruction_foreign_maker_ata() {
+    let mollusk = setup_mollusk();
+
+    let seed = 55555u64;
+    let maker = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint_a);
+    let foreign_ata_a = derive_associated_token_account(&attacker, &mint_a); // Not the maker's ATA
+
+    let instruction = create_refund_instruction(&maker, &escrow, &mint_a, &vault, &foreign_ata_a);
+
+    let accounts = vec![
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, 2000, bump)),
+        (mint_a, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint_a, 1500)),
+        (foreign_ata_a, create_token_account(&attacker, &mint_a, 0)),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)))],
+    );
+}
+
+#[test]
+fn test_refund_instruction_escrows_of_earlier_layouts() {
+    let mollusk = setup_mollusk();
+
+    // Every layout escrows were made with still refunds, with the fields it
+    // lacks read as none
+    for len in Escrow::LAYOUT_LENS {
+        let mut scenario = EscrowScenario::builder().open(2000, 1500).build();
+        let mut escrow_account = scenario.accounts[&scenario.escrow].clone();
+        escrow_account.data.truncate(len);
+        scenario.set_account(scenario.escrow, escrow_account);
+
+        let instruction = with_associated_token_program(scenario.refund_instruction());
+        let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+        assert_eq!(scenario.balance(&result.resulting_accounts, Ata::MakerA), 1500);
+        assert_eq!(scenario.escrow_state(&result.resulting_accounts), None);
+    }
+
+    // Any other length isn't an escrow
+    let mut scenario = EscrowScenario::builder().open(2000, 1500).build();
+    let mut escrow_account = scenario.accounts[&scenario.escrow].clone();
+    escrow_account.data.truncate(Escrow::LAYOUT_LENS[0] + 1);
+    scenario.set_account(scenario.escrow, escrow_account);
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidAccountData))],
+    );
+}
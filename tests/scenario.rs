@@ -0,0 +1,672 @@
+//! `EscrowScenario`, a fluent builder for the accounts of an escrow test.
+//!
+//! The builder creates the maker, the taker, both mints and every Associated
+//! Token Account with the requested balances, under either token program. The
+//! built scenario hands out instructions and the matching account list, and
+//! decodes token balances and `Escrow` state from whatever accounts a test
+//! ends up with. Token-2022 scenarios can give either mint extensions, and
+//! require memos or lock CPIs on any ATA.
+//!
+//! ```ignore
+//! let scenario = EscrowScenario::builder()
+//!     .open(2_000, 1_500)
+//!     .balance(Ata::TakerB, 3_000)
+//!     .frozen(Ata::MakerB)
+//!     .build();
+//! let instruction = scenario.take_instruction();
+//! let result = mollusk.process_instruction(&instruction, &scenario.accounts_for(&instruction));
+//! assert_eq!(scenario.balance(&result.resulting_accounts, Ata::TakerB), 1_000);
+//! ```
+
+use {
+    crate::helpers::*,
+    blueshift_escrow::Escrow,
+    mollusk_svm::program::keyed_account_for_system_program,
+    solana_account::{Account, WritableAccount},
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_pack::Pack,
+    solana_rent::Rent,
+    spl_token_2022::{
+        extension::{
+            cpi_guard::CpiGuard, default_account_state::DefaultAccountState, memo_transfer::MemoTransfer,
+            mint_close_authority::MintCloseAuthority, non_transferable::NonTransferable,
+            permanent_delegate::PermanentDelegate,
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+        },
+    },
+    spl_pod::optional_keys::OptionalNonZeroPubkey,
+    std::collections::{HashMap, HashSet},
+};
+
+/// The Associated Token Accounts of a scenario.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ata {
+    MakerA,
+    MakerB,
+    TakerA,
+    TakerB,
+    Vault,
+    /// The escrow's token account for mint B, holding an arbitrated escrow's
+    /// payment
+    PaymentVault,
+}
+
+impl Ata {
+    const ALL: [Ata; 6] = [Ata::MakerA, Ata::MakerB, Ata::TakerA, Ata::TakerB, Ata::Vault, Ata::PaymentVault];
+}
+
+/// The two mints of a scenario.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// Token-2022 mint extensions a scenario can put on `mint_a` or `mint_b`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MintExtension {
+    /// Withholds `basis_points` of every transfer, up to `maximum_fee`.
+    TransferFee { basis_points: u16, maximum_fee: u64 },
+    NonTransferable,
+    /// New token accounts start frozen. Accounts the builder creates are
+    /// left initialized, as if the freeze authority had thawed them.
+    DefaultAccountStateFrozen,
+    PermanentDelegate(Pubkey),
+    MintCloseAuthority(Pubkey),
+}
+
+impl MintExtension {
+    fn extension_type(&self) -> ExtensionType {
+        match self {
+            MintExtension::TransferFee { .. } => ExtensionType::TransferFeeConfig,
+            MintExtension::NonTransferable => ExtensionType::NonTransferable,
+            MintExtension::DefaultAccountStateFrozen => ExtensionType::DefaultAccountState,
+            MintExtension::PermanentDelegate(_) => ExtensionType::PermanentDelegate,
+            MintExtension::MintCloseAuthority(_) => ExtensionType::MintCloseAuthority,
+        }
+    }
+}
+
+/// Decoded copy of the on-chain `Escrow` account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscrowState {
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub receive: u64,
+    pub bump: u8,
+    pub max_referral_bps: u16,
+    pub terms_hash: [u8; 32],
+    pub external_ref: [u8; 16],
+    pub maker_nonce: u64,
+    pub arbiter: Pubkey,
+    pub arbiter_fee_bps: u16,
+    pub taker: Pubkey,
+}
+
+pub struct EscrowScenarioBuilder {
+    seed: u64,
+    decimals_a: u8,
+    decimals_b: u8,
+    token_program: Pubkey,
+    wallet_lamports: u64,
+    balances: HashMap<Ata, Option<u64>>,
+    frozen: HashSet<Ata>,
+    memo_required: HashSet<Ata>,
+    cpi_guarded: HashSet<Ata>,
+    extensions: HashMap<Side, Vec<MintExtension>>,
+    escrow: Option<u64>,
+    max_referral_bps: u16,
+    hashlock: Option<([u8; 32], i64)>,
+    authorizer: Option<Pubkey>,
+    arbiter: Option<(Pubkey, u16)>,
+    maker: Option<Pubkey>,
+}
+
+impl Default for EscrowScenarioBuilder {
+    fn default() -> Self {
+        // Every party ATA exists and is empty, the vaults only exist once
+        // the escrow is opened or taken
+        let mut balances: HashMap<Ata, Option<u64>> = Ata::ALL.into_iter().map(|ata| (ata, Some(0))).collect();
+        balances.insert(Ata::Vault, None);
+        balances.insert(Ata::PaymentVault, None);
+
+        Self {
+            seed: 1,
+            decimals_a: 9,
+            decimals_b: 6,
+            token_program: spl_token::ID,
+            wallet_lamports: 10_000_000,
+            balances,
+            frozen: HashSet::new(),
+            memo_required: HashSet::new(),
+            cpi_guarded: HashSet::new(),
+            extensions: HashMap::new(),
+            escrow: None,
+            max_referral_bps: 0,
+            hashlock: None,
+            authorizer: None,
+            arbiter: None,
+            maker: None,
+        }
+    }
+}
+
+impl EscrowScenarioBuilder {
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Uses `maker` instead of a fresh address, for a maker that signs.
+    pub fn maker(mut self, maker: Pubkey) -> Self {
+        self.maker = Some(maker);
+        self
+    }
+
+    pub fn decimals(mut self, decimals_a: u8, decimals_b: u8) -> Self {
+        self.decimals_a = decimals_a;
+        self.decimals_b = decimals_b;
+        self
+    }
+
+    /// Owns the mints and token accounts by Token-2022 instead of Token.
+    pub fn token_2022(self) -> Self {
+        self.token_program(spl_token_2022::ID)
+    }
+
+    pub fn token_program(mut self, token_program: Pubkey) -> Self {
+        self.token_program = token_program;
+        self
+    }
+
+    /// Adds a Token-2022 extension to one of the mints. Token accounts of that
+    /// mint get the account extensions it requires.
+    pub fn mint_extension(mut self, side: Side, extension: MintExtension) -> Self {
+        self.token_program = spl_token_2022::ID;
+        self.extensions.entry(side).or_default().push(extension);
+        self
+    }
+
+    /// Gives the ATA the Token-2022 MemoTransfer extension, requiring a memo
+    /// on every incoming transfer.
+    pub fn memo_required(mut self, ata: Ata) -> Self {
+        self.token_program = spl_token_2022::ID;
+        self.memo_required.insert(ata);
+        self
+    }
+
+    /// Gives the ATA the Token-2022 CpiGuard extension, locked, so its owner
+    /// can't sign transfers out of it through a CPI.
+    pub fn cpi_guard(mut self, ata: Ata) -> Self {
+        self.token_program = spl_token_2022::ID;
+        self.cpi_guarded.insert(ata);
+        self
+    }
+
+    /// Lamports held by both the maker and the taker.
+    pub fn wallet_lamports(mut self, lamports: u64) -> Self {
+        self.wallet_lamports = lamports;
+        self
+    }
+
+    /// Creates the ATA holding `amount` tokens.
+    pub fn balance(mut self, ata: Ata, amount: u64) -> Self {
+        self.balances.insert(ata, Some(amount));
+        self
+    }
+
+    /// Leaves the ATA uncreated.
+    pub fn missing(mut self, ata: Ata) -> Self {
+        self.balances.insert(ata, None);
+        self
+    }
+
+    pub fn frozen(mut self, ata: Ata) -> Self {
+        self.frozen.insert(ata);
+        self
+    }
+
+    /// Creates the escrow account asking for `receive`, with `deposit` tokens
+    /// in the vault.
+    pub fn open(mut self, receive: u64, deposit: u64) -> Self {
+        self.escrow = Some(receive);
+        self.balances.insert(Ata::Vault, Some(deposit));
+        self
+    }
+
+    /// The largest referral fee the opened escrow lets Take pay.
+    pub fn max_referral_bps(mut self, max_referral_bps: u16) -> Self {
+        self.max_referral_bps = max_referral_bps;
+        self
+    }
+
+    /// Hashlocks the opened escrow for the taker until `timeout`.
+    pub fn hashlock(mut self, hashlock: [u8; 32], timeout: i64) -> Self {
+        self.hashlock = Some((hashlock, timeout));
+        self
+    }
+
+    /// Makes Take on the opened escrow need `authorizer`'s signed release.
+    pub fn authorizer(mut self, authorizer: Pubkey) -> Self {
+        self.authorizer = Some(authorizer);
+        self
+    }
+
+    /// Makes Take on the opened escrow hold the payment for Release, or for
+    /// `arbiter` to Resolve for a fee of `arbiter_fee_bps`.
+    pub fn arbiter(mut self, arbiter: Pubkey, arbiter_fee_bps: u16) -> Self {
+        self.arbiter = Some((arbiter, arbiter_fee_bps));
+        self
+    }
+
+    pub fn build(self) -> EscrowScenario {
+        let maker = self.maker.unwrap_or_else(Pubkey::new_unique);
+        let taker = Pubkey::new_unique();
+        let mint_authority = Pubkey::new_unique();
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+        let (escrow, bump) = derive_escrow_pda(&maker, self.seed);
+
+        let mut scenario = EscrowScenario {
+            seed: self.seed,
+            maker,
+            taker,
+            mint_a,
+            mint_b,
+            escrow,
+            bump,
+            token_program: self.token_program,
+            accounts: HashMap::new(),
+        };
+
+        let program = self.token_program;
+        let with_owner = |mut account: Account| {
+            account.owner = program;
+            account
+        };
+        scenario.accounts.insert(maker, Account::new(self.wallet_lamports, 0, &solana_system_program::id()));
+        scenario.accounts.insert(taker, Account::new(self.wallet_lamports, 0, &solana_system_program::id()));
+        scenario.accounts.insert(mint_a, with_owner(self.mint_account(Side::A, &mint_authority)));
+        scenario.accounts.insert(mint_b, with_owner(self.mint_account(Side::B, &mint_authority)));
+
+        for ata in Ata::ALL {
+            let Some(amount) = self.balances[&ata] else {
+                continue;
+            };
+            let (owner, mint) = scenario.ata_owner_and_mint(ata);
+            let side = if mint == mint_a { Side::A } else { Side::B };
+            let account = self.token_account(ata, side, &owner, &mint, amount);
+            scenario.accounts.insert(scenario.ata(ata), with_owner(account));
+        }
+
+        if let Some(receive) = self.escrow {
+            let mut account = create_escrow_account(self.seed, &maker, &mint_a, &mint_b, receive, bump);
+            let state = Escrow::load_mut(&mut account.data).unwrap();
+            state.set_max_referral_bps(self.max_referral_bps);
+            if let Some((hashlock, timeout)) = self.hashlock {
+                state.set_hashlock(hashlock, taker.to_bytes(), timeout);
+            }
+            if let Some(authorizer) = self.authorizer {
+                state.set_authorizer(authorizer.to_bytes());
+            }
+            if let Some((arbiter, arbiter_fee_bps)) = self.arbiter {
+                state.set_arbiter(arbiter.to_bytes(), arbiter_fee_bps);
+            }
+            scenario.accounts.insert(escrow, account);
+        }
+
+        scenario
+    }
+
+    fn mint_extensions(&self, side: Side) -> &[MintExtension] {
+        self.extensions.get(&side).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn mint_account(&self, side: Side, mint_authority: &Pubkey) -> Account {
+        let decimals = match side {
+            Side::A => self.decimals_a,
+            Side::B => self.decimals_b,
+        };
+        let extensions = self.mint_extensions(side);
+        if extensions.is_empty() {
+            return create_mint_account(mint_authority, decimals);
+        }
+
+        let types: Vec<ExtensionType> = extensions.iter().map(MintExtension::extension_type).collect();
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&types).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data).unwrap();
+        for extension in extensions {
+            match *extension {
+                MintExtension::TransferFee { basis_points, maximum_fee } => {
+                    let fee = TransferFee {
+                        epoch: 0.into(),
+                        maximum_fee: maximum_fee.into(),
+                        transfer_fee_basis_points: basis_points.into(),
+                    };
+                    let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+                    config.older_transfer_fee = fee;
+                    config.newer_transfer_fee = fee;
+                }
+                MintExtension::NonTransferable => {
+                    state.init_extension::<NonTransferable>(true).unwrap();
+                }
+                MintExtension::DefaultAccountStateFrozen => {
+                    state.init_extension::<DefaultAccountState>(true).unwrap().state =
+                        spl_token_2022::state::AccountState::Frozen as u8;
+                }
+                MintExtension::PermanentDelegate(delegate) => {
+                    state.init_extension::<PermanentDelegate>(true).unwrap().delegate =
+                        OptionalNonZeroPubkey::try_from(Some(delegate)).unwrap();
+                }
+                MintExtension::MintCloseAuthority(authority) => {
+                    state.init_extension::<MintCloseAuthority>(true).unwrap().close_authority =
+                        OptionalNonZeroPubkey::try_from(Some(authority)).unwrap();
+                }
+            }
+        }
+        state.base = spl_token_2022::state::Mint {
+            mint_authority: Some(*mint_authority).into(),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            // DefaultAccountState needs a freeze authority
+            freeze_authority: Some(*mint_authority).into(),
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        Account::create(Rent::default().minimum_balance(len), data, spl_token_2022::ID, false, 0)
+    }
+
+    fn token_account(&self, ata: Ata, side: Side, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Account {
+        let frozen = self.frozen.contains(&ata);
+        let mint_types: Vec<ExtensionType> = self.mint_extensions(side).iter().map(MintExtension::extension_type).collect();
+        let mut types = ExtensionType::get_required_init_account_extensions(&mint_types);
+        if self.memo_required.contains(&ata) {
+            types.push(ExtensionType::MemoTransfer);
+        }
+        if self.cpi_guarded.contains(&ata) {
+            types.push(ExtensionType::CpiGuard);
+        }
+
+        if types.is_empty() {
+            let mut account = create_token_account(owner, mint, amount);
+            if frozen {
+                let mut token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+                token_account.state = spl_token::state::AccountState::Frozen;
+                spl_token::state::Account::pack(token_account, &mut account.data).unwrap();
+            }
+            return account;
+        }
+
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&types).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(&mut data).unwrap();
+        for extension_type in types {
+            match extension_type {
+                ExtensionType::MemoTransfer => {
+                    state.init_extension::<MemoTransfer>(true).unwrap().require_incoming_transfer_memos = true.into();
+                }
+                ExtensionType::CpiGuard => {
+                    state.init_extension::<CpiGuard>(true).unwrap().lock_cpi = true.into();
+                }
+                _ => {
+                    state.init_account_extension_from_type(extension_type).unwrap();
+                }
+            }
+        }
+        state.base = spl_token_2022::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: if frozen {
+                spl_token_2022::state::AccountState::Frozen
+            } else {
+                spl_token_2022::state::AccountState::Initialized
+            },
+            ..Default::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        Account::create(Rent::default().minimum_balance(len), data, spl_token_2022::ID, false, 0)
+    }
+}
+
+pub struct EscrowScenario {
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub escrow: Pubkey,
+    pub bump: u8,
+    pub token_program: Pubkey,
+    /// Every account the scenario created, by address
+    pub accounts: HashMap<Pubkey, Account>,
+}
+
+impl EscrowScenario {
+    pub fn builder() -> EscrowScenarioBuilder {
+        EscrowScenarioBuilder::default()
+    }
+
+    fn ata_owner_and_mint(&self, ata: Ata) -> (Pubkey, Pubkey) {
+        match ata {
+            Ata::MakerA => (self.maker, self.mint_a),
+            Ata::MakerB => (self.maker, self.mint_b),
+            Ata::TakerA => (self.taker, self.mint_a),
+            Ata::TakerB => (self.taker, self.mint_b),
+            Ata::Vault => (self.escrow, self.mint_a),
+            Ata::PaymentVault => (self.escrow, self.mint_b),
+        }
+    }
+
+    pub fn ata(&self, ata: Ata) -> Pubkey {
+        let (owner, mint) = self.ata_owner_and_mint(ata);
+        spl_associated_token_account::get_associated_token_address_with_program_id(&owner, &mint, &self.token_program)
+    }
+
+    // The instruction builders in `helpers` always pass the Token program
+    fn with_token_program(&self, mut instruction: Instruction) -> Instruction {
+        for meta in &mut instruction.accounts {
+            if meta.pubkey == spl_token::ID {
+                meta.pubkey = self.token_program;
+            }
+        }
+        instruction
+    }
+
+    pub fn make_instruction(&self, receive: u64, amount: u64) -> Instruction {
+        self.with_token_program(create_make_instruction(
+            &self.maker,
+            &self.escrow,
+            &self.mint_a,
+            &self.mint_b,
+            &self.ata(Ata::MakerA),
+            &self.ata(Ata::Vault),
+            self.seed,
+            receive,
+            amount,
+        ))
+    }
+
+    pub fn take_instruction(&self) -> Instruction {
+        self.with_token_program(create_take_instruction(
+            &self.taker,
+            &self.maker,
+            &self.escrow,
+            &self.mint_a,
+            &self.mint_b,
+            &self.ata(Ata::Vault),
+            &self.ata(Ata::TakerA),
+            &self.ata(Ata::TakerB),
+            &self.ata(Ata::MakerB),
+        ))
+    }
+
+    /// Claims the escrow for the taker, its hashlock's recipient.
+    pub fn claim_instruction(&self, preimage: &[u8; 32]) -> Instruction {
+        self.with_token_program(create_claim_instruction(
+            &self.taker,
+            &self.taker,
+            &self.maker,
+            &self.escrow,
+            &self.mint_a,
+            &self.ata(Ata::Vault),
+            &self.ata(Ata::TakerA),
+            preimage,
+        ))
+    }
+
+    /// Fills the maker's signed order for the taker.
+    pub fn fill_signed_order_instruction(&self, amount_a: u64, amount_b: u64, expiry: i64, nonce: u64, maker_nonce: u64) -> Instruction {
+        self.with_token_program(create_fill_signed_order_instruction(
+            &self.taker,
+            &self.maker,
+            &self.mint_a,
+            &self.mint_b,
+            &self.ata(Ata::MakerA),
+            &self.ata(Ata::MakerB),
+            &self.ata(Ata::TakerA),
+            &self.ata(Ata::TakerB),
+            amount_a,
+            amount_b,
+            expiry,
+            nonce,
+            maker_nonce,
+        ))
+    }
+
+    /// Takes the arbitrated escrow, paying into its payment vault.
+    pub fn take_into_escrow_instruction(&self) -> Instruction {
+        with_payment_vault(self.take_instruction(), &self.ata(Ata::PaymentVault))
+    }
+
+    /// Releases the taken escrow, signed by the maker and the taker.
+    pub fn release_instruction(&self) -> Instruction {
+        self.with_token_program(create_release_instruction(
+            &self.maker,
+            &self.taker,
+            &self.escrow,
+            &self.mint_a,
+            &self.mint_b,
+            &self.ata(Ata::Vault),
+            &self.ata(Ata::PaymentVault),
+            &self.ata(Ata::MakerB),
+            &self.ata(Ata::TakerA),
+        ))
+    }
+
+    /// Resolves the taken escrow as `arbiter`, completing `settle_bps` of the
+    /// trade.
+    pub fn resolve_instruction(&self, arbiter: &Pubkey, settle_bps: u16) -> Instruction {
+        self.with_token_program(create_resolve_instruction(
+            arbiter,
+            &self.maker,
+            &self.taker,
+            &self.escrow,
+            &self.mint_a,
+            &self.mint_b,
+            &self.ata(Ata::Vault),
+            &self.ata(Ata::PaymentVault),
+            &self.ata(Ata::MakerA),
+            &self.ata(Ata::MakerB),
+            &self.ata(Ata::TakerA),
+            &self.ata(Ata::TakerB),
+            settle_bps,
+        ))
+    }
+
+    pub fn refund_instruction(&self) -> Instruction {
+        self.with_token_program(create_refund_instruction(
+            &self.maker,
+            &self.escrow,
+            &self.mint_a,
+            &self.ata(Ata::Vault),
+            &self.ata(Ata::MakerA),
+        ))
+    }
+
+    /// Replaces or adds the account stored at `key`.
+    pub fn set_account(&mut self, key: Pubkey, account: Account) {
+        self.accounts.insert(key, account);
+    }
+
+    /// Carries the accounts an instruction left behind into the scenario, so
+    /// the next instruction starts from them.
+    pub fn apply(&mut self, accounts: &[(Pubkey, Account)]) {
+        for (key, account) in accounts {
+            self.accounts.insert(*key, account.clone());
+        }
+    }
+
+    /// Accounts for `instruction`, in the order of its metas, followed by the
+    /// token and Associated Token programs when the metas don't name them.
+    /// Addresses the scenario never created are passed as empty system
+    /// accounts.
+    pub fn accounts_for(&self, instruction: &Instruction) -> Vec<(Pubkey, Account)> {
+        let loader_owned = || Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"));
+        let account = |key: &Pubkey| match self.accounts.get(key) {
+            Some(account) => account.clone(),
+            None if *key == solana_system_program::id() => keyed_account_for_system_program().1,
+            None if [spl_token::ID, spl_token_2022::ID, ATOKEN_PROGRAM_ID, MEMO_PROGRAM_ID, PROGRAM_ID].contains(key) => loader_owned(),
+            None => Account::default(),
+        };
+
+        let mut keys: Vec<Pubkey> = Vec::new();
+        let programs = [self.token_program, ATOKEN_PROGRAM_ID];
+        for key in instruction.accounts.iter().map(|meta| &meta.pubkey).chain(programs.iter()) {
+            if !keys.contains(key) {
+                keys.push(*key);
+            }
+        }
+        keys.iter().map(|key| (*key, account(key))).collect()
+    }
+
+    /// Decodes the token account behind `ata`, if it is open.
+    pub fn token_account(&self, accounts: &[(Pubkey, Account)], ata: Ata) -> Option<spl_token_2022::state::Account> {
+        let key = self.ata(ata);
+        let (_, account) = accounts.iter().find(|(k, _)| *k == key)?;
+        if account.lamports == 0 || account.owner != self.token_program {
+            return None;
+        }
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+            .ok()
+            .map(|state| state.base)
+    }
+
+    /// Token balance of `ata`, zero when the account is closed or missing.
+    pub fn balance(&self, accounts: &[(Pubkey, Account)], ata: Ata) -> u64 {
+        self.token_account(accounts, ata).map(|account| account.amount).unwrap_or_default()
+    }
+
+    /// Decodes the escrow account, if it is open.
+    pub fn escrow_state(&self, accounts: &[(Pubkey, Account)]) -> Option<EscrowState> {
+        let (_, account) = accounts.iter().find(|(k, _)| *k == self.escrow)?;
+        if account.lamports == 0 || account.owner != PROGRAM_ID {
+            return None;
+        }
+        let escrow = Escrow::load(&account.data).ok()?;
+        Some(EscrowState {
+            seed: escrow.seed,
+            maker: Pubkey::new_from_array(escrow.maker),
+            mint_a: Pubkey::new_from_array(escrow.mint_a),
+            mint_b: Pubkey::new_from_array(escrow.mint_b),
+            receive: escrow.receive,
+            bump: escrow.bump[0],
+            max_referral_bps: escrow.max_referral_bps,
+            terms_hash: escrow.terms_hash,
+            external_ref: escrow.external_ref,
+            maker_nonce: escrow.maker_nonce,
+            arbiter: Pubkey::new_from_array(escrow.arbiter),
+            arbiter_fee_bps: escrow.arbiter_fee_bps,
+            taker: Pubkey::new_from_array(escrow.taker),
+        })
+    }
+}
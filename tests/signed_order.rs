@@ -0,0 +1,269 @@
+//! Signed orders: the maker signs an order off-chain and approves the
+//! program's delegate, and FillSignedOrder settles both legs for the taker
+//! without a vault. CancelOrder and CancelAll void orders before they fill.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::{fill_signed_order::FillSignedOrderInstructionData, EscrowError, OrderNonce, PinocchioError},
+    mollusk_svm::{result::{Check, ProgramResult}, Mollusk},
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_keypair::Keypair,
+    solana_pubkey::Pubkey,
+    solana_signer::Signer,
+    spl_token::solana_program::{program_error::ProgramError, program_option::COption, program_pack::Pack},
+};
+
+const NOW: i64 = 1_000;
+const EXPIRY: i64 = 2_000;
+
+struct Order {
+    amount_a: u64,
+    amount_b: u64,
+    expiry: i64,
+    nonce: u64,
+    maker_nonce: u64,
+}
+
+const ORDER: Order = Order { amount_a: 400, amount_b: 750, expiry: EXPIRY, nonce: 0, maker_nonce: 0 };
+
+/// The maker holds 1,000 token A, 800 of them approved to the delegate, and
+/// the taker 3,000 token B.
+fn order_scenario(maker: &Keypair) -> EscrowScenario {
+    let mut scenario = EscrowScenario::builder()
+        .maker(maker.pubkey())
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::TakerB, 3_000)
+        .build();
+    let maker_ata_a = scenario.ata(Ata::MakerA);
+    let mut account = scenario.accounts[&maker_ata_a].clone();
+    let mut token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+    token_account.delegate = COption::Some(DELEGATE_ADDRESS);
+    token_account.delegated_amount = 800;
+    spl_token::state::Account::pack(token_account, &mut account.data).unwrap();
+    scenario.set_account(maker_ata_a, account);
+    scenario
+}
+
+fn signed_order(signer: &Keypair, scenario: &EscrowScenario, order: &Order) -> Instruction {
+    let data = FillSignedOrderInstructionData {
+        amount_a: order.amount_a,
+        amount_b: order.amount_b,
+        expiry: order.expiry,
+        nonce: order.nonce,
+        maker_nonce: order.maker_nonce,
+    };
+    let message = data.message(&scenario.maker.to_bytes(), &scenario.mint_a.to_bytes(), &scenario.mint_b.to_bytes());
+    create_ed25519_instruction(signer, &message)
+}
+
+/// Runs FillSignedOrder for `order` after `signature`, keeping the accounts it
+/// leaves behind.
+fn fill(mollusk: &Mollusk, scenario: &mut EscrowScenario, signature: &Instruction, order: &Order, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let instruction = with_associated_token_program(scenario.fill_signed_order_instruction(order.amount_a, order.amount_b, order.expiry, order.nonce, order.maker_nonce));
+    scenario.set_account(solana_instructions_sysvar::ID, create_instructions_sysvar_account(&[signature, &instruction], 1));
+
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::program_result(expected)]);
+    scenario.apply(&result.resulting_accounts);
+    result.resulting_accounts
+}
+
+#[test]
+fn test_fill_signed_order_settles_both_legs() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    let accounts = fill(&mollusk, &mut scenario, &signature, &ORDER, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 600);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 400);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 750);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerB), 2_250);
+
+    // The taker paid for the account recording that nonce 0 is spent
+    let (order_nonce_key, bump) = derive_order_nonce_pda(&scenario.maker, 0);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == order_nonce_key).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    let order_nonce = OrderNonce::load(&account.data).unwrap();
+    assert_eq!((Pubkey::new_from_array(order_nonce.maker), { order_nonce.nonce }, order_nonce.bump[0]), (scenario.maker, 0, bump));
+    assert_eq!(({ order_nonce.maker_nonce }, Pubkey::new_from_array(order_nonce.payer)), (0, scenario.taker));
+}
+
+#[test]
+fn test_fill_signed_order_never_replays() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+
+    // Several live orders fill in any order, each only once
+    let (first, second) = (Order { nonce: 7, ..ORDER }, Order { nonce: 3, ..ORDER });
+    let first_signature = signed_order(&maker, &scenario, &first);
+    let second_signature = signed_order(&maker, &scenario, &second);
+    fill(&mollusk, &mut scenario, &first_signature, &first, ProgramResult::Success);
+    fill(&mollusk, &mut scenario, &first_signature, &first, escrow_error(EscrowError::OrderNonceInvalid));
+    let accounts = fill(&mollusk, &mut scenario, &second_signature, &second, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 800);
+    fill(&mollusk, &mut scenario, &second_signature, &second, escrow_error(EscrowError::OrderNonceInvalid));
+
+    // An alternative signed with a spent nonce is void too
+    let alternative = Order { amount_b: 900, ..first };
+    let signature = signed_order(&maker, &scenario, &alternative);
+    fill(&mollusk, &mut scenario, &signature, &alternative, escrow_error(EscrowError::OrderNonceInvalid));
+}
+
+#[test]
+fn test_fill_signed_order_with_prefunded_order_nonce() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+
+    // Lamports sent to the nonce's address ahead of time don't block the fill
+    let order_nonce_key = derive_order_nonce_pda(&scenario.maker, ORDER.nonce).0;
+    scenario.set_account(order_nonce_key, Account::new(1_000, 0, &solana_system_program::id()));
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    let accounts = fill(&mollusk, &mut scenario, &signature, &ORDER, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 400);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == order_nonce_key).unwrap();
+    assert_eq!(account.owner, PROGRAM_ID);
+    assert_eq!(account.lamports, mollusk.sysvars.rent.minimum_balance(OrderNonce::LEN));
+}
+
+#[test]
+fn test_cancel_order_voids_its_nonce() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+
+    // Only the maker can cancel
+    let mut instruction = create_cancel_order_instruction(&scenario.maker, ORDER.nonce);
+    instruction.accounts[0].is_signer = false;
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(PinocchioError::NotSigner as u32)))],
+    );
+    scenario.apply(&result.resulting_accounts);
+
+    let instruction = create_cancel_order_instruction(&scenario.maker, ORDER.nonce);
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+    scenario.apply(&result.resulting_accounts);
+
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    fill(&mollusk, &mut scenario, &signature, &ORDER, escrow_error(EscrowError::OrderNonceInvalid));
+
+    // Cancelling a spent nonce again fails
+    let instruction = create_cancel_order_instruction(&scenario.maker, ORDER.nonce);
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(escrow_error(EscrowError::OrderNonceInvalid))],
+    );
+}
+
+#[test]
+fn test_cancel_all_voids_signed_orders() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+
+    let instruction = create_cancel_all_instruction(&scenario.maker);
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+    scenario.apply(&result.resulting_accounts);
+
+    // Orders signed before CancelAll carry the old maker nonce
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    fill(&mollusk, &mut scenario, &signature, &ORDER, escrow_error(EscrowError::OrderCancelled));
+
+    let current = Order { maker_nonce: 1, ..ORDER };
+    let signature = signed_order(&maker, &scenario, &current);
+    let accounts = fill(&mollusk, &mut scenario, &signature, &current, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 400);
+}
+
+#[test]
+fn test_close_order_nonce_once_the_maker_moved_past_it() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    fill(&mollusk, &mut scenario, &signature, &ORDER, ProgramResult::Success);
+
+    // Until CancelAll, closing the nonce would let the order fill again
+    let instruction = create_close_order_nonce_instruction(&scenario.maker, &scenario.maker, &scenario.taker, ORDER.nonce);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::OrderNonceLive));
+    let instruction = create_cancel_all_instruction(&scenario.maker);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+
+    // Only the maker or the taker who paid may close it, and only to the taker
+    let instruction = create_close_order_nonce_instruction(&Pubkey::new_unique(), &scenario.maker, &scenario.taker, ORDER.nonce);
+    run(&mollusk, &mut scenario, instruction, escrow_error(EscrowError::Unauthorized));
+    let instruction = create_close_order_nonce_instruction(&scenario.maker, &scenario.maker, &scenario.maker, ORDER.nonce);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)));
+
+    let order_nonce_key = derive_order_nonce_pda(&scenario.maker, ORDER.nonce).0;
+    let rent = scenario.accounts[&order_nonce_key].lamports;
+    let taker_lamports = scenario.accounts[&scenario.taker].lamports;
+    let instruction = create_close_order_nonce_instruction(&scenario.maker, &scenario.maker, &scenario.taker, ORDER.nonce);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    let (_, account) = accounts.iter().find(|(key, _)| *key == order_nonce_key).unwrap();
+    assert_eq!(account.lamports, 0);
+    let (_, taker) = accounts.iter().find(|(key, _)| *key == scenario.taker).unwrap();
+    assert_eq!(taker.lamports, taker_lamports + rent);
+
+    // The old order stays cancelled, but the nonce is free at the new maker nonce
+    fill(&mollusk, &mut scenario, &signature, &ORDER, escrow_error(EscrowError::OrderCancelled));
+    let current = Order { maker_nonce: 1, ..ORDER };
+    let signature = signed_order(&maker, &scenario, &current);
+    let accounts = fill(&mollusk, &mut scenario, &signature, &current, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 800);
+}
+
+#[test]
+fn test_fill_signed_order_rejects_orders_the_maker_did_not_sign() {
+    let mollusk = mollusk_at(NOW);
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+
+    // Signed by someone else, or with the amounts changed after signing
+    let signature = signed_order(&Keypair::new(), &scenario, &ORDER);
+    fill(&mollusk, &mut scenario, &signature, &ORDER, escrow_error(EscrowError::OrderSignatureMissing));
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    let tampered = Order { amount_b: 1, ..ORDER };
+    fill(&mollusk, &mut scenario, &signature, &tampered, escrow_error(EscrowError::OrderSignatureMissing));
+
+    // Or by the maker for another nonce account than the one passed
+    let mut instruction = with_associated_token_program(scenario.fill_signed_order_instruction(ORDER.amount_a, ORDER.amount_b, ORDER.expiry, ORDER.nonce, ORDER.maker_nonce));
+    instruction.accounts[8].pubkey = derive_order_nonce_pda(&scenario.maker, 1).0;
+    scenario.set_account(solana_instructions_sysvar::ID, create_instructions_sysvar_account(&[&signature, &instruction], 1));
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(escrow_error(EscrowError::OrderNonceMismatch))],
+    );
+}
+
+#[test]
+fn test_fill_signed_order_rejects_expired_orders_and_other_delegates() {
+    let maker = Keypair::new();
+    let mut scenario = order_scenario(&maker);
+    let signature = signed_order(&maker, &scenario, &ORDER);
+    fill(&mollusk_at(EXPIRY), &mut scenario, &signature, &ORDER, escrow_error(EscrowError::OrderExpired));
+
+    let mut instruction = with_associated_token_program(scenario.fill_signed_order_instruction(ORDER.amount_a, ORDER.amount_b, ORDER.expiry, ORDER.nonce, ORDER.maker_nonce));
+    instruction.accounts[9].pubkey = Pubkey::new_unique();
+    mollusk_at(NOW).process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidAddress as u32)))],
+    );
+}
+
+#[test]
+fn test_delegate_address_is_the_delegate_pda() {
+    use blueshift_escrow::fill_signed_order::FillSignedOrder;
+    assert_eq!(
+        Pubkey::find_program_address(&[FillSignedOrder::DELEGATE_SEED], &PROGRAM_ID),
+        (DELEGATE_ADDRESS, FillSignedOrder::DELEGATE_BUMP),
+    );
+}
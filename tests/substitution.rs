@@ -0,0 +1,250 @@
+//! Account-substitution attacks generated from known-good scenarios.
+//!
+//! Each scenario is a Make, Take or Refund that succeeds as built. From it we
+//! derive every variant that swaps two accounts, clears a signer or writable
+//! flag, gives an account a different owner, or passes one account in two
+//! positions. Every variant must either fail, or succeed with the real
+//! authority still signing and without paying anyone outside the scenario.
+
+use {
+    crate::{
+        helpers::*,
+        scenario::{Ata, EscrowScenario},
+    },
+    mollusk_svm::Mollusk,
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_pack::Pack,
+    std::collections::HashMap,
+};
+
+struct Scenario {
+    instruction: Instruction,
+    accounts: Vec<(Pubkey, Account)>,
+    // The account whose signature authorizes the instruction
+    authority: Pubkey,
+    // Wallets and PDAs allowed to end up with more tokens or lamports
+    beneficiaries: Vec<Pubkey>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Variant {
+    Swap(usize, usize),
+    ClearSigner(usize),
+    ClearWritable(usize),
+    ReplaceOwner(usize, Pubkey),
+    Alias { source: usize, target: usize },
+}
+
+// Every single-step substitution of the instruction's accounts
+fn variants(instruction: &Instruction) -> Vec<Variant> {
+    let metas = &instruction.accounts;
+    let owners = [solana_system_program::id(), spl_token::ID, PROGRAM_ID, Pubkey::new_unique()];
+    let mut variants = Vec::new();
+
+    for a in 0..metas.len() {
+        for b in a + 1..metas.len() {
+            if metas[a].pubkey != metas[b].pubkey {
+                variants.push(Variant::Swap(a, b));
+            }
+        }
+    }
+    for (index, meta) in metas.iter().enumerate() {
+        if meta.is_signer {
+            variants.push(Variant::ClearSigner(index));
+        }
+        if meta.is_writable {
+            variants.push(Variant::ClearWritable(index));
+        }
+        for owner in owners {
+            variants.push(Variant::ReplaceOwner(index, owner));
+        }
+    }
+    for source in 0..metas.len() {
+        for target in 0..metas.len() {
+            if metas[source].pubkey != metas[target].pubkey {
+                variants.push(Variant::Alias { source, target });
+            }
+        }
+    }
+    variants
+}
+
+fn apply(scenario: &Scenario, variant: Variant) -> (Instruction, Vec<(Pubkey, Account)>) {
+    let mut instruction = scenario.instruction.clone();
+    let mut accounts = scenario.accounts.clone();
+    let metas = &mut instruction.accounts;
+
+    match variant {
+        Variant::Swap(a, b) => {
+            let key = metas[a].pubkey;
+            metas[a].pubkey = metas[b].pubkey;
+            metas[b].pubkey = key;
+        }
+        Variant::ClearSigner(index) => metas[index].is_signer = false,
+        Variant::ClearWritable(index) => metas[index].is_writable = false,
+        Variant::ReplaceOwner(index, owner) => {
+            let key = metas[index].pubkey;
+            if let Some((_, account)) = accounts.iter_mut().find(|(k, _)| *k == key) {
+                account.owner = owner;
+            }
+        }
+        Variant::Alias { source, target } => metas[target].pubkey = metas[source].pubkey,
+    }
+    (instruction, accounts)
+}
+
+// Token balances per (mint, token owner) over every live token account
+fn token_balances(accounts: &[(Pubkey, Account)]) -> HashMap<(Pubkey, Pubkey), u128> {
+    let mut balances = HashMap::new();
+    for (_, account) in accounts {
+        if account.owner != spl_token::ID || account.lamports == 0 {
+            continue;
+        }
+        if let Ok(token_account) = spl_token::state::Account::unpack(&account.data) {
+            *balances.entry((token_account.mint, token_account.owner)).or_default() += token_account.amount as u128;
+        }
+    }
+    balances
+}
+
+fn token_owner(account: &Account) -> Option<Pubkey> {
+    if account.owner != spl_token::ID {
+        return None;
+    }
+    spl_token::state::Account::unpack(&account.data).ok().map(|token_account| token_account.owner)
+}
+
+/// Runs the scenario, then every variant of it, and panics on the first
+/// variant that succeeds in an unsafe way.
+fn assert_substitutions_are_safe(mollusk: &Mollusk, scenario: &Scenario) {
+    let baseline = mollusk.process_instruction(&scenario.instruction, &scenario.accounts);
+    assert!(baseline.program_result.is_ok(), "baseline failed: {:?}", baseline.program_result);
+
+    for variant in variants(&scenario.instruction) {
+        let (instruction, accounts) = apply(scenario, variant);
+        let result = mollusk.process_instruction(&instruction, &accounts);
+        if result.program_result.is_err() {
+            continue;
+        }
+
+        let authority_signed = instruction
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == scenario.authority && meta.is_signer);
+        assert!(authority_signed, "{variant:?} succeeded without the authority's signature");
+
+        let before: HashMap<Pubkey, &Account> = accounts.iter().map(|(key, account)| (*key, account)).collect();
+        let is_beneficiary = |key: &Pubkey| scenario.beneficiaries.contains(key);
+
+        // Lamports only flow to beneficiaries or token accounts they own
+        for (key, account) in &result.resulting_accounts {
+            let previous = before.get(key).map(|account| account.lamports).unwrap_or_default();
+            if account.lamports > previous {
+                assert!(
+                    is_beneficiary(key) || token_owner(account).is_some_and(|owner| is_beneficiary(&owner)),
+                    "{variant:?} paid {} lamports to {key}",
+                    account.lamports - previous
+                );
+            }
+        }
+
+        // Tokens only flow to beneficiaries
+        let touched: Vec<(Pubkey, Account)> = accounts
+            .iter()
+            .filter(|(key, _)| result.resulting_accounts.iter().any(|(k, _)| k == key))
+            .cloned()
+            .collect();
+        let balances_before = token_balances(&touched);
+        for ((mint, owner), amount) in token_balances(&result.resulting_accounts) {
+            let previous = balances_before.get(&(mint, owner)).copied().unwrap_or_default();
+            assert!(
+                amount <= previous || is_beneficiary(&owner),
+                "{variant:?} moved {} of {mint} to {owner}",
+                amount - previous
+            );
+        }
+    }
+}
+
+fn make_scenario(existing_vault: bool) -> Scenario {
+    let mut builder = EscrowScenario::builder().seed(11).balance(Ata::MakerA, 1_000);
+    if existing_vault {
+        builder = builder.balance(Ata::Vault, 0);
+    }
+    let scenario = builder.build();
+    let instruction = with_associated_token_program(scenario.make_instruction(2_000, 500));
+
+    Scenario {
+        accounts: scenario.accounts_for(&instruction),
+        instruction,
+        authority: scenario.maker,
+        beneficiaries: vec![scenario.maker, scenario.escrow],
+    }
+}
+
+fn take_scenario(existing_atas: bool) -> Scenario {
+    let mut builder = EscrowScenario::builder()
+        .seed(12)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 3_000);
+    if !existing_atas {
+        builder = builder.missing(Ata::TakerA).missing(Ata::MakerB);
+    }
+    let scenario = builder.build();
+    let instruction = with_associated_token_program(scenario.take_instruction());
+
+    Scenario {
+        accounts: scenario.accounts_for(&instruction),
+        instruction,
+        authority: scenario.taker,
+        beneficiaries: vec![scenario.maker, scenario.taker],
+    }
+}
+
+fn refund_scenario(existing_ata: bool) -> Scenario {
+    let mut builder = EscrowScenario::builder().seed(13).open(2_000, 1_500);
+    if !existing_ata {
+        builder = builder.missing(Ata::MakerA);
+    }
+    let scenario = builder.build();
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+
+    Scenario {
+        accounts: scenario.accounts_for(&instruction),
+        instruction,
+        authority: scenario.maker,
+        beneficiaries: vec![scenario.maker],
+    }
+}
+
+#[test]
+fn test_make_substitutions_existing_vault() {
+    assert_substitutions_are_safe(&setup_mollusk(), &make_scenario(true));
+}
+
+#[test]
+fn test_make_substitutions_new_vault() {
+    assert_substitutions_are_safe(&setup_mollusk(), &make_scenario(false));
+}
+
+#[test]
+fn test_take_substitutions_existing_atas() {
+    assert_substitutions_are_safe(&setup_mollusk(), &take_scenario(true));
+}
+
+#[test]
+fn test_take_substitutions_new_atas() {
+    assert_substitutions_are_safe(&setup_mollusk(), &take_scenario(false));
+}
+
+#[test]
+fn test_refund_substitutions_existing_ata() {
+    assert_substitutions_are_safe(&setup_mollusk(), &refund_scenario(true));
+}
+
+#[test]
+fn test_refund_substitutions_new_ata() {
+    assert_substitutions_are_safe(&setup_mollusk(), &refund_scenario(false));
+}
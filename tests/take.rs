@@ -1,78 +1,44 @@
 use {
-    crate::helpers::*, mollusk_svm::{
+    crate::{helpers::*, scenario::{Ata, EscrowScenario, EscrowScenarioBuilder}}, blueshift_escrow::{Escrow, EscrowError, PinocchioError}, mollusk_svm::{
         program::keyed_account_for_system_program,
         result::{Check, ProgramResult}
-    }, solana_account::Account, solana_pubkey::Pubkey, solana_system_program, spl_token::solana_program::program_error::ProgramError
+    }, solana_account::Account, solana_instruction::{error::InstructionError, Instruction}, solana_pubkey::Pubkey, spl_token::{error::TokenError, solana_program::{program_error::ProgramError, program_pack::Pack}}
 };
 
 #[test]
 fn test_take_instruction_success() {
     let mollusk = setup_mollusk();
-    
-    // Test parameters
-    let seed = 54321u64;
-    let receive_amount = 2000u64;  // Amount taker needs to pay
-    let deposit_amount = 1500u64;  // Amount maker deposited (vault balance)
 
-    // Generate test keypairs
-    let maker = Pubkey::new_unique();
-    let taker = Pubkey::new_unique();
-    let mint_authority = Pubkey::new_unique();
-    let mint_a = Pubkey::new_unique(); // Token maker deposited
-    let mint_b = Pubkey::new_unique(); // Token taker will pay
-
-    // Derive PDAs and ATAs
-    let (escrow, bump) = derive_escrow_pda(&maker, seed);
-    let vault = derive_associated_token_account(&escrow, &mint_a);
-    let taker_ata_a = derive_associated_token_account(&taker, &mint_a);
-    let taker_ata_b = derive_associated_token_account(&taker, &mint_b);
-    let maker_ata_b = derive_associated_token_account(&maker, &mint_b);
-
-    // Create take instruction
-    let instruction = create_take_instruction(
-        &taker,
-        &maker,
-        &escrow,
-        &mint_a,
-        &mint_b,
-        &vault,
-        &taker_ata_a,
-        &taker_ata_b,
-        &maker_ata_b,
-    );
-
-    // Setup accounts
-    let accounts = vec![
-        (taker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, receive_amount, bump)),
-        (mint_a, create_mint_account(&mint_authority, 9)),
-        (mint_b, create_mint_account(&mint_authority, 6)),
-        (vault, create_token_account(&escrow, &mint_a, deposit_amount)), // Vault has deposit
-        (taker_ata_a, create_token_account(&taker, &mint_a, 0)), // Will receive from vault
-        (taker_ata_b, create_token_account(&taker, &mint_b, 3000)), // Taker has enough tokens to pay
-        (maker_ata_b, create_token_account(&maker, &mint_b, 0)), // Will receive from taker
-        keyed_account_for_system_program(),
-        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-        (ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-    ];
+    // The vault holds the maker's deposit and the taker has enough token B to pay
+    let scenario = EscrowScenario::builder()
+        .seed(54321)
+        .open(2000, 1500)
+        .balance(Ata::TakerB, 3000)
+        .build();
+    let instruction = scenario.take_instruction();
 
     // Process and validate the instruction
-    mollusk.process_and_validate_instruction(
+    let result = mollusk.process_and_validate_instruction(
         &instruction,
-        &accounts,
+        &scenario.accounts_for(&instruction),
         &[
             Check::success(),
             // Verify taker_ata_a received tokens from vault
-            Check::account(&taker_ata_a)
+            Check::account(&scenario.ata(Ata::TakerA))
                 .owner(&spl_token::ID)
                 .build(),
-            // Verify maker_ata_b received tokens from taker  
-            Check::account(&maker_ata_b)
+            // Verify maker_ata_b received tokens from taker
+            Check::account(&scenario.ata(Ata::MakerB))
                 .owner(&spl_token::ID)
                 .build(),
         ],
     );
+
+    let accounts = &result.resulting_accounts;
+    assert_eq!(scenario.balance(accounts, Ata::TakerA), 1500);
+    assert_eq!(scenario.balance(accounts, Ata::TakerB), 1000);
+    assert_eq!(scenario.balance(accounts, Ata::MakerB), 2000);
+    assert!(scenario.escrow_state(accounts).is_none());
 }
 
 // ============================================================================
@@ -82,157 +48,73 @@ fn test_take_instruction_success() {
 #[test]
 fn test_take_instruction_minimum_amounts() {
     let mollusk = setup_mollusk();
-    
-    // Test parameters - using minimum amounts (1 unit)
-    let seed = 99999u64;
-    let receive_amount = 1u64;  // Minimum amount taker needs to pay
-    let deposit_amount = 1u64;  // Minimum amount maker deposited
-
-    // Generate test keypairs
-    let maker = Pubkey::new_unique();
-    let taker = Pubkey::new_unique();
-    let mint_authority = Pubkey::new_unique();
-    let mint_a = Pubkey::new_unique(); // Token maker deposited
-    let mint_b = Pubkey::new_unique(); // Token taker will pay
-
-    // Derive PDAs and ATAs
-    let (escrow, bump) = derive_escrow_pda(&maker, seed);
-    let vault = derive_associated_token_account(&escrow, &mint_a);
-    let taker_ata_a = derive_associated_token_account(&taker, &mint_a);
-    let taker_ata_b = derive_associated_token_account(&taker, &mint_b);
-    let maker_ata_b = derive_associated_token_account(&maker, &mint_b);
-
-    // Create take instruction
-    let instruction = create_take_instruction(
-        &taker,
-        &maker,
-        &escrow,
-        &mint_a,
-        &mint_b,
-        &vault,
-        &taker_ata_a,
-        &taker_ata_b,
-        &maker_ata_b,
-    );
 
-    // Setup accounts
-    let accounts = vec![
-        (taker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, receive_amount, bump)),
-        (mint_a, create_mint_account(&mint_authority, 9)), // 9 decimals
-        (mint_b, create_mint_account(&mint_authority, 6)), // 6 decimals
-        (vault, create_token_account(&escrow, &mint_a, deposit_amount)), // Vault has 1 unit
-        (taker_ata_a, create_token_account(&taker, &mint_a, 0)), // Will receive 1 unit from vault
-        (taker_ata_b, create_token_account(&taker, &mint_b, 1)), // Taker has exactly 1 unit to pay
-        (maker_ata_b, create_token_account(&maker, &mint_b, 0)), // Will receive 1 unit from taker
-        keyed_account_for_system_program(),
-        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-        (ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-    ];
+    // Minimum amounts: the vault holds 1 unit and the taker pays exactly 1 unit
+    let scenario = EscrowScenario::builder()
+        .seed(99999)
+        .open(1, 1)
+        .balance(Ata::TakerB, 1)
+        .build();
+    let instruction = scenario.take_instruction();
 
     // Process and validate the instruction
-    mollusk.process_and_validate_instruction(
+    let result = mollusk.process_and_validate_instruction(
         &instruction,
-        &accounts,
+        &scenario.accounts_for(&instruction),
         &[
             Check::success(),
-            // Verify taker_ata_a received exactly 1 unit from vault
-            Check::account(&taker_ata_a)
-                .owner(&spl_token::ID)
-                .build(),
-            // Verify maker_ata_b received exactly 1 unit from taker  
-            Check::account(&maker_ata_b)
-                .owner(&spl_token::ID)
+            // Verify the vault is closed
+            Check::account(&scenario.ata(Ata::Vault))
+                .lamports(0)
                 .build(),
-            // Verify vault is closed (balance should be 0)
-            Check::account(&vault)
-                .lamports(0) // Account should be closed
-                .build(),
-            // Verify escrow is closed
-            Check::account(&escrow)
-                .lamports(0) // Account should be closed
+            // Verify the escrow is closed
+            Check::account(&scenario.escrow)
+                .lamports(0)
                 .build(),
         ],
     );
+
+    let accounts = &result.resulting_accounts;
+    assert_eq!(scenario.balance(accounts, Ata::TakerA), 1);
+    assert_eq!(scenario.balance(accounts, Ata::TakerB), 0);
+    assert_eq!(scenario.balance(accounts, Ata::MakerB), 1);
 }
 
 #[test]
 fn test_take_instruction_large_amounts() {
     let mollusk = setup_mollusk();
-    
-    // Test parameters - using large amounts (close to u64::MAX practical limits)
-    let seed = 88888u64;
-    let receive_amount = 1_000_000_000_000_000u64;  // 1 quadrillion units (large but reasonable)
-    let deposit_amount = 500_000_000_000_000u64;    // 500 trillion units
-
-    // Generate test keypairs
-    let maker = Pubkey::new_unique();
-    let taker = Pubkey::new_unique();
-    let mint_authority = Pubkey::new_unique();
-    let mint_a = Pubkey::new_unique(); // Token maker deposited
-    let mint_b = Pubkey::new_unique(); // Token taker will pay
-
-    // Derive PDAs and ATAs
-    let (escrow, bump) = derive_escrow_pda(&maker, seed);
-    let vault = derive_associated_token_account(&escrow, &mint_a);
-    let taker_ata_a = derive_associated_token_account(&taker, &mint_a);
-    let taker_ata_b = derive_associated_token_account(&taker, &mint_b);
-    let maker_ata_b = derive_associated_token_account(&maker, &mint_b);
-
-    // Create take instruction
-    let instruction = create_take_instruction(
-        &taker,
-        &maker,
-        &escrow,
-        &mint_a,
-        &mint_b,
-        &vault,
-        &taker_ata_a,
-        &taker_ata_b,
-        &maker_ata_b,
-    );
 
-    // Setup accounts with large balances
-    let accounts = vec![
-        (taker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, receive_amount, bump)),
-        (mint_a, create_mint_account(&mint_authority, 9)), // 9 decimals
-        (mint_b, create_mint_account(&mint_authority, 6)), // 6 decimals
-        (vault, create_token_account(&escrow, &mint_a, deposit_amount)), // Vault has large amount
-        (taker_ata_a, create_token_account(&taker, &mint_a, 0)), // Will receive large amount from vault
-        (taker_ata_b, create_token_account(&taker, &mint_b, 2_000_000_000_000_000u64)), // Taker has enough to pay
-        (maker_ata_b, create_token_account(&maker, &mint_b, 0)), // Will receive large amount from taker
-        keyed_account_for_system_program(),
-        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-        (ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-    ];
+    // Large amounts, well past any u32 range
+    let receive_amount = 1_000_000_000_000_000u64; // 1 quadrillion units
+    let deposit_amount = 500_000_000_000_000u64; // 500 trillion units
+    let scenario = EscrowScenario::builder()
+        .seed(88888)
+        .open(receive_amount, deposit_amount)
+        .balance(Ata::TakerB, 2_000_000_000_000_000)
+        .build();
+    let instruction = scenario.take_instruction();
 
     // Process and validate the instruction
-    mollusk.process_and_validate_instruction(
+    let result = mollusk.process_and_validate_instruction(
         &instruction,
-        &accounts,
+        &scenario.accounts_for(&instruction),
         &[
             Check::success(),
-            // Verify taker_ata_a received the large amount from vault
-            Check::account(&taker_ata_a)
-                .owner(&spl_token::ID)
-                .build(),
-            // Verify maker_ata_b received the large amount from taker  
-            Check::account(&maker_ata_b)
-                .owner(&spl_token::ID)
+            // Verify the vault is closed
+            Check::account(&scenario.ata(Ata::Vault))
+                .lamports(0)
                 .build(),
-            // Verify vault is closed (balance should be 0)
-            Check::account(&vault)
-                .lamports(0) // Account should be closed
-                .build(),
-            // Verify escrow is closed
-            Check::account(&escrow)
-                .lamports(0) // Account should be closed
+            // Verify the escrow is closed
+            Check::account(&scenario.escrow)
+                .lamports(0)
                 .build(),
         ],
     );
+
+    let accounts = &result.resulting_accounts;
+    assert_eq!(scenario.balance(accounts, Ata::TakerA), deposit_amount);
+    assert_eq!(scenario.balance(accounts, Ata::TakerB), 2_000_000_000_000_000 - receive_amount);
+    assert_eq!(scenario.balance(accounts, Ata::MakerB), receive_amount);
 }
 
 // ============================================================================
@@ -242,107 +124,233 @@ fn test_take_instruction_large_amounts() {
 #[test]
 fn test_take_instruction_invalid_escrow_pda() {
     let mollusk = setup_mollusk();
-    
-    // Test parameters
-    let seed = 12345u64;
-    let receive_amount = 2000u64;
-    let deposit_amount = 1500u64;
+    let mut scenario = EscrowScenario::builder()
+        .seed(12345)
+        .open(2000, 1500)
+        .balance(Ata::TakerB, 3000)
+        .build();
 
-    // Generate test keypairs
-    let maker = Pubkey::new_unique();
-    let taker = Pubkey::new_unique();
-    let mint_authority = Pubkey::new_unique();
-    let mint_a = Pubkey::new_unique();
-    let mint_b = Pubkey::new_unique();
+    // Copy valid escrow data to a random address that is not the PDA
+    let invalid_escrow = Pubkey::new_unique();
+    let escrow_account = scenario.accounts[&scenario.escrow].clone();
+    scenario.set_account(invalid_escrow, escrow_account);
 
-    // Derive correct PDA
-    let (correct_escrow, bump) = derive_escrow_pda(&maker, seed);
-    let vault = derive_associated_token_account(&correct_escrow, &mint_a);
-    let taker_ata_a = derive_associated_token_account(&taker, &mint_a);
-    let taker_ata_b = derive_associated_token_account(&taker, &mint_b);
-    let maker_ata_b = derive_associated_token_account(&maker, &mint_b);
-
-    // Create an INVALID escrow account (different from the correct PDA)
-    let invalid_escrow = Pubkey::new_unique(); // Random pubkey, not a proper PDA
-
-    // Create take instruction with the INVALID escrow
-    let instruction = create_take_instruction(
-        &taker,
-        &maker,
-        &invalid_escrow, // Using invalid escrow here
-        &mint_a,
-        &mint_b,
-        &vault,
-        &taker_ata_a,
-        &taker_ata_b,
-        &maker_ata_b,
-    );
-
-    // Setup accounts - note we create the invalid escrow account but it won't match PDA derivation
-    let accounts = vec![
-        (taker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
-        // Create invalid escrow account with proper data but wrong address
-        (invalid_escrow, create_escrow_account(seed, &maker, &mint_a, &mint_b, receive_amount, bump)),
-        (mint_a, create_mint_account(&mint_authority, 9)),
-        (mint_b, create_mint_account(&mint_authority, 6)),
-        (vault, create_token_account(&correct_escrow, &mint_a, deposit_amount)), // Vault still uses correct escrow
-        (taker_ata_a, create_token_account(&taker, &mint_a, 0)),
-        (taker_ata_b, create_token_account(&taker, &mint_b, 3000)),
-        (maker_ata_b, create_token_account(&maker, &mint_b, 0)),
-        keyed_account_for_system_program(),
-        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-        (ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
-    ];
+    let mut instruction = scenario.take_instruction();
+    instruction.accounts[ESCROW].pubkey = invalid_escrow;
 
     // Should fail because the escrow account doesn't match the derived PDA
     mollusk.process_and_validate_instruction(
         &instruction,
-        &accounts,
-        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidAccountOwner))],
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(custom(EscrowError::EscrowPdaMismatch as u32))],
     );
 }
 
+
+// Shared setup for the tests below: a valid Take where every ATA already
+// exists. Each test tweaks the instruction or an account before processing.
+struct TakeFixture {
+    maker: Pubkey,
+    taker: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    escrow: Pubkey,
+    bump: u8,
+    vault: Pubkey,
+    taker_ata_a: Pubkey,
+    taker_ata_b: Pubkey,
+    maker_ata_b: Pubkey,
+    instruction: Instruction,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+// Account positions shared by the Take instruction and the fixture's account list
+const TAKER: usize = 0;
+const MAKER: usize = 1;
+const ESCROW: usize = 2;
+const MINT_A: usize = 3;
+const MINT_B: usize = 4;
+const VAULT: usize = 5;
+const TAKER_ATA_A: usize = 6;
+const TAKER_ATA_B: usize = 7;
+const MAKER_ATA_B: usize = 8;
+const SYSTEM_PROGRAM: usize = 9;
+const TOKEN_PROGRAM: usize = 10;
+
+const RECEIVE_AMOUNT: u64 = 2000;
+const DEPOSIT_AMOUNT: u64 = 1500;
+const TAKER_BALANCE_B: u64 = 3000;
+
+// A valid Take where every ATA already exists
+fn take_scenario(seed: u64) -> EscrowScenarioBuilder {
+    EscrowScenario::builder()
+        .seed(seed)
+        .open(RECEIVE_AMOUNT, DEPOSIT_AMOUNT)
+        .balance(Ata::TakerB, TAKER_BALANCE_B)
+}
+
+impl TakeFixture {
+    fn new(seed: u64) -> Self {
+        Self::from_builder(take_scenario(seed))
+    }
+
+    fn from_builder(builder: EscrowScenarioBuilder) -> Self {
+        let scenario = builder.build();
+        let instruction = scenario.take_instruction();
+        let accounts = scenario.accounts_for(&instruction);
+
+        Self {
+            maker: scenario.maker,
+            taker: scenario.taker,
+            mint_a: scenario.mint_a,
+            mint_b: scenario.mint_b,
+            escrow: scenario.escrow,
+            bump: scenario.bump,
+            vault: scenario.ata(Ata::Vault),
+            taker_ata_a: scenario.ata(Ata::TakerA),
+            taker_ata_b: scenario.ata(Ata::TakerB),
+            maker_ata_b: scenario.ata(Ata::MakerB),
+            instruction,
+            accounts,
+        }
+    }
+
+    // Replace the account at `index` in both the instruction and the account list
+    fn replace_account(&mut self, index: usize, key: Pubkey, account: Account) {
+        self.instruction.accounts[index].pubkey = key;
+        self.accounts[index] = (key, account);
+    }
+
+    // Replace the account data behind `index`, keeping its address
+    fn set_account(&mut self, index: usize, account: Account) {
+        self.accounts[index].1 = account;
+    }
+}
+
+fn custom(error: u32) -> ProgramResult {
+    ProgramResult::Failure(ProgramError::Custom(error))
+}
+
 #[test]
-#[ignore]
 fn test_take_instruction_not_enough_account_keys() {
-    // TODO: Test instruction with insufficient accounts (< 12 accounts)
-    todo!("Implement test for insufficient account keys");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10001);
+
+    // Drop the trailing account so only 11 are passed
+    fixture.instruction.accounts.truncate(11);
+    fixture.accounts.truncate(11);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::NotEnoughAccountKeys))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_invalid_signer() {
-    // TODO: Test taker account not marked as signer
-    todo!("Implement test for invalid signer");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10002);
+
+    fixture.instruction.accounts[TAKER].is_signer = false;
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::NotSigner as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_wrong_account_owners() {
-    // TODO: Test accounts with incorrect program owners
-    todo!("Implement test for wrong account owners");
+    let mollusk = setup_mollusk();
+
+    // Escrow data is valid but the account is owned by another program
+    let mut fixture = TakeFixture::new(10003);
+    let mut escrow_account = fixture.accounts[ESCROW].1.clone();
+    escrow_account.owner = Pubkey::new_unique();
+    fixture.set_account(ESCROW, escrow_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidOwner as u32))],
+    );
+
+    // Taker's token B account is not owned by the token program
+    let mut fixture = TakeFixture::new(10004);
+    let mut taker_ata_b_account = fixture.accounts[TAKER_ATA_B].1.clone();
+    taker_ata_b_account.owner = solana_system_program::id();
+    fixture.set_account(TAKER_ATA_B, taker_ata_b_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidOwner as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_invalid_mint_accounts() {
-    // TODO: Test non-mint accounts passed as mint_a or mint_b
-    todo!("Implement test for invalid mint accounts");
+    let mollusk = setup_mollusk();
+
+    // A token account passed as mint_a has the wrong data length
+    let mut fixture = TakeFixture::new(10005);
+    let not_a_mint = create_token_account(&fixture.taker, &fixture.mint_a, 0);
+    fixture.set_account(MINT_A, not_a_mint);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidAccountData as u32))],
+    );
+
+    // A system account passed as mint_b is not owned by a token program
+    let mut fixture = TakeFixture::new(10006);
+    fixture.set_account(MINT_B, Account::new(1_000_000, 0, &solana_system_program::id()));
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidOwner as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_mismatched_vault() {
-    // TODO: Test vault ATA not belonging to escrow PDA
-    todo!("Implement test for mismatched vault");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10007);
+
+    // The vault belongs to someone other than the escrow PDA
+    let other_owner = Pubkey::new_unique();
+    let wrong_vault = derive_associated_token_account(&other_owner, &fixture.mint_a);
+    let wrong_vault_account = create_token_account(&other_owner, &fixture.mint_a, DEPOSIT_AMOUNT);
+    fixture.replace_account(VAULT, wrong_vault, wrong_vault_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidAddress as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_wrong_token_program() {
-    // TODO: Test invalid token program account
-    todo!("Implement test for wrong token program");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10008);
+
+    let fake_token_program = Pubkey::new_unique();
+    fixture.replace_account(
+        TOKEN_PROGRAM,
+        fake_token_program,
+        Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111")),
+    );
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(EscrowError::UnsupportedTokenProgram as u32))],
+    );
 }
 
 // ============================================================================
@@ -350,38 +358,102 @@ fn test_take_instruction_wrong_token_program() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_invalid_escrow_data() {
-    // TODO: Test corrupted or malformed escrow account data
-    todo!("Implement test for invalid escrow data");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10009);
+
+    // Program owned, but too short to hold an Escrow
+    let mut escrow_account = fixture.accounts[ESCROW].1.clone();
+    escrow_account.data.truncate(50);
+    fixture.set_account(ESCROW, escrow_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidAccountData))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_escrow_not_initialized() {
-    // TODO: Test uninitialized escrow account
-    todo!("Implement test for uninitialized escrow");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10010);
+
+    // Allocated and owned by the program, but never populated
+    let escrow_len = fixture.accounts[ESCROW].1.data.len();
+    fixture.set_account(ESCROW, Account::new(10_000_000, escrow_len, &PROGRAM_ID));
+
+    // The stored maker (all zeroes) doesn't match the maker account
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(EscrowError::MakerMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_wrong_maker() {
-    // TODO: Test escrow maker doesn't match provided maker account
-    todo!("Implement test for wrong maker");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10011);
+
+    // Someone other than the escrow's maker is passed to collect the payment
+    let other_maker = Pubkey::new_unique();
+    let other_maker_ata_b = derive_associated_token_account(&other_maker, &fixture.mint_b);
+    fixture.replace_account(MAKER, other_maker, Account::new(10_000_000, 0, &solana_system_program::id()));
+    fixture.replace_account(
+        MAKER_ATA_B,
+        other_maker_ata_b,
+        create_token_account(&other_maker, &fixture.mint_b, 0),
+    );
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(EscrowError::MakerMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_wrong_mints() {
-    // TODO: Test escrow mint_a/mint_b don't match provided mints
-    todo!("Implement test for wrong mints");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10012);
+
+    // The taker tries to pay with a worthless mint instead of the escrow's mint_b
+    let fake_mint_b = Pubkey::new_unique();
+    let fake_taker_ata_b = derive_associated_token_account(&fixture.taker, &fake_mint_b);
+    let fake_maker_ata_b = derive_associated_token_account(&fixture.maker, &fake_mint_b);
+    fixture.replace_account(MINT_B, fake_mint_b, create_mint_account(&fixture.taker, 6));
+    fixture.replace_account(
+        TAKER_ATA_B,
+        fake_taker_ata_b,
+        create_token_account(&fixture.taker, &fake_mint_b, TAKER_BALANCE_B),
+    );
+    fixture.replace_account(
+        MAKER_ATA_B,
+        fake_maker_ata_b,
+        create_token_account(&fixture.maker, &fake_mint_b, 0),
+    );
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(EscrowError::MintMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_escrow_already_closed() {
-    // TODO: Test attempting to take from already closed escrow
-    todo!("Implement test for already closed escrow");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10013);
+
+    // Closed accounts have no lamports, no data and are owned by the system program
+    fixture.set_account(ESCROW, Account::default());
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidOwner as u32))],
+    );
 }
 
 // ============================================================================
@@ -389,24 +461,57 @@ fn test_take_instruction_escrow_already_closed() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_insufficient_taker_balance() {
-    // TODO: Test taker doesn't have enough mint_b tokens to pay
-    todo!("Implement test for insufficient taker balance");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10014);
+
+    let poor_taker_ata_b = create_token_account(&fixture.taker, &fixture.mint_b, RECEIVE_AMOUNT - 1);
+    fixture.set_account(TAKER_ATA_B, poor_taker_ata_b);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::InsufficientFunds as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_empty_vault() {
-    // TODO: Test vault has zero balance
-    todo!("Implement test for empty vault");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10015);
+
+    // The taker must not pay for an escrow that holds nothing
+    let empty_vault = create_token_account(&fixture.escrow, &fixture.mint_a, 0);
+    fixture.set_account(VAULT, empty_vault);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(EscrowError::VaultBalanceMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
-fn test_take_instruction_vault_balance_mismatch() {
-    // TODO: Test vault balance doesn't match expected escrow amount
-    todo!("Implement test for vault balance mismatch");
+fn test_take_instruction_vault_topped_up_after_make() {
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10016);
+
+    // Someone sent extra tokens to the vault after Make. The escrow doesn't
+    // record the deposit, so the taker receives the whole vault balance.
+    let vault_balance = DEPOSIT_AMOUNT + 500;
+    let topped_up_vault = create_token_account(&fixture.escrow, &fixture.mint_a, vault_balance);
+    fixture.set_account(VAULT, topped_up_vault);
+
+    let result = mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::success()],
+    );
+
+    let taker_ata_a_account = result.get_account(&fixture.taker_ata_a).unwrap();
+    assert_eq!(get_token_account_amount(taker_ata_a_account), vault_balance);
+    let maker_ata_b_account = result.get_account(&fixture.maker_ata_b).unwrap();
+    assert_eq!(get_token_account_amount(maker_ata_b_account), RECEIVE_AMOUNT);
 }
 
 // ============================================================================
@@ -414,24 +519,74 @@ fn test_take_instruction_vault_balance_mismatch() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_ata_creation_fails() {
-    // TODO: Test system program or token program unavailable for ATA creation
-    todo!("Implement test for ATA creation failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10017);
+
+    // taker_ata_a must be created, but a bogus system program is passed
+    fixture.instruction = with_associated_token_program(fixture.instruction);
+    fixture.set_account(TAKER_ATA_A, Account::default());
+    fixture.replace_account(SYSTEM_PROGRAM, Pubkey::new_unique(), Account::default());
+
+    // The Associated Token program can't find the system program to create
+    // the account with
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::instruction_err(InstructionError::MissingAccount)],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_insufficient_sol_for_ata() {
-    // TODO: Test taker doesn't have enough SOL to create ATAs
-    todo!("Implement test for insufficient SOL for ATA creation");
+    let mollusk = setup_mollusk();
+
+    // Both ATAs must be created, but the taker can't cover their rent
+    let mut fixture = TakeFixture::from_builder(
+        take_scenario(10018)
+            .wallet_lamports(100_000)
+            .missing(Ata::TakerA)
+            .missing(Ata::MakerB),
+    );
+    fixture.instruction = with_associated_token_program(fixture.instruction);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Custom(1)))], // SystemError::ResultWithNegativeLamports
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_wrong_ata_derivation() {
-    // TODO: Test manually provided ATAs don't match derived addresses
-    todo!("Implement test for wrong ATA derivation");
+    let mollusk = setup_mollusk();
+
+    // taker_ata_b is a token account owned by the taker, but not their ATA
+    let mut fixture = TakeFixture::new(10019);
+    let non_ata = Pubkey::new_unique();
+    let non_ata_account = create_token_account(&fixture.taker, &fixture.mint_b, TAKER_BALANCE_B);
+    fixture.replace_account(TAKER_ATA_B, non_ata, non_ata_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(PinocchioError::InvalidAddress as u32))],
+    );
+
+    // taker_ata_a is a token account owned by the taker, but not their ATA.
+    // It fails the ATA check, and the Associated Token program refuses to
+    // create an ATA at that address.
+    let mut fixture = TakeFixture::new(10020);
+    fixture.instruction = with_associated_token_program(fixture.instruction);
+    let non_ata = Pubkey::new_unique();
+    let non_ata_account = create_token_account(&fixture.taker, &fixture.mint_a, 0);
+    fixture.replace_account(TAKER_ATA_A, non_ata, non_ata_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidSeeds))],
+    );
 }
 
 // ============================================================================
@@ -439,24 +594,51 @@ fn test_take_instruction_wrong_ata_derivation() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_vault_transfer_fails() {
-    // TODO: Test unable to transfer from vault to taker (authority issues)
-    todo!("Implement test for vault transfer failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10021);
+
+    // The vault sits at the escrow's ATA address, but its token owner isn't the escrow
+    let hijacked_vault = create_token_account(&Pubkey::new_unique(), &fixture.mint_a, DEPOSIT_AMOUNT);
+    fixture.set_account(VAULT, hijacked_vault);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::OwnerMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_taker_transfer_fails() {
-    // TODO: Test unable to transfer from taker to maker (insufficient funds)
-    todo!("Implement test for taker transfer failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10022);
+
+    // taker_ata_b sits at the taker's ATA address, but the taker can't spend from it
+    let not_taker_owned = create_token_account(&Pubkey::new_unique(), &fixture.mint_b, TAKER_BALANCE_B);
+    fixture.set_account(TAKER_ATA_B, not_taker_owned);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::OwnerMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_token_program_cpi_fails() {
-    // TODO: Test token program calls fail due to various reasons
-    todo!("Implement test for token program CPI failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10023);
+
+    // The vault holds tokens of a different mint than taker_ata_a
+    let other_mint_vault = create_token_account(&fixture.escrow, &Pubkey::new_unique(), DEPOSIT_AMOUNT);
+    fixture.set_account(VAULT, other_mint_vault);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::MintMismatch as u32))],
+    );
 }
 
 // ============================================================================
@@ -464,24 +646,67 @@ fn test_take_instruction_token_program_cpi_fails() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_vault_close_fails() {
-    // TODO: Test unable to close vault account
-    todo!("Implement test for vault close failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10024);
+
+    // A close authority other than the escrow prevents closing the vault
+    let mut vault_account = fixture.accounts[VAULT].1.clone();
+    let mut vault_state = spl_token::state::Account::unpack(&vault_account.data).unwrap();
+    vault_state.close_authority = Some(Pubkey::new_unique()).into();
+    spl_token::state::Account::pack(vault_state, &mut vault_account.data).unwrap();
+    fixture.set_account(VAULT, vault_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::OwnerMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_escrow_close_fails() {
-    // TODO: Test unable to close escrow account
-    todo!("Implement test for escrow close failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10025);
+
+    // The escrow can't be closed when it isn't passed as writable
+    fixture.instruction.accounts[ESCROW].is_writable = false;
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::Immutable))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_wrong_rent_destination() {
-    // TODO: Test rent not properly returned to correct recipients
-    todo!("Implement test for wrong rent destination");
+    let mollusk = setup_mollusk();
+    let fixture = TakeFixture::new(10026);
+
+    let vault_lamports = fixture.accounts[VAULT].1.lamports;
+    let escrow_lamports = fixture.accounts[ESCROW].1.lamports;
+
+    // Vault rent goes to the maker, escrow rent goes to the taker
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[
+            Check::success(),
+            Check::account(&fixture.maker)
+                .lamports(10_000_000 + vault_lamports)
+                .build(),
+            Check::account(&fixture.taker)
+                .lamports(10_000_000 + escrow_lamports)
+                .build(),
+            Check::account(&fixture.vault)
+                .lamports(0)
+                .build(),
+            Check::account(&fixture.escrow)
+                .lamports(0)
+                .build(),
+        ],
+    );
 }
 
 // ============================================================================
@@ -489,31 +714,147 @@ fn test_take_instruction_wrong_rent_destination() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_zero_receive_amount() {
-    // TODO: Test escrow with zero receive amount (edge case)
-    todo!("Implement test for zero receive amount");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10027);
+
+    // An escrow asking for nothing in return is a free claim for the taker
+    let free_escrow = create_escrow_account(10027, &fixture.maker, &fixture.mint_a, &fixture.mint_b, 0, fixture.bump);
+    fixture.set_account(ESCROW, free_escrow);
+
+    let result = mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::success()],
+    );
+
+    let taker_ata_a_account = result.get_account(&fixture.taker_ata_a).unwrap();
+    assert_eq!(get_token_account_amount(taker_ata_a_account), DEPOSIT_AMOUNT);
+    let taker_ata_b_account = result.get_account(&fixture.taker_ata_b).unwrap();
+    assert_eq!(get_token_account_amount(taker_ata_b_account), TAKER_BALANCE_B);
+    let maker_ata_b_account = result.get_account(&fixture.maker_ata_b).unwrap();
+    assert_eq!(get_token_account_amount(maker_ata_b_account), 0);
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_same_maker_and_taker() {
-    // TODO: Test maker attempting to take their own escrow
-    todo!("Implement test for same maker and taker");
+    let mollusk = setup_mollusk();
+    let scenario = EscrowScenario::builder()
+        .seed(10028)
+        .open(RECEIVE_AMOUNT, DEPOSIT_AMOUNT)
+        .balance(Ata::MakerB, TAKER_BALANCE_B)
+        .build();
+
+    // The maker fills their own escrow, paying token B to themselves
+    let instruction = create_take_instruction(
+        &scenario.maker,
+        &scenario.maker,
+        &scenario.escrow,
+        &scenario.mint_a,
+        &scenario.mint_b,
+        &scenario.ata(Ata::Vault),
+        &scenario.ata(Ata::MakerA),
+        &scenario.ata(Ata::MakerB),
+        &scenario.ata(Ata::MakerB),
+    );
+
+    // This behaves like a Refund: the deposit comes back and token B is untouched
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[
+            Check::success(),
+            Check::account(&scenario.ata(Ata::Vault))
+                .lamports(0)
+                .build(),
+            Check::account(&scenario.escrow)
+                .lamports(0)
+                .build(),
+        ],
+    );
+
+    let accounts = &result.resulting_accounts;
+    assert_eq!(scenario.balance(accounts, Ata::MakerA), DEPOSIT_AMOUNT);
+    assert_eq!(scenario.balance(accounts, Ata::MakerB), TAKER_BALANCE_B);
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_same_token_types() {
-    // TODO: Test escrow where mint_a equals mint_b
-    todo!("Implement test for same token types");
+    let mollusk = setup_mollusk();
+
+    let seed = 10029u64;
+    let maker = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let mint = Pubkey::new_unique(); // Used as both mint_a and mint_b
+
+    let (escrow, bump) = derive_escrow_pda(&maker, seed);
+    let vault = derive_associated_token_account(&escrow, &mint);
+    let taker_ata = derive_associated_token_account(&taker, &mint);
+    let maker_ata = derive_associated_token_account(&maker, &mint);
+
+    let instruction = create_take_instruction(
+        &taker,
+        &maker,
+        &escrow,
+        &mint,
+        &mint,
+        &vault,
+        &taker_ata,
+        &taker_ata,
+        &maker_ata,
+    );
+
+    let accounts = vec![
+        (taker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (maker, Account::new(10_000_000, 0, &solana_system_program::id())),
+        (escrow, create_escrow_account(seed, &maker, &mint, &mint, RECEIVE_AMOUNT, bump)),
+        (mint, create_mint_account(&mint_authority, 9)),
+        (vault, create_token_account(&escrow, &mint, DEPOSIT_AMOUNT)),
+        (taker_ata, create_token_account(&taker, &mint, TAKER_BALANCE_B)),
+        (maker_ata, create_token_account(&maker, &mint, 0)),
+        keyed_account_for_system_program(),
+        keyed_config_account(),
+        keyed_denylist_entry_account(&taker),
+        keyed_maker_state_account(&maker),
+        (spl_token::ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+        (ATOKEN_PROGRAM_ID, Account::new(1_000_000, 0, &solana_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111"))),
+    ];
+
+    // Swapping a token for itself is allowed: the taker nets the difference
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::success()],
+    );
+
+    let taker_ata_account = result.get_account(&taker_ata).unwrap();
+    assert_eq!(get_token_account_amount(taker_ata_account), TAKER_BALANCE_B + DEPOSIT_AMOUNT - RECEIVE_AMOUNT);
+    let maker_ata_account = result.get_account(&maker_ata).unwrap();
+    assert_eq!(get_token_account_amount(maker_ata_account), RECEIVE_AMOUNT);
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_frozen_token_accounts() {
-    // TODO: Test attempting to transfer from/to frozen token accounts
-    todo!("Implement test for frozen token accounts");
+    let mollusk = setup_mollusk();
+
+    // The taker's payment account is frozen
+    let fixture = TakeFixture::from_builder(take_scenario(10030).frozen(Ata::TakerB));
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::AccountFrozen as u32))],
+    );
+
+    // The vault is frozen
+    let fixture = TakeFixture::from_builder(take_scenario(10031).frozen(Ata::Vault));
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(TokenError::AccountFrozen as u32))],
+    );
 }
 
 // ============================================================================
@@ -521,17 +862,34 @@ fn test_take_instruction_frozen_token_accounts() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_invalid_discriminator() {
-    // TODO: Test wrong instruction discriminator
-    todo!("Implement test for invalid discriminator");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10032);
+
+    // No instruction uses this discriminator
+    fixture.instruction.data = vec![255u8];
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidInstructionData))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_extra_instruction_data() {
-    // TODO: Test take instruction with unexpected additional data
-    todo!("Implement test for extra instruction data");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10033);
+
+    // Take's arguments are an optional referral fee and flags byte, so a
+    // single byte after the discriminator is neither
+    fixture.instruction.data = vec![1u8, 0xde];
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidInstructionData))],
+    );
 }
 
 // ============================================================================
@@ -539,22 +897,78 @@ fn test_take_instruction_extra_instruction_data() {
 // ============================================================================
 
 #[test]
-#[ignore]
 fn test_take_instruction_account_info_borrow_fails() {
-    // TODO: Test unable to borrow account data
-    todo!("Implement test for account info borrow failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10034);
+
+    // The vault is passed a second time in place of taker_ata_a. It isn't the
+    // taker's associated token account, so creating that fails before the
+    // aliased vault is ever borrowed twice.
+    fixture.instruction = with_associated_token_program(fixture.instruction);
+    fixture.instruction.accounts[TAKER_ATA_A].pubkey = fixture.vault;
+    fixture.accounts.remove(TAKER_ATA_A);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidSeeds))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_program_address_derivation_fails() {
-    // TODO: Test PDA derivation with invalid seeds
-    todo!("Implement test for PDA derivation failure");
+    let mollusk = setup_mollusk();
+    let mut fixture = TakeFixture::new(10035);
+
+    // Store a non-canonical bump that derives no address at all
+    let bad_bump = (0..fixture.bump)
+        .rev()
+        .find(|bump| Pubkey::create_program_address(&[b"escrow", fixture.maker.as_ref(), &10035u64.to_le_bytes(), &[*bump]], &PROGRAM_ID).is_err())
+        .unwrap();
+    let escrow_account = create_escrow_account(10035, &fixture.maker, &fixture.mint_a, &fixture.mint_b, RECEIVE_AMOUNT, bad_bump);
+    fixture.set_account(ESCROW, escrow_account);
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::program_result(custom(EscrowError::EscrowPdaMismatch as u32))],
+    );
 }
 
 #[test]
-#[ignore]
 fn test_take_instruction_cpi_depth_exceeded() {
-    // TODO: Test too many cross-program invocations
-    todo!("Implement test for CPI depth exceeded");
-}
\ No newline at end of file
+    let mut mollusk = setup_mollusk();
+    let fixture = TakeFixture::new(10036);
+
+    // Allow only the top-level instruction, so the first CPI fails
+    mollusk.compute_budget.max_instruction_stack_depth = 1;
+
+    mollusk.process_and_validate_instruction(
+        &fixture.instruction,
+        &fixture.accounts,
+        &[Check::instruction_err(InstructionError::CallDepth)],
+    );
+}
+
+#[test]
+fn test_take_instruction_escrows_of_earlier_layouts() {
+    let mollusk = setup_mollusk();
+
+    // Escrows made before the layout grew, down to the original 113 bytes,
+    // can still be taken, with the fields they lack read as none
+    for len in Escrow::LAYOUT_LENS {
+        let mut scenario = EscrowScenario::builder()
+            .open(2000, 1500)
+            .balance(Ata::TakerB, 3000)
+            .build();
+        let mut escrow_account = scenario.accounts[&scenario.escrow].clone();
+        escrow_account.data.truncate(len);
+        scenario.set_account(scenario.escrow, escrow_account);
+
+        let instruction = scenario.take_instruction();
+        let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+        assert_eq!(scenario.balance(&result.resulting_accounts, Ata::TakerA), 1500);
+        assert_eq!(scenario.balance(&result.resulting_accounts, Ata::MakerB), 2000);
+        assert_eq!(scenario.escrow_state(&result.resulting_accounts), None);
+    }
+}
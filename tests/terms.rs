@@ -0,0 +1,61 @@
+//! Off-chain terms: Make binds an escrow to an agreement's hash and the
+//! maker's external reference, which clients check documents against.
+
+use {
+    crate::{helpers::*, scenario::{Ata, EscrowScenario}},
+    blueshift_escrow::Escrow,
+    mollusk_svm::result::{Check, ProgramResult},
+    spl_token::solana_program::program_error::ProgramError,
+};
+
+const DOCUMENT: &[u8] = b"Sale of 400 A for 750 B, delivery on settlement";
+const EXTERNAL_REF: [u8; 16] = *b"INV-2026-000042\0";
+
+#[test]
+fn test_make_records_terms() {
+    let mollusk = setup_mollusk();
+    let scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let terms_hash = Escrow::hash_terms(DOCUMENT);
+    let instruction = with_associated_token_program(with_terms(scenario.make_instruction(750, 400), &terms_hash, &EXTERNAL_REF));
+
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+    let state = scenario.escrow_state(&result.resulting_accounts).unwrap();
+    assert_eq!((state.terms_hash, state.external_ref), (terms_hash, EXTERNAL_REF));
+
+    // Clients check the document they were given against the escrow
+    let (_, account) = result.resulting_accounts.iter().find(|(key, _)| *key == scenario.escrow).unwrap();
+    let escrow = Escrow::load(&account.data).unwrap();
+    assert!(escrow.terms_match(DOCUMENT));
+    assert!(!escrow.terms_match(b"Sale of 400 A for 1 B, delivery on settlement"));
+}
+
+#[test]
+fn test_make_without_terms_leaves_them_zero() {
+    let mollusk = setup_mollusk();
+    let scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let instruction = with_associated_token_program(with_max_referral_bps(scenario.make_instruction(750, 400), 100));
+
+    let result = mollusk.process_and_validate_instruction(&instruction, &scenario.accounts_for(&instruction), &[Check::success()]);
+    let state = scenario.escrow_state(&result.resulting_accounts).unwrap();
+    assert_eq!((state.max_referral_bps, state.terms_hash, state.external_ref), (100, [0; 32], [0; 16]));
+}
+
+#[test]
+fn test_make_rejects_partial_terms() {
+    let mollusk = setup_mollusk();
+    let scenario = EscrowScenario::builder()
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let mut instruction = with_associated_token_program(with_terms(scenario.make_instruction(750, 400), &[7; 32], &EXTERNAL_REF));
+    instruction.data.pop();
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::program_result(ProgramResult::Failure(ProgramError::InvalidInstructionData))],
+    );
+}
@@ -0,0 +1,529 @@
+//! Make, Take and Refund under both token programs, plus Token-2022 mint and
+//! account extension fixtures.
+//!
+//! Make rejects mints with a permanent delegate, non-transferable mints and
+//! mints whose default account state is frozen, unless the maker sets
+//! `ALLOW_UNSAFE_MINT_EXTENSIONS`. The fixtures document how the escrow
+//! behaves with each extension once accepted:
+//!
+//! - Transfer fee on mint A: Make works and the fee is withheld on the vault.
+//!   Take and Refund harvest it to the mint before closing the vault, so the
+//!   mint must be passed writable.
+//! - Transfer fee on mint B: Take works and the maker receives `receive`
+//!   minus the fee.
+//! - Non-transferable: Make (mint A) or Take (mint B) fails with
+//!   `NonTransferable`.
+//! - Default account state frozen: ATAs the program creates start frozen and
+//!   the transfer into them fails with `AccountFrozen`.
+//! - Permanent delegate: everything works, but the delegate can drain the
+//!   vault.
+//! - Memo required on a destination: Take and Refund log a memo through the
+//!   Memo program, passed as an optional last account, before transferring.
+//!   Without it they fail with `MemoProgramMissing`.
+//! - CPI Guard on the maker's or taker's paying account: Make and Take fail
+//!   early with `CpiGuardEnabled`. Guarded receiving accounts work.
+//! - Mint close authority: everything works.
+
+use {
+    crate::{
+        helpers::*,
+        scenario::{Ata, EscrowScenario, EscrowScenarioBuilder, MintExtension, Side},
+    },
+    blueshift_escrow::{EscrowError, PinocchioError},
+    mollusk_svm::{
+        result::{Check, ProgramResult},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    spl_token::solana_program::program_error::ProgramError,
+    spl_token_2022::error::TokenError,
+};
+
+const TOKEN_PROGRAMS: [Pubkey; 2] = [spl_token::ID, spl_token_2022::ID];
+
+fn token_error(error: TokenError) -> ProgramResult {
+    ProgramResult::Failure(ProgramError::Custom(error as u32))
+}
+
+fn make_allowing_unsafe_extensions(mollusk: &Mollusk, scenario: &mut EscrowScenario, receive: u64, amount: u64, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let instruction = with_associated_token_program(allow_unsafe_mint_extensions(scenario.make_instruction(receive, amount)));
+    run(mollusk, scenario, instruction, expected)
+}
+
+fn make(mollusk: &Mollusk, scenario: &mut EscrowScenario, receive: u64, amount: u64, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let instruction = with_associated_token_program(scenario.make_instruction(receive, amount));
+    run(mollusk, scenario, instruction, expected)
+}
+
+fn take(mollusk: &Mollusk, scenario: &mut EscrowScenario, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let instruction = with_associated_token_program(scenario.take_instruction());
+    run(mollusk, scenario, instruction, expected)
+}
+
+fn refund(mollusk: &Mollusk, scenario: &mut EscrowScenario, expected: ProgramResult) -> Vec<(Pubkey, Account)> {
+    let instruction = with_associated_token_program(scenario.refund_instruction());
+    run(mollusk, scenario, instruction, expected)
+}
+
+// ============================================================================
+// BOTH TOKEN PROGRAMS
+// ============================================================================
+
+#[test]
+fn test_make_with_both_token_programs() {
+    let mollusk = setup_mollusk();
+
+    for token_program in TOKEN_PROGRAMS {
+        for vault_exists in [false, true] {
+            let mut builder = EscrowScenario::builder().token_program(token_program).balance(Ata::MakerA, 1_000);
+            if vault_exists {
+                builder = builder.balance(Ata::Vault, 0);
+            }
+            let mut scenario = builder.build();
+
+            let accounts = make(&mollusk, &mut scenario, 750, 400, ProgramResult::Success);
+
+            let escrow = scenario.escrow_state(&accounts).unwrap();
+            assert_eq!(escrow.receive, 750, "{token_program}");
+            assert_eq!(scenario.balance(&accounts, Ata::Vault), 400, "{token_program}");
+            assert_eq!(scenario.balance(&accounts, Ata::MakerA), 600, "{token_program}");
+        }
+    }
+}
+
+#[test]
+fn test_take_with_both_token_programs() {
+    let mollusk = setup_mollusk();
+
+    for token_program in TOKEN_PROGRAMS {
+        for atas_exist in [true, false] {
+            let mut builder = EscrowScenario::builder()
+                .token_program(token_program)
+                .open(2_000, 1_500)
+                .balance(Ata::TakerB, 3_000);
+            if !atas_exist {
+                builder = builder.missing(Ata::TakerA).missing(Ata::MakerB);
+            }
+            let mut scenario = builder.build();
+
+            let accounts = take(&mollusk, &mut scenario, ProgramResult::Success);
+
+            assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_500, "{token_program}");
+            assert_eq!(scenario.balance(&accounts, Ata::TakerB), 1_000, "{token_program}");
+            assert_eq!(scenario.balance(&accounts, Ata::MakerB), 2_000, "{token_program}");
+            assert!(scenario.token_account(&accounts, Ata::Vault).is_none(), "{token_program}");
+            assert!(scenario.escrow_state(&accounts).is_none(), "{token_program}");
+        }
+    }
+}
+
+#[test]
+fn test_refund_with_both_token_programs() {
+    let mollusk = setup_mollusk();
+
+    for token_program in TOKEN_PROGRAMS {
+        for ata_exists in [true, false] {
+            let mut builder = EscrowScenario::builder().token_program(token_program).open(2_000, 1_500);
+            if !ata_exists {
+                builder = builder.missing(Ata::MakerA);
+            }
+            let mut scenario = builder.build();
+
+            let accounts = refund(&mollusk, &mut scenario, ProgramResult::Success);
+
+            assert_eq!(scenario.balance(&accounts, Ata::MakerA), 1_500, "{token_program}");
+            assert!(scenario.token_account(&accounts, Ata::Vault).is_none(), "{token_program}");
+            assert!(scenario.escrow_state(&accounts).is_none(), "{token_program}");
+        }
+    }
+}
+
+#[test]
+fn test_make_take_round_trip_with_both_token_programs() {
+    let mollusk = setup_mollusk();
+
+    for token_program in TOKEN_PROGRAMS {
+        let mut scenario = EscrowScenario::builder()
+            .token_program(token_program)
+            .balance(Ata::MakerA, 1_000)
+            .balance(Ata::TakerB, 500)
+            .missing(Ata::TakerA)
+            .missing(Ata::MakerB)
+            .build();
+
+        make(&mollusk, &mut scenario, 500, 1_000, ProgramResult::Success);
+        let accounts = take(&mollusk, &mut scenario, ProgramResult::Success);
+
+        assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_000, "{token_program}");
+        assert_eq!(scenario.balance(&accounts, Ata::MakerB), 500, "{token_program}");
+    }
+}
+
+#[test]
+fn test_insufficient_balances_with_both_token_programs() {
+    let mollusk = setup_mollusk();
+
+    for token_program in TOKEN_PROGRAMS {
+        let mut scenario = EscrowScenario::builder().token_program(token_program).balance(Ata::MakerA, 100).build();
+        make(&mollusk, &mut scenario, 750, 400, token_error(TokenError::InsufficientFunds));
+
+        let mut scenario = EscrowScenario::builder()
+            .token_program(token_program)
+            .open(2_000, 1_500)
+            .balance(Ata::TakerB, 1_999)
+            .build();
+        take(&mollusk, &mut scenario, token_error(TokenError::InsufficientFunds));
+    }
+}
+
+#[test]
+fn test_frozen_accounts_with_both_token_programs() {
+    let mollusk = setup_mollusk();
+
+    for token_program in TOKEN_PROGRAMS {
+        let frozen = |ata: Ata| -> EscrowScenarioBuilder {
+            EscrowScenario::builder()
+                .token_program(token_program)
+                .open(2_000, 1_500)
+                .balance(Ata::TakerB, 3_000)
+                .frozen(ata)
+        };
+
+        take(&mollusk, &mut frozen(Ata::Vault).build(), token_error(TokenError::AccountFrozen));
+        take(&mollusk, &mut frozen(Ata::TakerB).build(), token_error(TokenError::AccountFrozen));
+        take(&mollusk, &mut frozen(Ata::MakerB).build(), token_error(TokenError::AccountFrozen));
+        refund(&mollusk, &mut frozen(Ata::MakerA).build(), token_error(TokenError::AccountFrozen));
+    }
+}
+
+#[test]
+fn test_mints_of_the_other_token_program_are_rejected() {
+    let mollusk = setup_mollusk();
+    let invalid_owner = ProgramResult::Failure(ProgramError::Custom(PinocchioError::InvalidOwner as u32));
+
+    for (accounts_program, passed_program) in [(spl_token_2022::ID, spl_token::ID), (spl_token::ID, spl_token_2022::ID)] {
+        let mut scenario = EscrowScenario::builder()
+            .token_program(accounts_program)
+            .open(2_000, 1_500)
+            .balance(Ata::MakerA, 1_000)
+            .balance(Ata::TakerB, 3_000)
+            .build();
+        let swap_program = |mut instruction: Instruction| {
+            for meta in &mut instruction.accounts {
+                if meta.pubkey == accounts_program {
+                    meta.pubkey = passed_program;
+                }
+            }
+            instruction
+        };
+
+        let instruction = swap_program(with_associated_token_program(scenario.take_instruction()));
+        run(&mollusk, &mut scenario, instruction, invalid_owner.clone());
+        let instruction = swap_program(with_associated_token_program(scenario.refund_instruction()));
+        run(&mollusk, &mut scenario, instruction, invalid_owner.clone());
+    }
+}
+
+// ============================================================================
+// TOKEN-2022 EXTENSIONS
+// ============================================================================
+
+#[test]
+fn test_make_rejects_unsafe_mint_extensions() {
+    let mollusk = setup_mollusk();
+    let unsafe_mint_extension = ProgramResult::Failure(ProgramError::Custom(EscrowError::UnsafeMintExtension as u32));
+
+    for side in [Side::A, Side::B] {
+        for extension in [
+            MintExtension::PermanentDelegate(Pubkey::new_unique()),
+            MintExtension::NonTransferable,
+            MintExtension::DefaultAccountStateFrozen,
+        ] {
+            let mut scenario = EscrowScenario::builder()
+                .mint_extension(side, extension)
+                .balance(Ata::MakerA, 1_000)
+                .build();
+            let accounts = make(&mollusk, &mut scenario, 500, 1_000, unsafe_mint_extension.clone());
+            assert!(scenario.escrow_state(&accounts).is_none(), "{side:?} {extension:?}");
+        }
+    }
+}
+
+#[test]
+fn test_make_accepts_unsafe_mint_extensions_when_allowed() {
+    let mollusk = setup_mollusk();
+
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::B, MintExtension::PermanentDelegate(Pubkey::new_unique()))
+        .mint_extension(Side::B, MintExtension::NonTransferable)
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    let accounts = make_allowing_unsafe_extensions(&mollusk, &mut scenario, 500, 1_000, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::Vault), 1_000);
+}
+
+#[test]
+fn test_make_rejects_unknown_flags() {
+    let mollusk = setup_mollusk();
+
+    let mut scenario = EscrowScenario::builder().token_2022().balance(Ata::MakerA, 1_000).build();
+    let mut instruction = with_associated_token_program(scenario.make_instruction(500, 1_000));
+    instruction.data.push(0b10);
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::InvalidInstructionData));
+}
+
+#[test]
+fn test_transfer_fee_on_mint_a_is_harvested_before_the_vault_closes() {
+    let mollusk = setup_mollusk();
+    let fee = MintExtension::TransferFee { basis_points: 100, maximum_fee: 1_000_000 };
+    let fee_scenario = |mollusk: &Mollusk| {
+        let mut scenario = EscrowScenario::builder()
+            .mint_extension(Side::A, fee)
+            .balance(Ata::MakerA, 1_000)
+            .balance(Ata::TakerB, 500)
+            .build();
+
+        // The 1% fee is withheld on the vault, which receives the rest
+        let accounts = make(mollusk, &mut scenario, 500, 1_000, ProgramResult::Success);
+        assert_eq!(scenario.balance(&accounts, Ata::Vault), 990);
+        assert_eq!(scenario.balance(&accounts, Ata::MakerA), 0);
+        scenario
+    };
+
+    // Take and Refund harvest the withheld fee to the mint, which needs no
+    // authority, then close the vault. The transfer out pays the fee again.
+    let mut scenario = fee_scenario(&mollusk);
+    let instruction = with_writable_mint(with_associated_token_program(scenario.take_instruction()), &scenario.mint_a);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 980);
+    assert!(scenario.token_account(&accounts, Ata::Vault).is_none());
+
+    let mut scenario = fee_scenario(&mollusk);
+    let instruction = with_writable_mint(with_associated_token_program(scenario.refund_instruction()), &scenario.mint_a);
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 980);
+    assert!(scenario.token_account(&accounts, Ata::Vault).is_none());
+}
+
+#[test]
+fn test_transfer_fee_on_mint_b_is_paid_by_the_maker() {
+    let mollusk = setup_mollusk();
+    let fee = MintExtension::TransferFee { basis_points: 100, maximum_fee: 1_000_000 };
+
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::B, fee)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+
+    let accounts = take(&mollusk, &mut scenario, ProgramResult::Success);
+
+    // The taker pays `receive` and the maker gets it minus the fee
+    assert_eq!(scenario.balance(&accounts, Ata::TakerB), 0);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 1_980);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_500);
+}
+
+#[test]
+fn test_non_transferable_mints_are_rejected() {
+    let mollusk = setup_mollusk();
+
+    // The deposit can't leave the maker's account
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::A, MintExtension::NonTransferable)
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    make_allowing_unsafe_extensions(&mollusk, &mut scenario, 500, 1_000, token_error(TokenError::NonTransferable));
+
+    // The payment can't leave the taker's account
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::B, MintExtension::NonTransferable)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+    take(&mollusk, &mut scenario, token_error(TokenError::NonTransferable));
+    refund(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_default_account_state_frozen_rejects_new_accounts() {
+    let mollusk = setup_mollusk();
+
+    // The vault Make creates starts frozen
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::A, MintExtension::DefaultAccountStateFrozen)
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    make_allowing_unsafe_extensions(&mollusk, &mut scenario, 500, 1_000, token_error(TokenError::AccountFrozen));
+
+    // So does the maker's token B account Take creates
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::B, MintExtension::DefaultAccountStateFrozen)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .missing(Ata::MakerB)
+        .build();
+    take(&mollusk, &mut scenario, token_error(TokenError::AccountFrozen));
+
+    // Accounts the freeze authority already thawed work as usual
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::B, MintExtension::DefaultAccountStateFrozen)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+    take(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_permanent_delegate_can_drain_the_vault() {
+    let mollusk = setup_mollusk();
+    let delegate = Pubkey::new_unique();
+
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::A, MintExtension::PermanentDelegate(delegate))
+        .balance(Ata::MakerA, 1_000)
+        .build();
+    make_allowing_unsafe_extensions(&mollusk, &mut scenario, 500, 1_000, ProgramResult::Success);
+
+    // The escrow accepts the mint, but the delegate can move the deposit
+    // without the maker or the escrow signing
+    let destination = scenario.ata(Ata::MakerA);
+    let instruction = spl_token_2022::instruction::transfer_checked(
+        &spl_token_2022::ID,
+        &scenario.ata(Ata::Vault),
+        &scenario.mint_a,
+        &destination,
+        &delegate,
+        &[],
+        1_000,
+        9,
+    )
+    .unwrap();
+    scenario.set_account(delegate, Account::new(1_000_000, 0, &solana_system_program::id()));
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &scenario.accounts_for(&instruction),
+        &[Check::success()],
+    );
+    scenario.apply(&result.resulting_accounts);
+    assert_eq!(scenario.balance(&result.resulting_accounts, Ata::Vault), 0);
+
+    // The emptied escrow can still be refunded
+    refund(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_memo_required_destinations_need_the_memo_program() {
+    let mollusk = setup_mollusk();
+    let memo_program_missing = ProgramResult::Failure(ProgramError::Custom(EscrowError::MemoProgramMissing as u32));
+
+    for destination in [Ata::TakerA, Ata::MakerB] {
+        let mut scenario = EscrowScenario::builder()
+            .memo_required(destination)
+            .open(2_000, 1_500)
+            .balance(Ata::TakerB, 2_000)
+            .build();
+        take(&mollusk, &mut scenario, memo_program_missing.clone());
+    }
+
+    let mut scenario = EscrowScenario::builder().memo_required(Ata::MakerA).open(2_000, 1_500).build();
+    refund(&mollusk, &mut scenario, memo_program_missing);
+}
+
+#[test]
+fn test_memo_required_destinations_get_a_memo() {
+    let mollusk = setup_mollusk();
+
+    let mut scenario = EscrowScenario::builder()
+        .memo_required(Ata::TakerA)
+        .memo_required(Ata::MakerB)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+    let instruction = with_memo_program(with_associated_token_program(scenario.take_instruction()));
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_500);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 2_000);
+
+    let mut scenario = EscrowScenario::builder().memo_required(Ata::MakerA).open(2_000, 1_500).build();
+    let instruction = with_memo_program(with_associated_token_program(scenario.refund_instruction()));
+    let accounts = run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerA), 1_500);
+}
+
+#[test]
+fn test_memo_program_is_only_checked_when_a_memo_is_required() {
+    let mollusk = setup_mollusk();
+    let with_fake_memo_program = |mut instruction: Instruction| {
+        instruction.accounts.push(solana_instruction::AccountMeta::new_readonly(Pubkey::new_unique(), false));
+        instruction
+    };
+
+    let mut scenario = EscrowScenario::builder()
+        .memo_required(Ata::MakerB)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+    let instruction = with_fake_memo_program(with_associated_token_program(scenario.take_instruction()));
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Failure(ProgramError::IncorrectProgramId));
+
+    // Without memo-required destinations the trailing account is ignored
+    let mut scenario = EscrowScenario::builder().token_2022().open(2_000, 1_500).balance(Ata::TakerB, 2_000).build();
+    let instruction = with_fake_memo_program(with_associated_token_program(scenario.take_instruction()));
+    run(&mollusk, &mut scenario, instruction, ProgramResult::Success);
+}
+
+#[test]
+fn test_cpi_guard_on_paying_accounts_is_rejected() {
+    let mollusk = setup_mollusk();
+    let cpi_guard_enabled = ProgramResult::Failure(ProgramError::Custom(EscrowError::CpiGuardEnabled as u32));
+
+    // The maker's deposit and the taker's payment are transfers the owner
+    // signs through the escrow's CPI, which CPI Guard blocks
+    let mut scenario = EscrowScenario::builder().cpi_guard(Ata::MakerA).balance(Ata::MakerA, 1_000).build();
+    make(&mollusk, &mut scenario, 500, 1_000, cpi_guard_enabled.clone());
+
+    let mut scenario = EscrowScenario::builder()
+        .cpi_guard(Ata::TakerB)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+    take(&mollusk, &mut scenario, cpi_guard_enabled);
+}
+
+#[test]
+fn test_cpi_guard_on_receiving_accounts_is_accepted() {
+    let mollusk = setup_mollusk();
+
+    let mut scenario = EscrowScenario::builder()
+        .cpi_guard(Ata::TakerA)
+        .cpi_guard(Ata::MakerB)
+        .open(2_000, 1_500)
+        .balance(Ata::TakerB, 2_000)
+        .build();
+    take(&mollusk, &mut scenario, ProgramResult::Success);
+
+    let mut scenario = EscrowScenario::builder().cpi_guard(Ata::MakerA).open(2_000, 1_500).build();
+    refund(&mollusk, &mut scenario, ProgramResult::Success);
+}
+
+#[test]
+fn test_mint_close_authority_is_accepted() {
+    let mollusk = setup_mollusk();
+    let authority = Pubkey::new_unique();
+
+    let mut scenario = EscrowScenario::builder()
+        .mint_extension(Side::A, MintExtension::MintCloseAuthority(authority))
+        .mint_extension(Side::B, MintExtension::MintCloseAuthority(authority))
+        .balance(Ata::MakerA, 1_000)
+        .balance(Ata::TakerB, 500)
+        .build();
+
+    make(&mollusk, &mut scenario, 500, 1_000, ProgramResult::Success);
+    let accounts = take(&mollusk, &mut scenario, ProgramResult::Success);
+
+    assert_eq!(scenario.balance(&accounts, Ata::TakerA), 1_000);
+    assert_eq!(scenario.balance(&accounts, Ata::MakerB), 500);
+}
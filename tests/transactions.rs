@@ -0,0 +1,326 @@
+//! Multi-instruction transaction tests on LiteSVM.
+//!
+//! Mollusk runs one instruction at a time, so flows that span several
+//! instructions or slots live here. Each test loads the same program and
+//! token ELFs as `setup_mollusk`, signs real transactions and checks that
+//! they either apply completely or not at all.
+
+use {
+    crate::helpers::*,
+    base64::{engine::general_purpose::STANDARD, Engine},
+    blueshift_escrow::{events::{MakeEvent, TakeEvent}, Escrow},
+    litesvm::LiteSVM,
+    solana_account::Account,
+    solana_clock::Clock,
+    solana_instruction::Instruction,
+    solana_keypair::Keypair,
+    solana_pubkey::Pubkey,
+    solana_signer::Signer,
+    solana_transaction::Transaction,
+};
+
+const MAKER_BALANCE_A: u64 = 10_000;
+const TAKER_BALANCE_B: u64 = 10_000;
+
+struct Env {
+    svm: LiteSVM,
+    maker: Keypair,
+    taker: Keypair,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+}
+
+impl Env {
+    fn new() -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(PROGRAM_ID, "../target/deploy/blueshift_escrow.so").unwrap();
+        svm.add_program_from_file(spl_token::ID, "elf/token.so").unwrap();
+        svm.add_program_from_file(ATOKEN_PROGRAM_ID, "elf/associated_token.so").unwrap();
+
+        let maker = Keypair::new();
+        let taker = Keypair::new();
+        let mint_authority = Pubkey::new_unique();
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+
+        svm.airdrop(&maker.pubkey(), 1_000_000_000).unwrap();
+        svm.airdrop(&taker.pubkey(), 1_000_000_000).unwrap();
+        svm.set_account(mint_a, create_mint_account(&mint_authority, 9)).unwrap();
+        svm.set_account(mint_b, create_mint_account(&mint_authority, 6)).unwrap();
+        svm.set_account(
+            derive_associated_token_account(&maker.pubkey(), &mint_a),
+            create_token_account(&maker.pubkey(), &mint_a, MAKER_BALANCE_A),
+        )
+        .unwrap();
+        svm.set_account(
+            derive_associated_token_account(&taker.pubkey(), &mint_b),
+            create_token_account(&taker.pubkey(), &mint_b, TAKER_BALANCE_B),
+        )
+        .unwrap();
+
+        Self { svm, maker, taker, mint_a, mint_b }
+    }
+
+    fn escrow(&self, seed: u64) -> Pubkey {
+        derive_escrow_pda(&self.maker.pubkey(), seed).0
+    }
+
+    fn make(&self, seed: u64, receive: u64, amount: u64) -> Instruction {
+        let maker = self.maker.pubkey();
+        let escrow = self.escrow(seed);
+        with_associated_token_program(create_make_instruction(
+            &maker,
+            &escrow,
+            &self.mint_a,
+            &self.mint_b,
+            &derive_associated_token_account(&maker, &self.mint_a),
+            &derive_associated_token_account(&escrow, &self.mint_a),
+            seed,
+            receive,
+            amount,
+        ))
+    }
+
+    fn take(&self, seed: u64) -> Instruction {
+        let (maker, taker) = (self.maker.pubkey(), self.taker.pubkey());
+        let escrow = self.escrow(seed);
+        with_associated_token_program(create_take_instruction(
+            &taker,
+            &maker,
+            &escrow,
+            &self.mint_a,
+            &self.mint_b,
+            &derive_associated_token_account(&escrow, &self.mint_a),
+            &derive_associated_token_account(&taker, &self.mint_a),
+            &derive_associated_token_account(&taker, &self.mint_b),
+            &derive_associated_token_account(&maker, &self.mint_b),
+        ))
+    }
+
+    fn refund(&self, seed: u64) -> Instruction {
+        let maker = self.maker.pubkey();
+        let escrow = self.escrow(seed);
+        with_associated_token_program(create_refund_instruction(
+            &maker,
+            &escrow,
+            &self.mint_a,
+            &derive_associated_token_account(&escrow, &self.mint_a),
+            &derive_associated_token_account(&maker, &self.mint_a),
+        ))
+    }
+
+    // Sends the instructions as one transaction paid by the first signer
+    fn send(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> bool {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signers[0].pubkey()),
+            signers,
+            self.svm.latest_blockhash(),
+        );
+        self.svm.send_transaction(transaction).is_ok()
+    }
+
+    // Like `send`, returning the decoded `sol_log_data` events on success
+    fn send_for_events(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Option<Vec<Vec<Vec<u8>>>> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signers[0].pubkey()),
+            signers,
+            self.svm.latest_blockhash(),
+        );
+        let meta = self.svm.send_transaction(transaction).ok()?;
+        let events = meta.logs.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .map(|data| data.split(' ').map(|field| STANDARD.decode(field).unwrap()).collect())
+            .collect();
+        Some(events)
+    }
+
+    fn account(&self, key: &Pubkey) -> Option<Account> {
+        self.svm.get_account(key).filter(|account| account.lamports > 0)
+    }
+
+    fn balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.account(&derive_associated_token_account(owner, mint))
+            .map(|account| get_token_account_amount(&account))
+            .unwrap_or_default()
+    }
+
+    fn vault_balance(&self, seed: u64) -> u64 {
+        self.balance(&self.escrow(seed), &self.mint_a)
+    }
+
+    // Moves to a later slot with a fresh blockhash, as a real cluster would
+    fn advance(&mut self, slots: u64) {
+        let slot = self.svm.get_sysvar::<Clock>().slot;
+        self.svm.warp_to_slot(slot + slots);
+        self.svm.expire_blockhash();
+    }
+}
+
+#[test]
+fn test_make_and_take_in_one_transaction() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+
+    let instructions = [env.make(1, 2_000, 1_500), env.take(1)];
+    assert!(env.send(&instructions, &[&maker, &taker]));
+
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_a), MAKER_BALANCE_A - 1_500);
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_a), 1_500);
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_b), TAKER_BALANCE_B - 2_000);
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_b), 2_000);
+    assert!(env.account(&env.escrow(1)).is_none());
+    assert!(env.account(&derive_associated_token_account(&env.escrow(1), &env.mint_a)).is_none());
+}
+
+#[test]
+fn test_failed_take_rolls_back_make_in_same_transaction() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+
+    // The taker cannot pay, so the Make before it must not persist either
+    let instructions = [env.make(1, TAKER_BALANCE_B + 1, 1_500), env.take(1)];
+    assert!(!env.send(&instructions, &[&maker, &taker]));
+
+    assert!(env.account(&env.escrow(1)).is_none());
+    assert_eq!(env.vault_balance(1), 0);
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_a), MAKER_BALANCE_A);
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_b), TAKER_BALANCE_B);
+}
+
+#[test]
+fn test_take_failing_halfway_rolls_back_vault_transfer() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+    assert!(env.send(&[env.make(1, TAKER_BALANCE_B + 1, 1_500)], &[&maker]));
+    env.advance(1);
+
+    // Take moves token A out of the vault before it fails to collect token B
+    assert!(!env.send(&[env.take(1)], &[&taker]));
+
+    assert_eq!(env.vault_balance(1), 1_500);
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_a), 0);
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_b), TAKER_BALANCE_B);
+    let escrow = env.account(&env.escrow(1)).expect("escrow should still exist");
+    assert_eq!({ Escrow::load(&escrow.data).unwrap().receive }, TAKER_BALANCE_B + 1);
+}
+
+#[test]
+fn test_make_then_refund_in_one_transaction_is_a_no_op() {
+    let mut env = Env::new();
+    let maker = env.maker.insecure_clone();
+    let lamports = env.account(&maker.pubkey()).unwrap().lamports;
+
+    let instructions = [env.make(1, 2_000, 1_500), env.refund(1)];
+    assert!(env.send(&instructions, &[&maker]));
+
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_a), MAKER_BALANCE_A);
+    assert!(env.account(&env.escrow(1)).is_none());
+    // Only the transaction fee is gone, all rent comes back
+    assert_eq!(env.account(&maker.pubkey()).unwrap().lamports, lamports - 5_000);
+}
+
+#[test]
+fn test_refund_then_take_in_one_transaction_fails_atomically() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+    assert!(env.send(&[env.make(1, 2_000, 1_500)], &[&maker]));
+    env.advance(1);
+
+    let instructions = [env.refund(1), env.take(1)];
+    assert!(!env.send(&instructions, &[&maker, &taker]));
+
+    assert_eq!(env.vault_balance(1), 1_500);
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_a), MAKER_BALANCE_A - 1_500);
+    assert!(env.account(&env.escrow(1)).is_some());
+}
+
+#[test]
+fn test_several_escrows_share_one_maker() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+
+    let instructions = [env.make(1, 1_000, 100), env.make(2, 2_000, 200), env.make(3, 3_000, 300)];
+    assert!(env.send(&instructions, &[&maker]));
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_a), MAKER_BALANCE_A - 600);
+    for (seed, amount) in [(1, 100), (2, 200), (3, 300)] {
+        assert_eq!(env.vault_balance(seed), amount);
+        let escrow = env.account(&env.escrow(seed)).unwrap();
+        let escrow = Escrow::load(&escrow.data).unwrap();
+        assert_eq!({ escrow.seed }, seed);
+        assert_eq!(escrow.maker, maker.pubkey().to_bytes());
+    }
+    env.advance(1);
+
+    // Settling one escrow leaves the others untouched
+    assert!(env.send(&[env.take(2)], &[&taker]));
+    env.advance(1);
+    assert!(env.send(&[env.refund(3)], &[&maker]));
+
+    assert!(env.account(&env.escrow(1)).is_some());
+    assert!(env.account(&env.escrow(2)).is_none());
+    assert!(env.account(&env.escrow(3)).is_none());
+    assert_eq!(env.vault_balance(1), 100);
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_a), 200);
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_b), 2_000);
+    assert_eq!(env.balance(&maker.pubkey(), &env.mint_a), MAKER_BALANCE_A - 100);
+}
+
+#[test]
+fn test_escrow_survives_across_slots_until_taken() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+    let start = env.svm.get_sysvar::<Clock>().slot;
+
+    assert!(env.send(&[env.make(1, 2_000, 1_500)], &[&maker]));
+    env.advance(1_000);
+    assert_eq!(env.svm.get_sysvar::<Clock>().slot, start + 1_000);
+
+    // The seed stays taken while the escrow is open
+    assert!(!env.send(&[env.make(1, 1, 1)], &[&maker]));
+    env.advance(1_000);
+
+    assert!(env.send(&[env.take(1)], &[&taker]));
+    assert_eq!(env.balance(&taker.pubkey(), &env.mint_a), 1_500);
+    env.advance(1);
+
+    // Once closed, the same seed can be reused in a later slot
+    assert!(env.send(&[env.make(1, 500, 500)], &[&maker]));
+    assert_eq!(env.vault_balance(1), 500);
+}
+
+#[test]
+fn test_make_and_take_emit_events_with_the_terms() {
+    let mut env = Env::new();
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+    let terms_hash = Escrow::hash_terms(b"Agreement");
+    let external_ref = *b"PO-77\0\0\0\0\0\0\0\0\0\0\0";
+    let escrow = env.escrow(1);
+
+    let instructions = [with_terms(env.make(1, 2_000, 1_500), &terms_hash, &external_ref), env.take(1)];
+    let events = env.send_for_events(&instructions, &[&maker, &taker]).expect("transaction should succeed");
+
+    let expected_make: Vec<Vec<u8>> = vec![
+        MakeEvent::NAME.to_vec(),
+        escrow.to_bytes().to_vec(),
+        maker.pubkey().to_bytes().to_vec(),
+        1u64.to_le_bytes().to_vec(),
+        1_500u64.to_le_bytes().to_vec(),
+        2_000u64.to_le_bytes().to_vec(),
+        terms_hash.to_vec(),
+        external_ref.to_vec(),
+    ];
+    let expected_take: Vec<Vec<u8>> = vec![
+        TakeEvent::NAME.to_vec(),
+        escrow.to_bytes().to_vec(),
+        maker.pubkey().to_bytes().to_vec(),
+        taker.pubkey().to_bytes().to_vec(),
+        1_500u64.to_le_bytes().to_vec(),
+        2_000u64.to_le_bytes().to_vec(),
+        0u64.to_le_bytes().to_vec(),
+        terms_hash.to_vec(),
+        external_ref.to_vec(),
+    ];
+    assert_eq!(events, vec![expected_make, expected_take]);
+}